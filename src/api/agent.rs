@@ -0,0 +1,210 @@
+// api/agent.rs
+//! A tool-calling agent loop.
+//!
+//! [`Agent`] sends a chat request with its tools, runs the tool calls the model asks for
+//! through the registered handlers, feeds the results back, and repeats until the model
+//! answers without calling a tool. The `on_tool_call` and `on_tool_result` callbacks see
+//! every call while the loop runs, e.g. to log it, ask the user for confirmation, or stream
+//! tool activity to a UI.
+
+use super::chat::ChatApi;
+use crate::error::{Error, Result};
+use crate::models::tool::{Tool, ToolCall};
+use crate::types::chat::{ChatCompletionRequest, ChatCompletionResponse, Message};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+
+/// Model round trips allowed per run unless configured otherwise.
+const DEFAULT_MAX_STEPS: usize = 8;
+
+type ToolHandler = Arc<dyn Fn(ToolCall) -> BoxFuture<'static, Result<String>> + Send + Sync>;
+type OnToolCall = Arc<dyn Fn(ToolCall) -> BoxFuture<'static, ToolDecision> + Send + Sync>;
+type OnToolResult = Arc<dyn Fn(ToolCall, ToolOutput) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// Whether a requested tool call may run, as decided by the `on_tool_call` callback.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolDecision {
+    /// Run the tool.
+    Run,
+    /// Skip the tool and tell the model why.
+    Reject { reason: String },
+}
+
+impl ToolDecision {
+    /// Rejects the call with `reason`.
+    pub fn reject(reason: impl Into<String>) -> Self {
+        ToolDecision::Reject {
+            reason: reason.into(),
+        }
+    }
+}
+
+/// What a tool call produced; its text is sent back to the model as the tool result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolOutput {
+    /// The handler succeeded with this output.
+    Success(String),
+    /// The handler failed, or no handler is registered for the tool.
+    Failed(String),
+    /// The `on_tool_call` callback rejected the call for this reason.
+    Rejected(String),
+}
+
+impl ToolOutput {
+    /// The text sent to the model.
+    pub fn text(&self) -> String {
+        match self {
+            ToolOutput::Success(output) => output.clone(),
+            ToolOutput::Failed(error) => format!("Error: {}", error),
+            ToolOutput::Rejected(reason) => format!("Tool call rejected: {}", reason),
+        }
+    }
+}
+
+/// The outcome of an agent run.
+#[derive(Debug)]
+pub struct AgentRun {
+    /// The full transcript: the input messages, every assistant turn and tool result, and
+    /// the final reply.
+    pub messages: Vec<Message>,
+    /// The final response, which contains no tool calls.
+    pub response: ChatCompletionResponse,
+    /// The number of model round trips made.
+    pub steps: usize,
+}
+
+/// Runs chat completions in a loop, executing the tool calls the model requests.
+#[derive(Clone)]
+pub struct Agent {
+    chat: ChatApi,
+    model: String,
+    tools: Vec<Tool>,
+    handlers: HashMap<String, ToolHandler>,
+    max_steps: usize,
+    on_tool_call: Option<OnToolCall>,
+    on_tool_result: Option<OnToolResult>,
+}
+
+impl Agent {
+    /// Creates an agent sending requests to `model` through `chat`.
+    pub fn new(chat: ChatApi, model: impl Into<String>) -> Self {
+        Self {
+            chat,
+            model: model.into(),
+            tools: Vec::new(),
+            handlers: HashMap::new(),
+            max_steps: DEFAULT_MAX_STEPS,
+            on_tool_call: None,
+            on_tool_result: None,
+        }
+    }
+
+    /// Offers `tool` to the model and runs `handler` when the model calls it. The
+    /// handler's output, or its error message, is sent back as the tool result.
+    pub fn with_tool<F, Fut>(mut self, tool: Tool, handler: F) -> Self
+    where
+        F: Fn(ToolCall) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String>> + Send + 'static,
+    {
+        let Tool::Function { ref function } = tool;
+        self.handlers.insert(
+            function.name.clone(),
+            Arc::new(move |call| handler(call).boxed()),
+        );
+        self.tools.push(tool);
+        self
+    }
+
+    /// Sets how many model round trips a run may take (default 8, at least 1).
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps.max(1);
+        self
+    }
+
+    /// Calls `callback` before every tool call; returning [`ToolDecision::Reject`] skips
+    /// the call, e.g. when the user declines to confirm it.
+    pub fn on_tool_call<F, Fut>(mut self, callback: F) -> Self
+    where
+        F: Fn(ToolCall) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ToolDecision> + Send + 'static,
+    {
+        self.on_tool_call = Some(Arc::new(move |call| callback(call).boxed()));
+        self
+    }
+
+    /// Calls `callback` with the output of every tool call, including failed and rejected
+    /// ones, before it is sent back to the model.
+    pub fn on_tool_result<F, Fut>(mut self, callback: F) -> Self
+    where
+        F: Fn(ToolCall, ToolOutput) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.on_tool_result = Some(Arc::new(move |call, output| callback(call, output).boxed()));
+        self
+    }
+
+    /// Runs the loop starting from `messages` until the model replies without calling a
+    /// tool.
+    ///
+    /// Tool calls within one assistant turn run in order. Fails with
+    /// `Error::MaxStepsExceeded` if the model still calls tools after the last allowed step.
+    pub async fn run(&self, mut messages: Vec<Message>) -> Result<AgentRun> {
+        for step in 1..=self.max_steps {
+            let request = ChatCompletionRequest {
+                model: self.model.clone(),
+                messages: messages.clone(),
+                tools: (!self.tools.is_empty()).then(|| self.tools.clone()),
+                ..Default::default()
+            };
+            let response = self.chat.chat_completion(request).await?;
+            let Some(reply) = response
+                .choices
+                .first()
+                .map(|choice| choice.message.clone())
+            else {
+                return Err(Error::ApiError {
+                    code: 200,
+                    message: "Response contains no choices".into(),
+                    metadata: None,
+                });
+            };
+            let calls = reply.tool_calls.clone().unwrap_or_default();
+            messages.push(reply);
+            if calls.is_empty() {
+                return Ok(AgentRun {
+                    messages,
+                    response,
+                    steps: step,
+                });
+            }
+            for call in calls {
+                let output = self.execute(call.clone()).await;
+                if let Some(ref callback) = self.on_tool_result {
+                    callback(call.clone(), output.clone()).await;
+                }
+                messages.push(call.respond_with(output.text()));
+            }
+        }
+        Err(Error::MaxStepsExceeded {
+            steps: self.max_steps,
+        })
+    }
+
+    async fn execute(&self, call: ToolCall) -> ToolOutput {
+        if let Some(ref callback) = self.on_tool_call {
+            if let ToolDecision::Reject { reason } = callback(call.clone()).await {
+                return ToolOutput::Rejected(reason);
+            }
+        }
+        let Some(handler) = self.handlers.get(&call.function_call.name) else {
+            return ToolOutput::Failed(format!("unknown tool `{}`", call.function_call.name));
+        };
+        match handler(call).await {
+            Ok(output) => ToolOutput::Success(output),
+            Err(error) => ToolOutput::Failed(error.to_string()),
+        }
+    }
+}
@@ -1,16 +1,33 @@
-use crate::client::ClientConfig;
+use super::stream::{buffer_stream, ChatCompletionStream, StreamBuffer};
+use crate::audit::AuditContext;
+use crate::budget::CostBudget;
+use crate::client::{ClientConfig, PreparedRequest};
 use crate::error::{Error, Result};
-use crate::types::chat::{ChatCompletionChunk, ChatCompletionRequest, ChatCompletionResponse};
+use crate::models::structured::{
+    IncrementalSchemaValidator, JsonSchemaConfig, JsonSchemaDefinition, ResponseFormat,
+};
+use crate::scheduler::Priority;
+use crate::transport::HttpResponse;
+use crate::types::chat::{
+    ChatCompletionChunk, ChatCompletionRequest, ChatCompletionResponse, Content, Message,
+};
+use crate::types::envelope::ResponseEnvelope;
+use crate::types::metrics::CallMetrics;
+use crate::utils::tokens::estimate_tokens_for_chars;
 use async_stream::try_stream;
-use futures::stream::Stream;
+use bytes::Bytes;
+use futures::stream::BoxStream;
 use futures::StreamExt;
 use futures::TryStreamExt;
 use reqwest::Client;
+use serde::de::DeserializeOwned;
 use serde_json;
-use std::pin::Pin;
-use tokio_util::codec::{FramedRead, LinesCodec};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_util::codec::{FramedRead, LinesCodec, LinesCodecError};
 use tokio_util::io::StreamReader;
 
+#[derive(Clone)]
 pub struct ChatApi {
     pub client: Client,
     pub config: ClientConfig,
@@ -24,33 +41,215 @@ impl ChatApi {
         }
     }
 
+    /// Overrides the base URL for calls made through this handle only, e.g. to route
+    /// them through a regional gateway or a local OpenAI-compatible proxy.
+    /// The URL must include a trailing slash.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Result<Self> {
+        self.config.base_url = crate::client::parse_base_url(&base_url.into())?;
+        Ok(self)
+    }
+
+    /// Overrides the API key for calls made through this handle only, so one shared
+    /// client and connection pool can serve many tenants, each billed to their own key.
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.config.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Overrides the `Referer` attribution header for calls made through this handle
+    /// only, so a backend serving several products from one client can credit each app.
+    pub fn with_http_referer(mut self, referer: impl Into<String>) -> Self {
+        self.config.http_referer = Some(referer.into());
+        self
+    }
+
+    /// Overrides the `X-Title` attribution header for calls made through this handle only.
+    pub fn with_site_title(mut self, title: impl Into<String>) -> Self {
+        self.config.site_title = Some(title.into());
+        self
+    }
+
+    /// Enforces `budget` for calls made through this handle only, e.g. to cap the spend
+    /// of a single conversation session.
+    pub fn with_budget(mut self, budget: Arc<CostBudget>) -> Self {
+        self.config.budget = Some(budget);
+        self
+    }
+
+    /// Schedules calls made through this handle with `priority`, e.g. `Interactive` for
+    /// user-facing requests or `Background` for batch jobs. Only has an effect when the
+    /// client has a scheduler.
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.config.priority = priority;
+        self
+    }
+
+    /// Sets how streams opened through this handle behave when their consumer is slow.
+    pub fn with_stream_buffer(mut self, buffer: StreamBuffer) -> Self {
+        self.config.stream_buffer = buffer;
+        self
+    }
+
+    /// Attaches the label `key = value` to every request sent through this handle, e.g.
+    /// the feature or experiment it serves.
+    pub fn with_tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.tags.insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets how long streams opened through this handle may go without receiving any bytes
+    /// before failing with `Error::StreamStalled`.
+    pub fn with_stream_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.config.stream_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Returns the exact request `chat_completion` would send, without any network call.
+    pub fn dry_run(&self, request: &ChatCompletionRequest) -> Result<PreparedRequest> {
+        self.config.prepare_chat_request(request)
+    }
+
+    /// Continues completions truncated by the token limit made through this handle, up to
+    /// `max_continuations` follow-up requests each. See
+    /// [`chat_completion`](Self::chat_completion).
+    pub fn with_auto_continue(mut self, max_continuations: usize) -> Self {
+        self.config.max_continuations = max_continuations;
+        self
+    }
+
     /// Sends a chat completion request and returns a complete ChatCompletionResponse.
+    ///
+    /// With auto-continue enabled, a reply cut off by the token limit (`finish_reason` of
+    /// `length`) is continued by resending the request with the partial reply as an
+    /// assistant prefill, up to the configured number of times. The pieces are stitched into
+    /// one response carrying the last finish reason and the summed usage. If a follow-up
+    /// request fails, its error is returned.
     pub async fn chat_completion(
         &self,
         request: ChatCompletionRequest,
     ) -> Result<ChatCompletionResponse> {
-        // Build the complete URL for the chat completions endpoint.
-        let url = self
-            .config
-            .base_url
-            .join("chat/completions")
-            .map_err(|e| Error::ApiError {
-                code: 400,
-                message: format!("Invalid URL: {}", e),
-                metadata: None,
-            })?;
+        self.chat_completion_with_envelope(request)
+            .await
+            .map(ResponseEnvelope::into_data)
+    }
 
-        // Issue the POST request with appropriate headers and JSON body.
-        let response = self
-            .client
-            .post(url)
-            .headers(self.config.build_headers()?)
-            .json(&request)
-            .send()
-            .await?;
+    /// Like [`chat_completion`](Self::chat_completion), but also returns the HTTP status,
+    /// response headers, and raw body. When a truncated reply was auto-continued, these
+    /// are from the last request, while the data is the stitched response.
+    pub async fn chat_completion_with_envelope(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ResponseEnvelope<ChatCompletionResponse>> {
+        if self.config.max_continuations == 0 {
+            return self.complete_once(request).await;
+        }
+        let mut envelope = self.complete_once(request.clone()).await?;
+        for _ in 0..self.config.max_continuations {
+            let Some(partial) = truncated_reply(&envelope.data) else {
+                break;
+            };
+            tracing::debug!("completion truncated by the token limit, continuing");
+            let mut follow_up = request.clone();
+            follow_up.messages.push(Message::assistant(partial));
+            let next = self.complete_once(follow_up).await?;
+            let mut response = envelope.data;
+            stitch_continuation(&mut response, next.data);
+            envelope = ResponseEnvelope {
+                data: response,
+                ..next
+            };
+        }
+        Ok(envelope)
+    }
 
-        // Capture the HTTP status.
-        let status = response.status();
+    async fn complete_once(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ResponseEnvelope<ChatCompletionResponse>> {
+        // Apply defaults, validate, and build the URL, headers, and body.
+        let prepared = self.config.prepare_chat_request(&request)?;
+
+        // Fail fast if the request would exceed the spending limits, and reserve its
+        // estimated cost until the response arrives.
+        let reservation = match self.config.budget {
+            Some(ref budget) => Some(budget.reserve(&prepared)?),
+            None => None,
+        };
+
+        // Let the moderation hook veto the request before any tokens are billed.
+        crate::moderation::moderate(&self.config, &prepared).await?;
+
+        // Wait for room in the tokens-per-minute allowance.
+        if let Some(ref limiter) = self.config.token_limiter {
+            limiter.acquire_request(&prepared).await;
+        }
+
+        let audit = AuditContext::begin(&self.config, &prepared);
+        let started = Instant::now();
+        let mut result = self.send_chat_request(prepared).await;
+        if let Ok(ResponseEnvelope {
+            data: ref mut response,
+            ..
+        }) = result
+        {
+            response.metrics = Some(CallMetrics {
+                time_to_first_token: None,
+                duration: started.elapsed(),
+                completion_tokens: response.usage.as_ref().map(|usage| usage.completion_tokens),
+            });
+        }
+        if let (Some(reservation), Ok(ref envelope)) = (reservation, &result) {
+            reservation.settle(&envelope.data);
+        }
+        if let Some(audit) = audit {
+            let outcome = result.as_ref().map(|envelope| &envelope.data);
+            audit.finish(&self.config, outcome).await;
+        }
+        result
+    }
+
+    /// Sends a chat completion requesting JSON that matches `schema` and decodes it into `T`.
+    ///
+    /// The request's `response_format` is replaced with the schema in strict mode. Before
+    /// decoding, the reply is checked against the schema; a reply that is not valid JSON,
+    /// misses required properties, or does not fit `T` fails with
+    /// `Error::SchemaValidationError`.
+    pub async fn chat_completion_typed<T: DeserializeOwned>(
+        &self,
+        mut request: ChatCompletionRequest,
+        name: impl Into<String>,
+        schema: JsonSchemaDefinition,
+    ) -> Result<T> {
+        let mut validator = IncrementalSchemaValidator::new(schema.clone());
+        request.response_format = Some(ResponseFormat::json_schema(JsonSchemaConfig::new(
+            name, schema,
+        )));
+        let response = self.chat_completion(request).await?;
+        let choice = response
+            .choices
+            .first()
+            .ok_or_else(|| Error::SchemaValidationError("Response contains no choices".into()))?;
+        validator.push(&choice.message.content.to_string())?;
+        let value = validator.finish()?;
+        serde_json::from_value(value).map_err(|e| {
+            Error::SchemaValidationError(format!(
+                "Response does not match the expected type: {}",
+                e
+            ))
+        })
+    }
+
+    /// Sends a prepared chat request and decodes the response.
+    async fn send_chat_request(
+        &self,
+        prepared: PreparedRequest,
+    ) -> Result<ResponseEnvelope<ChatCompletionResponse>> {
+        // Issue the request through the configured transport.
+        let mut response = crate::transport::execute(&self.client, &self.config, prepared).await?;
+
+        // Capture the HTTP status and headers.
+        let status = response.status;
+        let headers = std::mem::take(&mut response.headers);
 
         // Retrieve the response body.
         let body = response.text().await?;
@@ -72,85 +271,341 @@ impl ChatApi {
             });
         }
 
-        // Deserialize the JSON response into ChatCompletionResponse.
-        serde_json::from_str::<ChatCompletionResponse>(&body).map_err(|e| Error::ApiError {
-            code: status.as_u16(),
-            message: format!("Failed to decode JSON: {}. Body was: {}", e, body),
-            metadata: None,
+        // Deserialize the JSON response into ChatCompletionResponse, surfacing errors that
+        // some providers report with a 200 status.
+        let value: serde_json::Value = crate::error::decode_json(status.as_u16(), &body)?;
+        if let Some(error) = error_payload(&value) {
+            return Err(error);
+        }
+        let response: ChatCompletionResponse = serde_path_to_error::deserialize(value)
+            .map_err(|e| Error::decode(status.as_u16(), e, &body))?;
+
+        // Reject tool calls this crate cannot dispatch.
+        validate_tool_calls(&response)?;
+        Ok(ResponseEnvelope {
+            status,
+            headers,
+            body,
+            data: response,
         })
     }
 
     /// Returns a stream for a chat completion request.
-    /// Each yielded item is a ChatCompletionChunk.
+    /// Each yielded item is a ChatCompletionChunk; see [`ChatStreamExt`](super::stream::ChatStreamExt)
+    /// for per-chunk transform hooks.
+    ///
+    /// A request that fails before its first chunk arrives (connection refused, a 429 or
+    /// 502, or an in-stream error event) is retried under the client's retry policy. Once
+    /// a chunk has been yielded the request is never replayed, and later errors end the
+    /// stream.
     pub fn chat_completion_stream(
         &self,
-        request: ChatCompletionRequest,
-    ) -> Pin<Box<dyn Stream<Item = Result<ChatCompletionChunk>> + Send>> {
+        mut request: ChatCompletionRequest,
+    ) -> ChatCompletionStream {
+        request.stream = Some(true);
         let client = self.client.clone();
         let config = self.config.clone();
 
         let stream = try_stream! {
-            // Build the URL for the chat completions endpoint.
-            let url = config.base_url.join("chat/completions").map_err(|e| Error::ApiError {
-                code: 400,
-                message: format!("Invalid URL: {}", e),
-                metadata: None,
-            })?;
+            // Apply defaults, validate, and serialize the request with streaming enabled.
+            let prepared = config.prepare_chat_request(&request)?;
 
-            // Serialize the request with streaming enabled.
-            let mut req_body = serde_json::to_value(&request).map_err(|e| Error::ApiError {
-                code: 500,
-                message: format!("Request serialization error: {}", e),
-                metadata: None,
-            })?;
-            req_body["stream"] = serde_json::Value::Bool(true);
-
-            // Issue the POST request with error-for-status checking.
-            let response = client
-                .post(url)
-                .headers(config.build_headers()?)
-                .json(&req_body)
-                .send()
-                .await?
-                .error_for_status()
-                .map_err(|e| {
-                    Error::ApiError {
-                        code: e.status().map(|s| s.as_u16()).unwrap_or(500),
-                        message: e.to_string(),
-                        metadata: None,
+            let mut reservation = match config.budget {
+                Some(ref budget) => Some(budget.reserve(&prepared)?),
+                None => None,
+            };
+            crate::moderation::moderate(&config, &prepared).await?;
+            if let Some(ref limiter) = config.token_limiter {
+                limiter.acquire_request(&prepared).await;
+            }
+
+            let estimate = reservation.as_ref().and_then(|reservation| reservation.estimate());
+            let mut audit = AuditContext::begin(&config, &prepared);
+            let mut id = String::new();
+            let mut chunks = stream_attempts(client, config.clone(), prepared);
+            while let Some(chunk) = chunks.next().await {
+                match chunk {
+                    Ok(chunk) => {
+                        // Streams report no usage, so they are charged their estimated
+                        // cost once generation has started.
+                        if let Some(reservation) = reservation.take() {
+                            reservation.commit();
+                        }
+                        id.clone_from(&chunk.id);
+                        yield chunk;
                     }
-                })?;
+                    Err(error) => {
+                        if let Some(audit) = audit.take() {
+                            let charged = estimate.filter(|_| reservation.is_none());
+                            audit.finish_with_id(&config, Err(&error), charged).await;
+                        }
+                        Err(error)?;
+                    }
+                }
+            }
+            // Record the stream once it ends; a stream that is dropped early goes unrecorded.
+            if let Some(audit) = audit {
+                let charged = estimate.filter(|_| reservation.is_none());
+                audit.finish_with_id(&config, Ok(&id), charged).await;
+            }
+        };
 
-            // Process the bytes stream as an asynchronous line stream.
-            let byte_stream = response.bytes_stream().map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e));
-            let stream_reader = StreamReader::new(byte_stream);
-            let mut lines = FramedRead::new(stream_reader, LinesCodec::new());
+        buffer_stream(Box::pin(stream), self.config.stream_buffer)
+    }
+}
 
-            while let Some(line_result) = lines.next().await {
-                let line = line_result.map_err(|e| Error::ApiError {
-                    code: 500,
-                    message: format!("LinesCodec error: {}", e),
+/// Sends a streaming request and yields its chunks.
+///
+/// Failures before the first chunk are retried under the retry policy; once a chunk has
+/// been yielded, errors are surfaced to the caller.
+fn stream_attempts(
+    client: Client,
+    config: ClientConfig,
+    prepared: PreparedRequest,
+) -> ChatCompletionStream {
+    Box::pin(try_stream! {
+        let mut timer = StreamTimer::start();
+        let mut attempt = 1;
+        'attempts: loop {
+            // Issue the request through the configured transport.
+            let response = crate::transport::execute(&client, &config, prepared.clone()).await?;
+            if !response.status.is_success() {
+                let code = response.status.as_u16();
+                Err(Error::ApiError {
+                    code,
+                    message: response.text().await?,
                     metadata: None,
                 })?;
-                if line.trim().is_empty() {
-                    continue;
-                }
-                if line.starts_with("data:") {
-                    let data_part = line.trim_start_matches("data:").trim();
-                    if data_part == "[DONE]" {
-                        break;
+                return;
+            }
+
+            let mut chunks = sse_chunks(response, config.stream_idle_timeout);
+            let mut received = false;
+            while let Some(chunk) = chunks.next().await {
+                match chunk {
+                    Ok(mut chunk) => {
+                        received = true;
+                        timer.observe(&mut chunk);
+                        yield chunk;
                     }
-                    match serde_json::from_str::<ChatCompletionChunk>(data_part) {
-                        Ok(chunk) => yield chunk,
-                        Err(_err) => continue,
+                    Err(error) if !received => {
+                        let Some(delay) = config.retry_delay(&error, attempt) else {
+                            Err(error)?;
+                            return;
+                        };
+                        tracing::debug!(
+                            attempt,
+                            "stream failed before the first chunk, retrying in {:?}: {}",
+                            delay,
+                            error
+                        );
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                        continue 'attempts;
                     }
-                } else if line.starts_with(":") {
-                    // Ignore SSE comment lines.
+                    Err(error) => Err(error)?,
+                }
+            }
+            break;
+        }
+    })
+}
+
+/// Decodes the Server-Sent Events body of a streaming response into chunks.
+///
+/// Comment lines and payloads that are not chunks are skipped; an `error` payload, which
+/// OpenRouter sends when generation fails after the response has started, ends the stream
+/// with an `Error::ApiError`. With an `idle_timeout`, the stream fails with
+/// `Error::StreamStalled` once no bytes have arrived for that long.
+fn sse_chunks(response: HttpResponse, idle_timeout: Option<Duration>) -> ChatCompletionStream {
+    let body = match idle_timeout {
+        Some(idle) => stall_guard(response.body, idle),
+        None => response.body,
+    };
+    let byte_stream = body.map_err(std::io::Error::other);
+    let stream_reader = StreamReader::new(byte_stream);
+    let mut lines = FramedRead::new(stream_reader, LinesCodec::new());
+
+    Box::pin(try_stream! {
+        while let Some(line_result) = lines.next().await {
+            let line = line_result.map_err(line_error)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if line.starts_with("data:") {
+                let data_part = line.trim_start_matches("data:").trim();
+                if data_part == "[DONE]" {
+                    break;
+                }
+                let Ok(value) = serde_json::from_str::<serde_json::Value>(data_part) else {
                     continue;
+                };
+                if let Some(error) = error_payload(&value) {
+                    Err(error)?;
                 }
+                if let Ok(chunk) = serde_json::from_value::<ChatCompletionChunk>(value) {
+                    yield chunk;
+                }
+            } else if line.starts_with(":") {
+                // Ignore SSE comment lines.
+                continue;
             }
-        };
+        }
+    })
+}
+
+/// Measures a stream from the moment its request is sent, across retries.
+struct StreamTimer {
+    started: Instant,
+    first_token: Option<Duration>,
+    chars: usize,
+}
+
+impl StreamTimer {
+    fn start() -> Self {
+        Self {
+            started: Instant::now(),
+            first_token: None,
+            chars: 0,
+        }
+    }
 
-        Box::pin(stream)
+    /// Attaches the metrics of the stream so far to `chunk`.
+    fn observe(&mut self, chunk: &mut ChatCompletionChunk) {
+        let elapsed = self.started.elapsed();
+        for choice in &chunk.choices {
+            let chars = choice
+                .delta
+                .content
+                .as_deref()
+                .map_or(0, |c| c.chars().count());
+            if self.first_token.is_none() && (chars > 0 || choice.delta.tool_calls.is_some()) {
+                self.first_token = Some(elapsed);
+            }
+            self.chars += chars;
+        }
+        let tokens = estimate_tokens_for_chars(self.chars);
+        chunk.metrics = Some(CallMetrics {
+            time_to_first_token: self.first_token,
+            duration: elapsed,
+            completion_tokens: Some(u32::try_from(tokens).unwrap_or(u32::MAX)),
+        });
+    }
+}
+
+/// Fails a response body with `Error::StreamStalled` if no bytes arrive for `idle`.
+fn stall_guard(
+    mut body: BoxStream<'static, Result<Bytes>>,
+    idle: Duration,
+) -> BoxStream<'static, Result<Bytes>> {
+    Box::pin(async_stream::stream! {
+        loop {
+            match tokio::time::timeout(idle, body.next()).await {
+                Ok(Some(bytes)) => yield bytes,
+                Ok(None) => break,
+                Err(_) => {
+                    yield Err(Error::StreamStalled { idle });
+                    break;
+                }
+            }
+        }
+    })
+}
+
+/// Converts a line decoding failure back into the crate error that caused it, if any.
+fn line_error(error: LinesCodecError) -> Error {
+    let message = error.to_string();
+    if let LinesCodecError::Io(io) = error {
+        if let Some(inner) = io.into_inner() {
+            if let Ok(error) = inner.downcast::<Error>() {
+                return *error;
+            }
+        }
+    }
+    Error::ApiError {
+        code: 500,
+        message: format!("LinesCodec error: {}", message),
+        metadata: None,
+    }
+}
+
+/// Checks that every tool call in `response` is a function call.
+pub(crate) fn validate_tool_calls(response: &ChatCompletionResponse) -> Result<()> {
+    for choice in &response.choices {
+        for call in choice.message.tool_calls.iter().flatten() {
+            if call.kind != "function" {
+                return Err(Error::SchemaValidationError(format!(
+                    "Invalid tool call kind: {}. Expected 'function'",
+                    call.kind
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses an `{"error": {...}}` payload, sent in a stream or with a 200 status, into an
+/// API error.
+fn error_payload(value: &serde_json::Value) -> Option<Error> {
+    let error = value.get("error")?;
+    let code = error["code"]
+        .as_u64()
+        .and_then(|code| u16::try_from(code).ok())
+        .unwrap_or(500);
+    Some(Error::ApiError {
+        code,
+        message: error.to_string(),
+        metadata: error.get("metadata").cloned(),
+    })
+}
+
+/// Returns the text of a reply cut off by the token limit, or `None` if it finished.
+fn truncated_reply(response: &ChatCompletionResponse) -> Option<String> {
+    let choice = response.choices.first()?;
+    let calls_tools = choice
+        .message
+        .tool_calls
+        .as_ref()
+        .is_some_and(|calls| !calls.is_empty());
+    (choice.finish_reason.as_deref() == Some("length") && !calls_tools)
+        .then(|| choice.message.content.to_text())
+}
+
+/// Appends the reply of a continuation request to `response`.
+fn stitch_continuation(response: &mut ChatCompletionResponse, next: ChatCompletionResponse) {
+    let (Some(choice), Some(next_choice)) = (
+        response.choices.first_mut(),
+        next.choices.into_iter().next(),
+    ) else {
+        return;
+    };
+    let text = choice.message.content.to_text() + &next_choice.message.content.to_text();
+    choice.message.content = Content::Text(text);
+    if let Some(annotations) = next_choice.message.annotations {
+        choice
+            .message
+            .annotations
+            .get_or_insert_with(Vec::new)
+            .extend(annotations);
+    }
+    choice.finish_reason = next_choice.finish_reason;
+    choice.native_finish_reason = next_choice.native_finish_reason;
+
+    response.usage = match (response.usage.take(), next.usage) {
+        (Some(mut usage), Some(next)) => {
+            usage.prompt_tokens += next.prompt_tokens;
+            usage.completion_tokens += next.completion_tokens;
+            usage.total_tokens += next.total_tokens;
+            usage.cost = match (usage.cost, next.cost) {
+                (Some(cost), Some(next)) => Some(cost + next),
+                (cost, next) => cost.or(next),
+            };
+            Some(usage)
+        }
+        (usage, next) => usage.or(next),
+    };
+    if let (Some(ref mut metrics), Some(next)) = (&mut response.metrics, next.metrics) {
+        metrics.duration += next.duration;
+        metrics.completion_tokens = response.usage.as_ref().map(|usage| usage.completion_tokens);
     }
 }
@@ -1,5 +1,6 @@
 // api/completion.rs
-use crate::client::ClientConfig;
+use crate::audit::AuditContext;
+use crate::client::{ClientConfig, PreparedRequest};
 use crate::error::{Error, Result};
 use crate::types::completion::{CompletionRequest, CompletionResponse};
 use reqwest::Client;
@@ -19,31 +20,69 @@ impl CompletionApi {
         }
     }
 
+    /// Overrides the base URL for calls made through this handle only, e.g. to route
+    /// them through a regional gateway or a local OpenAI-compatible proxy.
+    /// The URL must include a trailing slash.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Result<Self> {
+        self.config.base_url = crate::client::parse_base_url(&base_url.into())?;
+        Ok(self)
+    }
+
+    /// Overrides the API key for calls made through this handle only, so one shared
+    /// client and connection pool can serve many tenants, each billed to their own key.
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.config.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Overrides the `Referer` attribution header for calls made through this handle
+    /// only, so a backend serving several products from one client can credit each app.
+    pub fn with_http_referer(mut self, referer: impl Into<String>) -> Self {
+        self.config.http_referer = Some(referer.into());
+        self
+    }
+
+    /// Overrides the `X-Title` attribution header for calls made through this handle only.
+    pub fn with_site_title(mut self, title: impl Into<String>) -> Self {
+        self.config.site_title = Some(title.into());
+        self
+    }
+
     /// Calls the completions endpoint. The request payload includes at minimum the `model` and `prompt` fields,
     /// along with any additional generation parameters (temperature, top_p, and so on).
-    pub async fn text_completion(&self, request: CompletionRequest) -> Result<CompletionResponse> {
-        // Build the URL.
-        let url = self
-            .config
-            .base_url
-            .join("completions")
-            .map_err(|e| Error::ApiError {
-                code: 400,
-                message: format!("Invalid URL for completions: {}", e),
-                metadata: None,
-            })?;
+    pub async fn text_completion(
+        &self,
+        mut request: CompletionRequest,
+    ) -> Result<CompletionResponse> {
+        self.config.apply_completion_defaults(&mut request);
+        request.sampling.validate()?;
+
+        // Build the URL, headers, and body.
+        let prepared =
+            self.config
+                .prepare_request(reqwest::Method::POST, "completions", &request)?;
+
+        let audit = AuditContext::begin(&self.config, &prepared);
+        let result = self.send_completion_request(prepared).await;
+        if let Some(audit) = audit {
+            let outcome = match result {
+                Ok(ref response) => Ok(response.id.as_deref().unwrap_or_default()),
+                Err(ref error) => Err(error),
+            };
+            audit.finish_with_id(&self.config, outcome, None).await;
+        }
+        result
+    }
 
-        // Send the POST request.
-        let response = self
-            .client
-            .post(url)
-            .headers(self.config.build_headers()?)
-            .json(&request)
-            .send()
-            .await?;
+    async fn send_completion_request(
+        &self,
+        prepared: PreparedRequest,
+    ) -> Result<CompletionResponse> {
+        // Send the request through the configured transport.
+        let response = crate::transport::execute(&self.client, &self.config, prepared).await?;
 
         // Capture the status code before consuming the response body.
-        let status = response.status();
+        let status = response.status;
 
         // Get the response body.
         let body = response.text().await?;
@@ -66,10 +105,6 @@ impl CompletionApi {
         }
 
         // Deserialize the body.
-        serde_json::from_str::<CompletionResponse>(&body).map_err(|e| Error::ApiError {
-            code: status.as_u16(),
-            message: format!("Failed to decode JSON: {}. Body was: {}", e, body),
-            metadata: None,
-        })
+        crate::error::decode_json::<CompletionResponse>(status.as_u16(), &body)
     }
 }
@@ -0,0 +1,99 @@
+// api/generation.rs
+use crate::client::ClientConfig;
+use crate::error::{Error, Result};
+use crate::types::generation::{Generation, GenerationResponse};
+use reqwest::Client;
+use std::time::{Duration, Instant};
+
+/// Delay before the first poll of a generation record that is not available yet.
+const INITIAL_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Upper bound on the delay between polls.
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// API endpoint for generation stats.
+#[derive(Clone)]
+pub struct GenerationApi {
+    pub client: Client,
+    pub config: ClientConfig,
+}
+
+impl GenerationApi {
+    /// Creates a new GenerationApi with the given reqwest client and configuration.
+    pub fn new(client: Client, config: &ClientConfig) -> Self {
+        Self {
+            client,
+            config: config.clone(),
+        }
+    }
+
+    /// Overrides the base URL for calls made through this handle only, e.g. to route
+    /// them through a regional gateway or a local OpenAI-compatible proxy.
+    /// The URL must include a trailing slash.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Result<Self> {
+        self.config.base_url = crate::client::parse_base_url(&base_url.into())?;
+        Ok(self)
+    }
+
+    /// Overrides the API key for calls made through this handle only, so one shared
+    /// client and connection pool can serve many tenants, each billed to their own key.
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.config.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Fetches the stats of the generation with the given id, once.
+    ///
+    /// The record usually becomes available a few seconds after the completion; until then
+    /// this fails with a 404 `Error::ApiError`. Use [`wait`](Self::wait) to poll for it.
+    pub async fn get(&self, id: &str) -> Result<Generation> {
+        let mut prepared = self.config.prepare_request(
+            reqwest::Method::GET,
+            "generation",
+            &serde_json::Value::Null,
+        )?;
+        prepared.url.query_pairs_mut().append_pair("id", id);
+
+        let response = crate::transport::execute(&self.client, &self.config, prepared).await?;
+        let status = response.status;
+        let body = response.text().await?;
+
+        if !status.is_success() {
+            return Err(Error::ApiError {
+                code: status.as_u16(),
+                message: body,
+                metadata: None,
+            });
+        }
+
+        crate::error::decode_json::<GenerationResponse>(status.as_u16(), &body)
+            .map(|response| response.data)
+    }
+
+    /// Polls for the stats of the generation with the given id until they are available
+    /// or `deadline` has passed.
+    ///
+    /// Only "not found" responses are retried, with a delay growing from 250ms to 2s; any
+    /// other error is returned immediately. Once the deadline has passed, the last
+    /// "not found" error is returned.
+    pub async fn wait(&self, id: &str, deadline: Duration) -> Result<Generation> {
+        let started = Instant::now();
+        let mut interval = INITIAL_POLL_INTERVAL;
+        loop {
+            let error = match self.get(id).await {
+                Err(error) if error.status_code() == Some(404) => error,
+                result => return result,
+            };
+            let remaining = deadline.saturating_sub(started.elapsed());
+            if remaining.is_zero() {
+                return Err(error);
+            }
+            tracing::debug!(
+                "generation {} not available yet, polling in {:?}",
+                id,
+                interval
+            );
+            tokio::time::sleep(interval.min(remaining)).await;
+            interval = (interval * 2).min(MAX_POLL_INTERVAL);
+        }
+    }
+}
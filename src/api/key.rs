@@ -0,0 +1,191 @@
+// api/key.rs
+//! The API key endpoint and a monitor warning about its approaching credit limit.
+//!
+//! [`KeyMonitor`] polls the key's usage and fires an alert the first time usage crosses
+//! each configured fraction of the limit, so services can page someone or shed load
+//! before requests start failing with 402 Payment Required.
+
+use crate::client::ClientConfig;
+use crate::error::{Error, Result};
+use crate::types::key::{KeyInfo, KeyInfoResponse};
+use reqwest::Client;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How often a spawned monitor checks the key unless configured otherwise.
+const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// Usage fractions alerted on unless configured otherwise.
+const DEFAULT_THRESHOLDS: [f64; 3] = [0.75, 0.9, 0.95];
+
+type OnAlert = Arc<dyn Fn(&KeyAlert) + Send + Sync>;
+
+/// API endpoint for the usage and limits of the current API key.
+#[derive(Clone)]
+pub struct KeyApi {
+    pub client: Client,
+    pub config: ClientConfig,
+}
+
+impl KeyApi {
+    /// Creates a new KeyApi with the given reqwest client and configuration.
+    pub fn new(client: Client, config: &ClientConfig) -> Self {
+        Self {
+            client,
+            config: config.clone(),
+        }
+    }
+
+    /// Overrides the API key for calls made through this handle only, e.g. to monitor a
+    /// tenant's key from a shared client.
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.config.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Fetches the usage and limits of the API key.
+    pub async fn get(&self) -> Result<KeyInfo> {
+        let prepared =
+            self.config
+                .prepare_request(reqwest::Method::GET, "key", &serde_json::Value::Null)?;
+        let response = crate::transport::execute(&self.client, &self.config, prepared).await?;
+        let status = response.status;
+        let body = response.text().await?;
+
+        if !status.is_success() {
+            return Err(Error::ApiError {
+                code: status.as_u16(),
+                message: body,
+                metadata: None,
+            });
+        }
+
+        crate::error::decode_json::<KeyInfoResponse>(status.as_u16(), &body)
+            .map(|response| response.data)
+    }
+
+    /// Creates a monitor checking this key against the default thresholds.
+    pub fn monitor(self) -> KeyMonitor {
+        KeyMonitor::new(self)
+    }
+}
+
+/// Raised when a key's usage first crosses one of the monitor's thresholds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyAlert {
+    /// The fraction of the limit that was crossed, e.g. 0.9.
+    pub threshold: f64,
+    /// The fraction of the limit used when the alert fired.
+    pub usage_fraction: f64,
+    /// The key's usage and limits when the alert fired.
+    pub info: KeyInfo,
+}
+
+/// Periodically checks a key's usage and alerts when it crosses configured fractions of
+/// the limit.
+///
+/// Each threshold fires once; if usage falls back below it, e.g. after the limit is
+/// raised, it fires again on the next crossing. Keys without a limit never alert.
+#[derive(Clone)]
+pub struct KeyMonitor {
+    api: KeyApi,
+    thresholds: Vec<f64>,
+    interval: Duration,
+    on_alert: Option<OnAlert>,
+    crossed: Arc<Mutex<Option<f64>>>,
+}
+
+impl KeyMonitor {
+    /// Creates a monitor alerting at 75%, 90%, and 95% of the limit, checking every minute
+    /// when spawned.
+    pub fn new(api: KeyApi) -> Self {
+        Self {
+            api,
+            thresholds: DEFAULT_THRESHOLDS.to_vec(),
+            interval: DEFAULT_CHECK_INTERVAL,
+            on_alert: None,
+            crossed: Arc::default(),
+        }
+    }
+
+    /// Alerts when usage crosses each of `thresholds`, given as fractions of the limit.
+    /// Values outside `0.0..=1.0` are ignored.
+    pub fn with_thresholds(mut self, thresholds: impl IntoIterator<Item = f64>) -> Self {
+        self.thresholds = thresholds
+            .into_iter()
+            .filter(|threshold| (0.0..=1.0).contains(threshold))
+            .collect();
+        self.thresholds.sort_by(f64::total_cmp);
+        self.thresholds.dedup();
+        self
+    }
+
+    /// Sets how often a spawned monitor checks the key (default 60s).
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Calls `callback` with every alert, in addition to logging it as a warning.
+    pub fn on_alert<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&KeyAlert) + Send + Sync + 'static,
+    {
+        self.on_alert = Some(Arc::new(callback));
+        self
+    }
+
+    /// Checks the key once, returning the alert fired, if any. When usage has crossed
+    /// several new thresholds since the last check, a single alert for the highest is
+    /// fired.
+    pub async fn check(&self) -> Result<Option<KeyAlert>> {
+        let info = self.api.get().await?;
+        let Some(usage_fraction) = info.usage_fraction() else {
+            return Ok(None);
+        };
+        let reached = self
+            .thresholds
+            .iter()
+            .copied()
+            .rfind(|&threshold| usage_fraction >= threshold);
+        {
+            let mut crossed = self.crossed.lock().unwrap();
+            if reached.unwrap_or(f64::MIN) <= crossed.unwrap_or(f64::MIN) {
+                *crossed = reached;
+                return Ok(None);
+            }
+            *crossed = reached;
+        }
+        let Some(threshold) = reached else {
+            return Ok(None);
+        };
+        let alert = KeyAlert {
+            threshold,
+            usage_fraction,
+            info,
+        };
+        tracing::warn!(
+            "API key {} has used {:.0}% of its credit limit ({} of {:?} credits)",
+            alert.info.label.as_deref().unwrap_or("(unlabeled)"),
+            usage_fraction * 100.0,
+            alert.info.usage,
+            alert.info.limit,
+        );
+        if let Some(ref callback) = self.on_alert {
+            callback(&alert);
+        }
+        Ok(Some(alert))
+    }
+
+    /// Checks the key every interval on a background task until the handle is aborted.
+    /// Failed checks are logged and retried at the next interval.
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if let Err(error) = self.check().await {
+                    tracing::warn!("API key limit check failed: {}", error);
+                }
+                tokio::time::sleep(self.interval).await;
+            }
+        })
+    }
+}
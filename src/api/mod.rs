@@ -1,4 +1,11 @@
+pub mod agent;
 pub mod chat;
 pub mod completion;
+pub mod generation;
+pub mod key;
+pub mod models;
 pub mod request;
+#[cfg(feature = "tower")]
+pub mod service;
+pub mod stream;
 pub mod web_search;
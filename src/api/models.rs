@@ -0,0 +1,125 @@
+// api/models.rs
+use crate::catalog::CacheLookup;
+use crate::client::ClientConfig;
+use crate::error::{Error, Result};
+use crate::types::models::{Model, ModelsResponse};
+use reqwest::Client;
+
+/// API endpoint for the models catalog.
+#[derive(Clone)]
+pub struct ModelsApi {
+    pub client: Client,
+    pub config: ClientConfig,
+}
+
+impl ModelsApi {
+    /// Creates a new ModelsApi with the given reqwest client and configuration.
+    pub fn new(client: Client, config: &ClientConfig) -> Self {
+        Self {
+            client,
+            config: config.clone(),
+        }
+    }
+
+    /// Overrides the base URL for calls made through this handle only, e.g. to route
+    /// them through a regional gateway or a local OpenAI-compatible proxy.
+    /// The URL must include a trailing slash.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Result<Self> {
+        self.config.base_url = crate::client::parse_base_url(&base_url.into())?;
+        Ok(self)
+    }
+
+    /// Overrides the API key for calls made through this handle only, so one shared
+    /// client and connection pool can serve many tenants, each billed to their own key.
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.config.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Returns the list of available models.
+    ///
+    /// With a model cache configured, a fresh cached catalog is returned without a network
+    /// call; an expired one is either refetched or, with background refresh, returned while
+    /// a refresh runs in the background.
+    ///
+    /// With the `offline-catalog` feature, the embedded snapshot is returned if the endpoint
+    /// is unreachable or fails with a server error.
+    pub async fn list(&self) -> Result<ModelsResponse> {
+        let result = self.cached_or_fetch().await;
+        #[cfg(feature = "offline-catalog")]
+        if let Err(ref error) = result {
+            if crate::catalog::is_unreachable(error) {
+                tracing::debug!(
+                    "models endpoint unreachable, using the embedded snapshot: {}",
+                    error
+                );
+                return Ok(crate::catalog::snapshot().clone());
+            }
+        }
+        result
+    }
+
+    async fn cached_or_fetch(&self) -> Result<ModelsResponse> {
+        let Some(ref cache) = self.config.model_cache else {
+            return self.fetch().await;
+        };
+        match cache.lookup() {
+            CacheLookup::Fresh(catalog) => Ok((*catalog).clone()),
+            CacheLookup::Stale(catalog) => {
+                if cache.begin_refresh() {
+                    let api = self.clone();
+                    let cache = cache.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = api.refresh().await {
+                            tracing::debug!(
+                                "background refresh of the models catalog failed: {}",
+                                e
+                            );
+                        }
+                        cache.end_refresh();
+                    });
+                }
+                Ok((*catalog).clone())
+            }
+            CacheLookup::Miss => self.refresh().await,
+        }
+    }
+
+    /// Fetches the catalog from the network, bypassing and then updating the model cache.
+    pub async fn refresh(&self) -> Result<ModelsResponse> {
+        let catalog = self.fetch().await?;
+        if let Some(ref cache) = self.config.model_cache {
+            cache.store(catalog.clone());
+        }
+        Ok(catalog)
+    }
+
+    async fn fetch(&self) -> Result<ModelsResponse> {
+        let prepared = self.config.prepare_request(
+            reqwest::Method::GET,
+            "models",
+            &serde_json::Value::Null,
+        )?;
+
+        let response = crate::transport::execute(&self.client, &self.config, prepared).await?;
+        let status = response.status;
+        let body = response.text().await?;
+
+        if !status.is_success() {
+            return Err(Error::ApiError {
+                code: status.as_u16(),
+                message: body,
+                metadata: None,
+            });
+        }
+
+        crate::error::decode_json::<ModelsResponse>(status.as_u16(), &body)
+    }
+
+    /// Fetches the catalog and returns the models matching `query`, best match first.
+    /// See [`ModelsResponse::search`] for how matches are ranked.
+    pub async fn search(&self, query: &str) -> Result<Vec<Model>> {
+        let catalog = self.list().await?;
+        Ok(catalog.search(query).into_iter().cloned().collect())
+    }
+}
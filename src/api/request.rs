@@ -11,17 +11,31 @@
      This enables the model to suggest or invoke external functions via the API.
 
    - **Provider Preferences:** Clients can attach routing options using our first‑class provider preferences.
+
+   The builder is type‑state: `build()` is only available once a model and at least one message
+   have been supplied, so an incomplete payload is a compile error rather than an API error.
+   Optional generation parameters are range‑checked by their setters.
+
+   ```ignore
+   let payload = RequestBuilder::new(json!({}))
+       .model("openai/gpt-4o")
+       .message(Message::user("Hello"))
+       .with_temperature(0.7)?
+       .build();
+   ```
 */
 
+use crate::error::{Error, Result};
+use crate::models::parameters::SamplingParams;
 use crate::models::structured::JsonSchemaConfig;
 use crate::models::tool::Tool;
-use crate::types::chat::Message;
+use crate::types::chat::{Message, Modality};
 use serde::Serialize;
 use serde_json::Value;
+use std::marker::PhantomData;
 
 /// Representation for enabling structured outputs in the request payload.
 #[derive(Debug, Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
 pub struct ResponseFormatConfig {
     /// Fixed string indicating the type of response format.
     #[serde(rename = "type")]
@@ -37,9 +51,9 @@ pub struct ResponseFormatConfig {
 /// - The messages to send.
 /// - Optionally, structured output configuration.
 /// - Optionally, tool calling instructions.
+/// - Optionally, sampling parameters, max_tokens, and output modalities.
 /// - Any extra parameters merged using flattening.
 #[derive(Debug, Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
 pub struct RequestPayload<T: Serialize> {
     /// The model ID to use.
     pub model: String,
@@ -51,49 +65,115 @@ pub struct RequestPayload<T: Serialize> {
     /// Optional tool calling instructions.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<Tool>>,
+    /// Optional sampling parameters.
+    #[serde(flatten)]
+    pub sampling: SamplingParams,
+    /// Optional maximum number of tokens to generate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Optional output kinds to generate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modalities: Option<Vec<Modality>>,
     /// Additional parameters merged into the request payload.
     #[serde(flatten)]
     pub extra_params: T,
 }
 
+// Type‑state markers.
+/// The builder has no model yet.
+pub struct NoModel;
+/// The builder has a model.
+pub struct HasModel;
+/// The builder has no messages yet.
+pub struct NoMessages;
+/// The builder has at least one message.
+pub struct HasMessages;
+
 /// A unified request builder for non‑interactive endpoints.
 ///
+/// The `Model` and `Messages` type parameters track whether the required fields have been
+/// supplied; [build](RequestBuilder::build) only exists once both have.
+///
 /// This builder supports configuration for:
 /// - **Structured Outputs:** By invoking [with_structured_output], clients can enable a specific JSON Schema for responses.
 /// - **Tool Calling:** By invoking [with_tools], clients can supply a list of callable tools.
 /// - **Provider Preferences:** By invoking [with_provider_preferences], clients can configure provider routing.
-pub struct RequestBuilder<T: Serialize> {
+pub struct RequestBuilder<T: Serialize, Model = HasModel, Messages = HasMessages> {
     model: String,
     messages: Vec<Message>,
     extra_params: T,
     structured_output: Option<ResponseFormatConfig>,
     /// Optional list of tools for tool calling.
     tools: Option<Vec<Tool>>,
+    sampling: SamplingParams,
+    max_tokens: Option<u32>,
+    modalities: Option<Vec<Modality>>,
     /// Whether to perform JSON Schema validation on the response.
     pub validate_structured: bool,
     /// If true, fallback to an unstructured response on validation failure.
     pub fallback_on_failure: bool,
+    _state: PhantomData<(Model, Messages)>,
 }
 
-impl<T: Serialize> RequestBuilder<T> {
-    /// Creates a new request builder.
+impl<T: Serialize> RequestBuilder<T, NoModel, NoMessages> {
+    /// Creates a new request builder without a model or messages.
     ///
     /// # Parameters
     ///
-    /// - `model`: The model ID to be used for the request.
-    /// - `messages`: A vector of [Message] objects representing the conversation.
     /// - `extra_params`: Any additional parameters to include in the payload.
-    pub fn new(model: impl Into<String>, messages: Vec<Message>, extra_params: T) -> Self {
+    pub fn new(extra_params: T) -> Self {
         Self {
-            model: model.into(),
-            messages,
+            model: String::new(),
+            messages: Vec::new(),
             extra_params,
             structured_output: None,
             tools: None,
+            sampling: SamplingParams::default(),
+            max_tokens: None,
+            modalities: None,
             validate_structured: true,
             fallback_on_failure: false,
+            _state: PhantomData,
         }
     }
+}
+
+impl<T: Serialize, Model, Messages> RequestBuilder<T, Model, Messages> {
+    /// Moves the builder into a new type state without touching its fields.
+    fn transition<NewModel, NewMessages>(self) -> RequestBuilder<T, NewModel, NewMessages> {
+        RequestBuilder {
+            model: self.model,
+            messages: self.messages,
+            extra_params: self.extra_params,
+            structured_output: self.structured_output,
+            tools: self.tools,
+            sampling: self.sampling,
+            max_tokens: self.max_tokens,
+            modalities: self.modalities,
+            validate_structured: self.validate_structured,
+            fallback_on_failure: self.fallback_on_failure,
+            _state: PhantomData,
+        }
+    }
+
+    /// Sets the model ID to be used for the request.
+    pub fn model(mut self, model: impl Into<String>) -> RequestBuilder<T, HasModel, Messages> {
+        self.model = model.into();
+        self.transition()
+    }
+
+    /// Appends a message to the conversation.
+    pub fn message(mut self, message: Message) -> RequestBuilder<T, Model, HasMessages> {
+        self.messages.push(message);
+        self.transition()
+    }
+
+    /// Appends several messages to the conversation. This does not satisfy the
+    /// message requirement on its own, since the iterator may be empty.
+    pub fn messages(mut self, messages: impl IntoIterator<Item = Message>) -> Self {
+        self.messages.extend(messages);
+        self
+    }
 
     /// Enables structured output support.
     ///
@@ -127,44 +207,97 @@ impl<T: Serialize> RequestBuilder<T> {
         self
     }
 
+    /// Sets the sampling parameters, failing if any is out of range.
+    pub fn with_sampling(mut self, sampling: SamplingParams) -> Result<Self> {
+        sampling.validate()?;
+        self.sampling = sampling;
+        Ok(self)
+    }
+
+    /// Sets the sampling temperature, which must be between 0 and 2.
+    pub fn with_temperature(self, temperature: f64) -> Result<Self> {
+        let sampling = self.sampling.clone().with_temperature(temperature);
+        self.with_sampling(sampling)
+    }
+
+    /// Sets the nucleus sampling probability mass, which must be in (0, 1].
+    pub fn with_top_p(self, top_p: f64) -> Result<Self> {
+        let sampling = self.sampling.clone().with_top_p(top_p);
+        self.with_sampling(sampling)
+    }
+
+    /// Sets the number of most likely tokens to sample from, which must be positive.
+    pub fn with_top_k(self, top_k: u32) -> Result<Self> {
+        let sampling = self.sampling.clone().with_top_k(top_k);
+        self.with_sampling(sampling)
+    }
+
+    /// Sets the maximum number of tokens to generate, which must be positive.
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Result<Self> {
+        if max_tokens == 0 {
+            return Err(Error::InvalidRequest(
+                "max_tokens must be greater than 0".into(),
+            ));
+        }
+        self.max_tokens = Some(max_tokens);
+        Ok(self)
+    }
+
+    /// Sets the output kinds to generate, failing if `modalities` is empty.
+    pub fn with_modalities(
+        mut self,
+        modalities: impl IntoIterator<Item = Modality>,
+    ) -> Result<Self> {
+        let modalities: Vec<Modality> = modalities.into_iter().collect();
+        if modalities.is_empty() {
+            return Err(Error::InvalidRequest(
+                "At least one modality is required".into(),
+            ));
+        }
+        self.modalities = Some(modalities);
+        Ok(self)
+    }
+}
+
+impl<T: Serialize> RequestBuilder<T, HasModel, HasMessages> {
     /// Consumes the builder and returns the complete request payload.
     ///
     /// # Returns
     ///
     /// A [RequestPayload] that includes the model, messages, structured output settings,
-    /// tool calling instructions, and extra parameters.
+    /// tool calling instructions, generation parameters, and extra parameters.
     pub fn build(self) -> RequestPayload<T> {
         RequestPayload {
             model: self.model,
             messages: self.messages,
             response_format: self.structured_output,
             tools: self.tools,
+            sampling: self.sampling,
+            max_tokens: self.max_tokens,
+            modalities: self.modalities,
             extra_params: self.extra_params,
         }
     }
 }
 
 /// Extension methods when extra parameters are represented as a serde_json::Value.
-impl RequestBuilder<Value> {
+impl<Model, Messages> RequestBuilder<Value, Model, Messages> {
     /// Adds provider preferences into the request payload.
     ///
     /// This method accepts a strongly‑typed [ProviderPreferences] instance and serializes it
-    /// into the JSON payload under the "provider" key. It validates the preferences and panics
-    /// with a detailed error message if validation fails.
+    /// into the JSON payload under the "provider" key. The preferences are validated first,
+    /// and an error is returned if they are invalid.
     pub fn with_provider_preferences(
         mut self,
         preferences: crate::models::provider_preferences::ProviderPreferences,
-    ) -> Self {
-        if let Err(err) = preferences.validate() {
-            // Here we panic on validation error. Alternatively, the API could be changed
-            // to return a Result, but for consistency with the builder pattern we panic.
-            panic!("Invalid provider preferences: {}", err);
-        }
-        let provider_value =
-            serde_json::to_value(preferences).expect("Failed to serialize provider preferences");
+    ) -> Result<Self> {
+        preferences.validate()?;
+        let provider_value = serde_json::to_value(preferences).map_err(|e| {
+            Error::InvalidRequest(format!("Failed to serialize provider preferences: {}", e))
+        })?;
         if let Value::Object(ref mut map) = self.extra_params {
             map.insert("provider".to_string(), provider_value);
         }
-        self
+        Ok(self)
     }
 }
@@ -0,0 +1,47 @@
+// api/service.rs
+//! `tower::Service` implementations for chat completions, enabled with the `tower` feature.
+//!
+//! Both the client and a [`ChatApi`] handle are services from [`ChatCompletionRequest`] to
+//! [`ChatCompletionResponse`], so tower middleware such as timeouts, load shedding,
+//! concurrency limits, and retry layers can wrap OpenRouter calls. The services are always
+//! ready; each call runs [`ChatApi::chat_completion`] with the handle's configuration.
+
+use super::chat::ChatApi;
+use crate::client::{OpenRouterClient, Ready};
+use crate::error::{Error, Result};
+use crate::types::chat::{ChatCompletionRequest, ChatCompletionResponse};
+use futures::future::{self, BoxFuture, FutureExt};
+use std::task::{Context, Poll};
+use tower_service::Service;
+
+impl Service<ChatCompletionRequest> for ChatApi {
+    type Response = ChatCompletionResponse;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<ChatCompletionResponse>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: ChatCompletionRequest) -> Self::Future {
+        let api = self.clone();
+        async move { api.chat_completion(request).await }.boxed()
+    }
+}
+
+impl Service<ChatCompletionRequest> for OpenRouterClient<Ready> {
+    type Response = ChatCompletionResponse;
+    type Error = Error;
+    type Future = BoxFuture<'static, Result<ChatCompletionResponse>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: ChatCompletionRequest) -> Self::Future {
+        match self.chat() {
+            Ok(mut api) => api.call(request),
+            Err(error) => future::ready(Err(error)).boxed(),
+        }
+    }
+}
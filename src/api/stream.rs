@@ -0,0 +1,429 @@
+/*!
+   # Stream Module
+
+   Helpers for consuming streamed chat completions. [`ChatStreamExt`] adds per-chunk transform
+   hooks to any stream of [`ChatCompletionChunk`]s, e.g. to strip markdown, censor words, or
+   normalize whitespace before the consumer sees the text, or to validate structured output
+   against a JSON Schema while it streams. [`StreamAccumulator`] assembles the
+   chunks into the partial assistant message, so UIs can render it while the stream runs.
+   [`text_deltas`](ChatStreamExt::text_deltas) reduces a stream to its text, with reasoning
+   deltas as an optional separate channel.
+
+   [`StreamBuffer`] controls what happens when the consumer reads slower than the model
+   writes, e.g. when relaying to slow websocket clients: apply backpressure to the socket,
+   read ahead into a bounded buffer, or coalesce deltas into fewer, larger chunks.
+*/
+
+use crate::error::Result;
+use crate::models::structured::{IncrementalSchemaValidator, JsonSchemaDefinition};
+use crate::models::tool::{FunctionCall, ToolCall};
+use crate::types::chat::{Annotation, ChatCompletionChunk, Message};
+use crate::types::metrics::CallMetrics;
+use async_stream::try_stream;
+use futures::channel::oneshot;
+use futures::future;
+use futures::stream::Stream;
+use futures::{StreamExt, TryStreamExt};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, Notify};
+
+/// A boxed stream of chat completion chunks, as returned by `chat_completion_stream`.
+pub type ChatCompletionStream = Pin<Box<dyn Stream<Item = Result<ChatCompletionChunk>> + Send>>;
+
+/// A boxed stream of text fragments, as returned by [`ChatStreamExt::text_deltas`].
+pub type TextDeltaStream = Pin<Box<dyn Stream<Item = Result<TextDelta>> + Send>>;
+
+/// A fragment of streamed text, telling the answer apart from the model's reasoning.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextDelta {
+    /// Part of the chain of thought of a reasoning model.
+    Reasoning(String),
+    /// Part of the answer.
+    Content(String),
+}
+
+/// How a chat stream behaves when its consumer reads slower than the chunks arrive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StreamBuffer {
+    /// Chunks are read from the connection only as the consumer polls, so a slow consumer
+    /// slows the connection down (TCP backpressure). The default.
+    #[default]
+    Backpressure,
+    /// Reads up to the given number of chunks ahead of the consumer, then applies
+    /// backpressure.
+    Buffer(usize),
+    /// Reads up to the given number of chunks ahead of the consumer; once the buffer is
+    /// full, further deltas are merged into the newest buffered chunk instead of pausing
+    /// the connection, so a slow consumer receives fewer, larger chunks and never stalls
+    /// the upstream.
+    Coalesce(usize),
+}
+
+/// Applies `policy` to `stream`. Read-ahead starts on the first poll and stops when the
+/// returned stream is dropped.
+pub fn buffer_stream(stream: ChatCompletionStream, policy: StreamBuffer) -> ChatCompletionStream {
+    match policy {
+        StreamBuffer::Backpressure => stream,
+        StreamBuffer::Buffer(capacity) => read_ahead(stream, capacity.max(1)),
+        StreamBuffer::Coalesce(capacity) => coalescing(stream, capacity.max(1)),
+    }
+}
+
+fn read_ahead(mut stream: ChatCompletionStream, capacity: usize) -> ChatCompletionStream {
+    Box::pin(async_stream::stream! {
+        let (sender, mut receiver) = mpsc::channel(capacity);
+        tokio::spawn(async move {
+            loop {
+                let item = tokio::select! {
+                    item = stream.next() => item,
+                    () = sender.closed() => break,
+                };
+                let Some(item) = item else { break };
+                if sender.send(item).await.is_err() {
+                    break;
+                }
+            }
+        });
+        while let Some(item) = receiver.recv().await {
+            yield item;
+        }
+    })
+}
+
+#[derive(Default)]
+struct CoalesceState {
+    queue: VecDeque<Result<ChatCompletionChunk>>,
+    done: bool,
+}
+
+fn coalescing(mut stream: ChatCompletionStream, capacity: usize) -> ChatCompletionStream {
+    Box::pin(async_stream::stream! {
+        let shared = Arc::new((Mutex::new(CoalesceState::default()), Notify::new()));
+        // Dropping `_closed` together with this stream stops the reader task.
+        let (_closed, mut closed) = oneshot::channel::<()>();
+        let writer = shared.clone();
+        tokio::spawn(async move {
+            let (ref state, ref notify) = *writer;
+            loop {
+                let item = tokio::select! {
+                    item = stream.next() => item,
+                    _ = &mut closed => break,
+                };
+                let mut state = state.lock().unwrap();
+                let Some(item) = item else {
+                    state.done = true;
+                    break;
+                };
+                match (state.queue.len() >= capacity, state.queue.back_mut(), item) {
+                    (true, Some(Ok(newest)), Ok(chunk)) => merge_chunk(newest, chunk),
+                    (_, _, item) => state.queue.push_back(item),
+                }
+                drop(state);
+                notify.notify_one();
+            }
+            notify.notify_one();
+        });
+
+        let (ref state, ref notify) = *shared;
+        loop {
+            let next = {
+                let mut state = state.lock().unwrap();
+                match state.queue.pop_front() {
+                    Some(item) => Some(item),
+                    None if state.done => break,
+                    None => None,
+                }
+            };
+            match next {
+                Some(item) => yield item,
+                None => notify.notified().await,
+            }
+        }
+    })
+}
+
+/// Appends the deltas of `next` to `into`, choice by choice.
+fn merge_chunk(into: &mut ChatCompletionChunk, next: ChatCompletionChunk) {
+    if next.metrics.is_some() {
+        into.metrics = next.metrics;
+    }
+    for choice in next.choices {
+        let Some(target) = into.choices.iter_mut().find(|c| c.index == choice.index) else {
+            into.choices.push(choice);
+            continue;
+        };
+        let delta = choice.delta;
+        if target.delta.role.is_none() {
+            target.delta.role = delta.role;
+        }
+        if let Some(content) = delta.content {
+            target
+                .delta
+                .content
+                .get_or_insert_with(String::new)
+                .push_str(&content);
+        }
+        if let Some(reasoning) = delta.reasoning {
+            target
+                .delta
+                .reasoning
+                .get_or_insert_with(String::new)
+                .push_str(&reasoning);
+        }
+        if let Some(calls) = delta.tool_calls {
+            target
+                .delta
+                .tool_calls
+                .get_or_insert_with(Vec::new)
+                .extend(calls);
+        }
+        if let Some(annotations) = delta.annotations {
+            target
+                .delta
+                .annotations
+                .get_or_insert_with(Vec::new)
+                .extend(annotations);
+        }
+        if choice.finish_reason.is_some() {
+            target.finish_reason = choice.finish_reason;
+            target.native_finish_reason = choice.native_finish_reason;
+        }
+    }
+}
+
+/// Transform hooks for streams of chat completion chunks.
+pub trait ChatStreamExt:
+    Stream<Item = Result<ChatCompletionChunk>> + Sized + Send + 'static
+{
+    /// Rewrites the content delta of every choice with `f` before it is yielded.
+    fn map_deltas<F>(self, mut f: F) -> ChatCompletionStream
+    where
+        F: FnMut(String) -> String + Send + 'static,
+    {
+        Box::pin(self.map_ok(move |mut chunk| {
+            for choice in &mut chunk.choices {
+                if let Some(content) = choice.delta.content.take() {
+                    choice.delta.content = Some(f(content));
+                }
+            }
+            chunk
+        }))
+    }
+
+    /// Drops content deltas for which `f` returns `false`.
+    ///
+    /// Chunks left with nothing to report (no content, reasoning, tool calls, annotations,
+    /// role, or finish reason) are skipped entirely; chunks carrying a finish reason are
+    /// always passed through.
+    fn filter_deltas<F>(self, mut f: F) -> ChatCompletionStream
+    where
+        F: FnMut(&str) -> bool + Send + 'static,
+    {
+        Box::pin(self.try_filter_map(move |mut chunk| {
+            for choice in &mut chunk.choices {
+                if choice.delta.content.as_deref().is_some_and(|c| !f(c)) {
+                    choice.delta.content = None;
+                }
+            }
+            let keep = chunk.choices.iter().any(|choice| {
+                choice.delta.content.is_some()
+                    || choice.delta.reasoning.is_some()
+                    || choice.delta.tool_calls.is_some()
+                    || choice.delta.annotations.is_some()
+                    || choice.delta.role.is_some()
+                    || choice.finish_reason.is_some()
+            });
+            future::ready(Ok(keep.then_some(chunk)))
+        }))
+    }
+
+    /// Reduces the stream to the text of the first choice, in order. Reasoning deltas are
+    /// yielded as [`TextDelta::Reasoning`] if `reasoning` is true and dropped otherwise.
+    fn text_deltas(self, reasoning: bool) -> TextDeltaStream {
+        Box::pin(
+            self.map_ok(move |chunk| {
+                let deltas: Vec<Result<TextDelta>> = chunk
+                    .choices
+                    .into_iter()
+                    .filter(|choice| choice.index == 0)
+                    .flat_map(|choice| {
+                        let thought = choice
+                            .delta
+                            .reasoning
+                            .filter(|text| reasoning && !text.is_empty())
+                            .map(TextDelta::Reasoning);
+                        let answer = choice
+                            .delta
+                            .content
+                            .filter(|text| !text.is_empty())
+                            .map(TextDelta::Content);
+                        thought.into_iter().chain(answer)
+                    })
+                    .map(Ok)
+                    .collect();
+                futures::stream::iter(deltas)
+            })
+            .try_flatten(),
+        )
+    }
+
+    /// Validates streamed structured output against `schema` as it arrives.
+    ///
+    /// The stream fails with `Error::SchemaValidationError` and stops as soon as a violation
+    /// is detected, which drops the connection instead of paying for the rest of a bad
+    /// generation. Completeness (`required` properties, valid JSON) is checked when the
+    /// finish reason arrives.
+    fn validate_schema(self, schema: JsonSchemaDefinition) -> ChatCompletionStream {
+        let mut validator = IncrementalSchemaValidator::new(schema);
+        let mut inner = Box::pin(self);
+        Box::pin(try_stream! {
+            while let Some(chunk) = inner.next().await {
+                let chunk = chunk?;
+                if let Some(choice) = chunk.choices.iter().find(|choice| choice.index == 0) {
+                    if let Some(ref content) = choice.delta.content {
+                        validator.push(content)?;
+                    }
+                    if choice.finish_reason.is_some() {
+                        validator.finish()?;
+                    }
+                }
+                yield chunk;
+            }
+        })
+    }
+}
+
+impl<S> ChatStreamExt for S where S: Stream<Item = Result<ChatCompletionChunk>> + Send + 'static {}
+
+/// Assembles streamed chunks into the assistant message received so far.
+///
+/// Only the first choice (`index` 0) is tracked. Tool call deltas must arrive in index
+/// order; a delta for an index beyond the next new call is ignored.
+#[derive(Debug, Clone, Default)]
+pub struct StreamAccumulator {
+    id: Option<String>,
+    text: String,
+    reasoning: String,
+    tool_calls: Vec<ToolCall>,
+    annotations: Vec<Annotation>,
+    finish_reason: Option<String>,
+    metrics: Option<CallMetrics>,
+}
+
+impl StreamAccumulator {
+    /// Creates an empty accumulator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Merges a chunk into the accumulated state.
+    pub fn push(&mut self, chunk: &ChatCompletionChunk) {
+        if self.id.is_none() {
+            self.id = Some(chunk.id.clone());
+        }
+        if chunk.metrics.is_some() {
+            self.metrics = chunk.metrics;
+        }
+        let Some(choice) = chunk.choices.iter().find(|choice| choice.index == 0) else {
+            return;
+        };
+        if let Some(ref content) = choice.delta.content {
+            self.text.push_str(content);
+        }
+        if let Some(ref reasoning) = choice.delta.reasoning {
+            self.reasoning.push_str(reasoning);
+        }
+        for delta in choice.delta.tool_calls.iter().flatten() {
+            // The index comes off the wire; never allocate more than one call ahead.
+            if delta.index > self.tool_calls.len() {
+                tracing::debug!("ignoring tool call delta with index {}", delta.index);
+                continue;
+            }
+            if delta.index == self.tool_calls.len() {
+                self.tool_calls.push(ToolCall {
+                    id: String::new(),
+                    kind: "function".to_string(),
+                    function_call: FunctionCall {
+                        name: String::new(),
+                        arguments: String::new(),
+                    },
+                });
+            }
+            let call = &mut self.tool_calls[delta.index];
+            if let Some(ref id) = delta.id {
+                call.id.clone_from(id);
+            }
+            if let Some(ref kind) = delta.kind {
+                call.kind.clone_from(kind);
+            }
+            if let Some(ref function) = delta.function {
+                if let Some(ref name) = function.name {
+                    call.function_call.name.push_str(name);
+                }
+                if let Some(ref arguments) = function.arguments {
+                    call.function_call.arguments.push_str(arguments);
+                }
+            }
+        }
+        self.annotations
+            .extend(choice.delta.annotations.iter().flatten().cloned());
+        if choice.finish_reason.is_some() {
+            self.finish_reason.clone_from(&choice.finish_reason);
+        }
+    }
+
+    /// The generation id, once the first chunk has arrived.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// The text received so far.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The reasoning received so far, empty for models that don't stream their reasoning.
+    pub fn reasoning(&self) -> &str {
+        &self.reasoning
+    }
+
+    /// The tool calls received so far; the last one's arguments may still be incomplete JSON.
+    pub fn tool_calls(&self) -> &[ToolCall] {
+        &self.tool_calls
+    }
+
+    /// The annotations, such as web citations, received so far.
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    /// The finish reason, once the model has stopped.
+    pub fn finish_reason(&self) -> Option<&str> {
+        self.finish_reason.as_deref()
+    }
+
+    /// Client-side timings of the stream up to the latest chunk, including the time to
+    /// first token and an estimated generation speed.
+    pub fn metrics(&self) -> Option<&CallMetrics> {
+        self.metrics.as_ref()
+    }
+
+    /// Returns true once a chunk carrying a finish reason has been received.
+    pub fn is_finished(&self) -> bool {
+        self.finish_reason.is_some()
+    }
+
+    /// Returns the (possibly partial) assistant message received so far.
+    pub fn message(&self) -> Message {
+        let mut message = Message::assistant(self.text.as_str());
+        if !self.tool_calls.is_empty() {
+            message.tool_calls = Some(self.tool_calls.clone());
+        }
+        if !self.annotations.is_empty() {
+            message.annotations = Some(self.annotations.clone());
+        }
+        message
+    }
+}
@@ -1,4 +1,4 @@
-//// File: openrouter_api/src/api/web_search.rs
+// File: openrouter_api/src/api/web_search.rs
 use crate::{
     client::ClientConfig,
     error::{Error, Result},
@@ -21,30 +21,33 @@ impl WebSearchApi {
         }
     }
 
+    /// Overrides the base URL for calls made through this handle only, e.g. to route
+    /// them through a regional gateway or a local OpenAI-compatible proxy.
+    /// The URL must include a trailing slash.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Result<Self> {
+        self.config.base_url = crate::client::parse_base_url(&base_url.into())?;
+        Ok(self)
+    }
+
+    /// Overrides the API key for calls made through this handle only, so one shared
+    /// client and connection pool can serve many tenants, each billed to their own key.
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.config.api_key = Some(api_key.into());
+        self
+    }
+
     /// Performs a web search with the given request and returns a structured response.
     pub async fn search(&self, request: WebSearchRequest) -> Result<WebSearchResponse> {
-        // Join the base URL with the relative path "web/search".
-        let url = self
-            .config
-            .base_url
-            .join("web/search")
-            .map_err(|e| Error::ApiError {
-                code: 400,
-                message: format!("Invalid URL for web search: {}", e),
-                metadata: None,
-            })?;
+        // Build the URL for the relative path "web/search", plus headers and body.
+        let prepared =
+            self.config
+                .prepare_request(reqwest::Method::POST, "web/search", &request)?;
 
-        let response = self
-            .client
-            .post(url)
-            .headers(self.config.build_headers()?)
-            .json(&request)
-            .send()
-            .await?;
+        let response = crate::transport::execute(&self.client, &self.config, prepared).await?;
 
-        if !response.status().is_success() {
+        if !response.status.is_success() {
             return Err(Error::ApiError {
-                code: response.status().as_u16(),
+                code: response.status.as_u16(),
                 message: response.text().await?,
                 metadata: None,
             });
@@ -55,11 +58,11 @@ impl WebSearchApi {
     }
 
     /// Internal helper to deserialize a response while handling errors.
-    async fn handle_response<T>(&self, response: reqwest::Response) -> Result<T>
+    async fn handle_response<T>(&self, response: crate::transport::HttpResponse) -> Result<T>
     where
         T: DeserializeOwned,
     {
-        let status = response.status();
+        let status = response.status;
         let body = response.text().await?;
         if !status.is_success() {
             return Err(Error::ApiError {
@@ -75,10 +78,6 @@ impl WebSearchApi {
                 metadata: None,
             });
         }
-        serde_json::from_str::<T>(&body).map_err(|e| Error::ApiError {
-            code: status.as_u16(),
-            message: format!("Failed to decode JSON: {}. Body was: {}", e, body),
-            metadata: None,
-        })
+        crate::error::decode_json::<T>(status.as_u16(), &body)
     }
 }
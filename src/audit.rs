@@ -0,0 +1,184 @@
+/*!
+   # Audit Module
+
+   An [`AuditSink`] receives one [`AuditRecord`] per chat completion request, streaming or
+   not, and per text completion request, so regulated environments can persist an audit
+   trail from within the client. Streams are recorded when they end, with the estimated cost
+   they were charged, since they report no usage; text completions report neither usage nor
+   cost. Install a sink
+   with `with_audit_sink()` on the builder; prompt hashing is opt-in via
+   `with_audit_prompt_hash()`.
+*/
+
+#[cfg(feature = "reqwest")]
+use crate::client::{ClientConfig, PreparedRequest};
+#[cfg(feature = "reqwest")]
+use crate::error::Error;
+#[cfg(feature = "reqwest")]
+use crate::types::chat::ChatCompletionResponse;
+#[cfg(feature = "reqwest")]
+use crate::utils::hash::fnv1a;
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+#[cfg(feature = "reqwest")]
+use std::time::Instant;
+
+/// A structured record describing a single request and its outcome.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditRecord {
+    /// When the request was sent.
+    pub timestamp: DateTime<Utc>,
+    /// The model requested.
+    pub model: String,
+    /// The generation id returned by the API, if the request succeeded.
+    pub request_id: Option<String>,
+    /// HTTP status of the response; `None` if no response was received.
+    pub status: Option<u16>,
+    /// Time from sending the request until the response was complete.
+    pub latency: Duration,
+    /// Prompt tokens reported by the API.
+    pub prompt_tokens: Option<u32>,
+    /// Completion tokens reported by the API.
+    pub completion_tokens: Option<u32>,
+    /// Total tokens reported by the API.
+    pub total_tokens: Option<u32>,
+    /// Cost in credits reported by the API, or the estimated cost charged for a stream.
+    /// Serialized as a decimal string.
+    pub cost: Option<Decimal>,
+    /// Hex FNV-1a hash of the serialized messages, or the prompt of a text completion, if
+    /// prompt hashing is enabled. Identifies identical prompts without storing their content.
+    pub prompt_hash: Option<String>,
+    /// The error message, if the request failed.
+    pub error: Option<String>,
+    /// The labels attached to the request, e.g. for per-feature cost attribution.
+    pub tags: BTreeMap<String, String>,
+}
+
+/// Receives audit records, e.g. to write them to a database or append-only log.
+pub trait AuditSink: Send + Sync + fmt::Debug {
+    /// Persists a record. Called once per chat or text completion request, after the
+    /// response or error is known.
+    fn record(&self, record: AuditRecord) -> BoxFuture<'_, ()>;
+}
+
+/// Tracks an in-flight request so an audit record can be emitted once it completes.
+#[cfg(feature = "reqwest")]
+pub(crate) struct AuditContext {
+    timestamp: DateTime<Utc>,
+    started: Instant,
+    model: String,
+    prompt_hash: Option<String>,
+    tags: BTreeMap<String, String>,
+}
+
+#[cfg(feature = "reqwest")]
+impl AuditContext {
+    /// Starts tracking `request`, or returns `None` if no audit sink is configured.
+    pub(crate) fn begin(config: &ClientConfig, request: &PreparedRequest) -> Option<Self> {
+        config.audit_sink.as_ref()?;
+        let prompt_hash = config.audit_prompt_hash.then(|| {
+            let prompt = match request.body.get("messages") {
+                Some(messages) => messages,
+                None => &request.body["prompt"],
+            };
+            format!("{:016x}", fnv1a(prompt.to_string().as_bytes()))
+        });
+        Some(Self {
+            timestamp: Utc::now(),
+            started: Instant::now(),
+            model: request.body["model"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            prompt_hash,
+            tags: request.tags.clone(),
+        })
+    }
+
+    /// Sends the record for the completed request to the configured sink.
+    pub(crate) async fn finish(
+        self,
+        config: &ClientConfig,
+        outcome: std::result::Result<&ChatCompletionResponse, &Error>,
+    ) {
+        let Some(ref sink) = config.audit_sink else {
+            return;
+        };
+        let mut record = self.into_record();
+        match outcome {
+            Ok(response) => {
+                record.request_id = Some(response.id.clone());
+                record.status = Some(200);
+                if let Some(ref usage) = response.usage {
+                    record.prompt_tokens = Some(usage.prompt_tokens);
+                    record.completion_tokens = Some(usage.completion_tokens);
+                    record.total_tokens = Some(usage.total_tokens);
+                    record.cost = usage.cost;
+                }
+            }
+            Err(error) => {
+                record.status = error.status_code();
+                record.error = Some(error_summary(error));
+            }
+        }
+        sink.record(record).await;
+    }
+
+    /// Sends the record for a request whose response reports no usage, i.e. an ended stream
+    /// or a text completion, to the configured sink. `outcome` carries the generation id
+    /// received, and `cost` the estimate the request was charged, if any.
+    pub(crate) async fn finish_with_id(
+        self,
+        config: &ClientConfig,
+        outcome: std::result::Result<&str, &Error>,
+        cost: Option<Decimal>,
+    ) {
+        let Some(ref sink) = config.audit_sink else {
+            return;
+        };
+        let mut record = self.into_record();
+        record.cost = cost;
+        match outcome {
+            Ok(id) => {
+                record.request_id = (!id.is_empty()).then(|| id.to_string());
+                record.status = Some(200);
+            }
+            Err(error) => {
+                record.status = error.status_code();
+                record.error = Some(error_summary(error));
+            }
+        }
+        sink.record(record).await;
+    }
+
+    fn into_record(self) -> AuditRecord {
+        AuditRecord {
+            timestamp: self.timestamp,
+            model: self.model,
+            request_id: None,
+            status: None,
+            latency: self.started.elapsed(),
+            prompt_tokens: None,
+            completion_tokens: None,
+            total_tokens: None,
+            cost: None,
+            prompt_hash: self.prompt_hash,
+            error: None,
+            tags: self.tags,
+        }
+    }
+}
+
+/// Describes an error without echoing request content back into the audit trail.
+#[cfg(feature = "reqwest")]
+fn error_summary(error: &Error) -> String {
+    match error {
+        Error::ApiError { code, .. } => format!("API error (status {})", code),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,204 @@
+/*!
+   # Load Balancer Module
+
+   Spreads requests across several OpenAI-compatible backends, e.g. OpenRouter plus
+   self-hosted gateways. A [`LoadBalancer`] picks a [`Backend`] for every request according
+   to its [`Strategy`] and fails over to the next one when a backend is unreachable or
+   answers with a transient error (429 or 5xx). Failing backends are taken out of rotation
+   for a cooldown period and only used again as a last resort until it expires.
+
+   Install a balancer with `with_load_balancer()` on the builder. Endpoint paths are resolved
+   against the chosen backend's base URL, and a backend with its own API key replaces the
+   client's key for the requests it serves.
+*/
+
+use crate::client::{parse_base_url, PreparedRequest};
+use crate::error::{Error, Result};
+use http::header::{HeaderValue, AUTHORIZATION};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// How long a failing backend is taken out of rotation unless configured otherwise.
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// How the balancer spreads requests over healthy backends.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strategy {
+    /// Smooth weighted round robin: each backend serves a share of requests proportional
+    /// to its weight.
+    #[default]
+    Weighted,
+    /// Sends everything to the first healthy backend in configuration order; later
+    /// backends only serve traffic while earlier ones are failing.
+    Failover,
+}
+
+/// An OpenAI-compatible API endpoint served by the balancer.
+#[derive(Debug, Clone)]
+pub struct Backend {
+    base_url: Url,
+    api_key: Option<String>,
+    weight: u32,
+}
+
+impl Backend {
+    /// Creates a backend with weight 1 that uses the client's API key.
+    pub fn new(base_url: &str) -> Result<Self> {
+        let mut base_url = parse_base_url(base_url)?;
+        if !base_url.path().ends_with('/') {
+            let path = format!("{}/", base_url.path());
+            base_url.set_path(&path);
+        }
+        Ok(Self {
+            base_url,
+            api_key: None,
+            weight: 1,
+        })
+    }
+
+    /// Uses `api_key` instead of the client's key for requests sent to this backend.
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Sets the relative share of traffic for the weighted strategy (at least 1).
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight.max(1);
+        self
+    }
+
+    /// The base URL endpoint paths are resolved against.
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// The relative share of traffic.
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+}
+
+/// Picks a backend for every request and tracks backend health.
+#[derive(Debug)]
+pub struct LoadBalancer {
+    backends: Vec<Backend>,
+    strategy: Strategy,
+    cooldown: Duration,
+    state: Mutex<Vec<BackendState>>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct BackendState {
+    current_weight: i64,
+    ejected_until: Option<Instant>,
+}
+
+impl LoadBalancer {
+    /// Creates a weighted balancer over `backends`.
+    pub fn new(backends: Vec<Backend>) -> Self {
+        let state = vec![BackendState::default(); backends.len()];
+        Self {
+            backends,
+            strategy: Strategy::default(),
+            cooldown: DEFAULT_COOLDOWN,
+            state: Mutex::new(state),
+        }
+    }
+
+    /// Sets the strategy used to spread requests over healthy backends.
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Sets how long a failing backend is taken out of rotation (default 30 seconds).
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// The configured backends, in configuration order.
+    pub fn backends(&self) -> &[Backend] {
+        &self.backends
+    }
+
+    /// The base URLs of the backends currently in rotation.
+    pub fn healthy_backends(&self) -> Vec<Url> {
+        let now = Instant::now();
+        let state = self.state.lock().unwrap();
+        self.backends
+            .iter()
+            .zip(state.iter())
+            .filter(|(_, state)| state.is_healthy(now))
+            .map(|(backend, _)| backend.base_url.clone())
+            .collect()
+    }
+
+    /// Returns the order in which backends are tried for the next request: the backend
+    /// picked by the strategy, the other healthy ones, then the failing ones whose cooldown
+    /// ends soonest.
+    pub(crate) fn plan(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let (mut healthy, mut failing): (Vec<usize>, Vec<usize>) =
+            (0..self.backends.len()).partition(|&i| state[i].is_healthy(now));
+        if self.strategy == Strategy::Weighted && !healthy.is_empty() {
+            let total: i64 = healthy
+                .iter()
+                .map(|&i| i64::from(self.backends[i].weight))
+                .sum();
+            for &i in &healthy {
+                state[i].current_weight += i64::from(self.backends[i].weight);
+            }
+            // The first backend with the highest current weight wins.
+            let picked = healthy
+                .iter()
+                .copied()
+                .rev()
+                .max_by_key(|&i| state[i].current_weight)
+                .expect("healthy backends are not empty");
+            state[picked].current_weight -= total;
+            healthy.retain(|&i| i != picked);
+            healthy.insert(0, picked);
+        }
+        failing.sort_by_key(|&i| state[i].ejected_until);
+        healthy.extend(failing);
+        healthy
+    }
+
+    /// Rewrites `request` to be sent to the backend at `index`.
+    pub(crate) fn route(
+        &self,
+        index: usize,
+        base_url: &Url,
+        mut request: PreparedRequest,
+    ) -> Result<PreparedRequest> {
+        let backend = &self.backends[index];
+        if let Some(path) = request.url.as_str().strip_prefix(base_url.as_str()) {
+            request.url = backend
+                .base_url
+                .join(path)
+                .map_err(|e| Error::ConfigError(format!("Invalid backend URL: {}", e)))?;
+        }
+        if let Some(ref key) = backend.api_key {
+            let value = HeaderValue::from_str(&format!("Bearer {}", key))
+                .map_err(|e| Error::ConfigError(format!("Invalid API key header format: {}", e)))?;
+            request.headers.insert(AUTHORIZATION, value);
+        }
+        Ok(request)
+    }
+
+    /// Records the outcome of a request sent to the backend at `index`.
+    pub(crate) fn report(&self, index: usize, healthy: bool) {
+        let mut state = self.state.lock().unwrap();
+        state[index].ejected_until = (!healthy).then(|| Instant::now() + self.cooldown);
+    }
+}
+
+impl BackendState {
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.ejected_until.is_none_or(|until| until <= now)
+    }
+}
@@ -0,0 +1,306 @@
+/*!
+   # Budget Module
+
+   Cost guardrails for chat completions. A [`CostBudget`] estimates what a request will cost
+   from per-model [`ModelPricing`], counting its prompt plus its completion limit
+   (`max_tokens` or `max_completion_tokens`, or a configurable estimate when neither is set),
+   and rejects it with [`Error::BudgetExceeded`] before it is sent if it would exceed the
+   per-request limit or the remaining cumulative budget.
+
+   Prices, costs, and limits are exact [`Decimal`]s, so per-token prices such as `0.000002`
+   accumulate without floating-point drift.
+
+   A request that passes the check has its estimate reserved against the cumulative budget
+   while it is in flight, so concurrent requests cannot together overspend it. Once the
+   response arrives the reservation is replaced by the actual cost; a request that fails
+   releases its reservation without being charged.
+
+   Install a budget on the client with `with_budget()` to cap the whole client, or on a
+   single endpoint handle with `ChatApi::with_budget()` to cap one session. Budgets are
+   shared through an `Arc`, so every handle holding the same budget draws from it.
+*/
+
+use crate::error::{Error, Result};
+use crate::types::chat::Message;
+use crate::types::models::ModelsResponse;
+use crate::utils::tokens::estimate_messages_tokens;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+#[cfg(feature = "reqwest")]
+use std::sync::Arc;
+use std::sync::Mutex;
+
+#[cfg(feature = "reqwest")]
+use crate::client::PreparedRequest;
+#[cfg(feature = "reqwest")]
+use crate::types::chat::ChatCompletionResponse;
+
+/// Completion tokens assumed for requests without a token limit.
+const DEFAULT_COMPLETION_TOKENS_ESTIMATE: u32 = 512;
+
+/// Price of a model, in credits (USD) per token.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPricing {
+    /// Price per prompt token.
+    pub prompt: Decimal,
+    /// Price per completion token.
+    pub completion: Decimal,
+}
+
+impl ModelPricing {
+    /// Creates pricing from per-token prompt and completion prices.
+    pub fn new(prompt: Decimal, completion: Decimal) -> Self {
+        Self { prompt, completion }
+    }
+
+    /// Returns the cost of the given token counts.
+    pub fn cost(&self, prompt_tokens: u32, completion_tokens: u32) -> Decimal {
+        self.prompt * Decimal::from(prompt_tokens)
+            + self.completion * Decimal::from(completion_tokens)
+    }
+}
+
+/// Per-request and cumulative spending limits.
+#[derive(Debug)]
+pub struct CostBudget {
+    max_request_cost: Option<Decimal>,
+    max_total_cost: Option<Decimal>,
+    completion_tokens_estimate: u32,
+    pricing: HashMap<String, ModelPricing>,
+    ledger: Mutex<Ledger>,
+}
+
+/// Costs recorded so far and estimates reserved by requests in flight.
+#[derive(Debug, Default)]
+struct Ledger {
+    spent: Decimal,
+    reserved: Decimal,
+}
+
+impl Default for CostBudget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CostBudget {
+    /// Creates a budget without limits; add them with the `with_*` methods.
+    pub fn new() -> Self {
+        Self {
+            max_request_cost: None,
+            max_total_cost: None,
+            completion_tokens_estimate: DEFAULT_COMPLETION_TOKENS_ESTIMATE,
+            pricing: HashMap::new(),
+            ledger: Mutex::default(),
+        }
+    }
+
+    /// Rejects any single request whose estimated cost exceeds `limit`.
+    pub fn with_max_request_cost(mut self, limit: Decimal) -> Self {
+        self.max_request_cost = Some(limit);
+        self
+    }
+
+    /// Rejects requests once the cumulative cost would exceed `limit`.
+    pub fn with_max_total_cost(mut self, limit: Decimal) -> Self {
+        self.max_total_cost = Some(limit);
+        self
+    }
+
+    /// Registers the pricing used to estimate requests to `model`. Requests to models
+    /// without pricing are only checked against the cumulative budget already spent.
+    pub fn with_pricing(mut self, model: impl Into<String>, pricing: ModelPricing) -> Self {
+        self.pricing.insert(model.into(), pricing);
+        self
+    }
+
+    /// Registers the pricing of every priced model in `catalog`, e.g. from `models().list()`
+    /// or the embedded offline snapshot. Negative placeholder prices (as listed for the Auto
+    /// Router, whose price depends on the model it picks) are skipped.
+    pub fn with_catalog_pricing(mut self, catalog: &ModelsResponse) -> Self {
+        for model in &catalog.data {
+            let Some(ref pricing) = model.pricing else {
+                continue;
+            };
+            if pricing.prompt.is_sign_negative() || pricing.completion.is_sign_negative() {
+                continue;
+            }
+            self.pricing.insert(
+                model.id.clone(),
+                ModelPricing::new(pricing.prompt, pricing.completion),
+            );
+        }
+        self
+    }
+
+    /// Sets how many completion tokens are assumed for requests that set no token limit
+    /// (default 512).
+    pub fn with_completion_tokens_estimate(mut self, tokens: u32) -> Self {
+        self.completion_tokens_estimate = tokens;
+        self
+    }
+
+    /// Returns the total cost recorded so far.
+    pub fn spent(&self) -> Decimal {
+        self.ledger.lock().unwrap().spent
+    }
+
+    /// Returns the estimated cost reserved by requests still in flight.
+    pub fn reserved(&self) -> Decimal {
+        self.ledger.lock().unwrap().reserved
+    }
+
+    /// Returns the remaining cumulative budget, net of reservations, if one is set.
+    pub fn remaining(&self) -> Option<Decimal> {
+        let ledger = self.ledger.lock().unwrap();
+        self.remaining_in(&ledger)
+    }
+
+    /// Clears the recorded spend, e.g. at the start of a new billing period. Reservations
+    /// of requests in flight are kept.
+    pub fn reset(&self) {
+        self.ledger.lock().unwrap().spent = Decimal::ZERO;
+    }
+
+    /// Adds `cost` to the recorded spend.
+    pub fn record(&self, cost: Decimal) {
+        self.ledger.lock().unwrap().spent += cost;
+    }
+
+    /// Releases an estimate reserved by [`check`](Self::check), e.g. because the request
+    /// failed or its actual cost has been recorded.
+    pub fn release(&self, estimate: Option<Decimal>) {
+        self.settle_reservation(estimate, None);
+    }
+
+    /// Estimates the cost of sending `messages` to `model` with a completion limit of
+    /// `max_tokens`, or the completion tokens estimate if `max_tokens` is `None`. Returns
+    /// `None` if the model's pricing is unknown.
+    pub fn estimate(
+        &self,
+        model: &str,
+        messages: &[Message],
+        max_tokens: Option<u32>,
+    ) -> Option<Decimal> {
+        let pricing = self.pricing.get(model)?;
+        let prompt_tokens = u32::try_from(estimate_messages_tokens(messages)).unwrap_or(u32::MAX);
+        Some(pricing.cost(
+            prompt_tokens,
+            max_tokens.unwrap_or(self.completion_tokens_estimate),
+        ))
+    }
+
+    /// Checks a request against the limits, reserves its estimated cost, and returns it.
+    ///
+    /// Fails with [`Error::BudgetExceeded`] if the estimate exceeds the per-request limit or
+    /// the remaining cumulative budget, or if the cumulative budget is already exhausted.
+    /// The estimate stays reserved until it is [`release`](Self::release)d.
+    pub fn check(
+        &self,
+        model: &str,
+        messages: &[Message],
+        max_tokens: Option<u32>,
+    ) -> Result<Option<Decimal>> {
+        let estimate = self.estimate(model, messages, max_tokens);
+        let cost = estimate.unwrap_or(Decimal::ZERO);
+        if let Some(limit) = self.max_request_cost {
+            if cost > limit {
+                return Err(Error::BudgetExceeded {
+                    estimated: cost,
+                    limit,
+                });
+            }
+        }
+        let mut ledger = self.ledger.lock().unwrap();
+        if let Some(remaining) = self.remaining_in(&ledger) {
+            if cost > remaining || remaining <= Decimal::ZERO {
+                return Err(Error::BudgetExceeded {
+                    estimated: cost,
+                    limit: remaining,
+                });
+            }
+        }
+        ledger.reserved += cost;
+        Ok(estimate)
+    }
+
+    /// Checks a prepared chat request, reserving its estimated cost until the returned
+    /// reservation is settled or dropped.
+    #[cfg(feature = "reqwest")]
+    pub(crate) fn reserve(self: &Arc<Self>, request: &PreparedRequest) -> Result<Reservation> {
+        let model = request.body["model"].as_str().unwrap_or_default();
+        let messages: Vec<Message> =
+            serde_json::from_value(request.body["messages"].clone()).unwrap_or_default();
+        let max_tokens = ["max_completion_tokens", "max_tokens"]
+            .iter()
+            .find_map(|field| request.body[field].as_u64())
+            .map(|tokens| u32::try_from(tokens).unwrap_or(u32::MAX));
+        let estimate = self.check(model, &messages, max_tokens)?;
+        Ok(Reservation {
+            budget: Arc::clone(self),
+            estimate,
+        })
+    }
+
+    fn remaining_in(&self, ledger: &Ledger) -> Option<Decimal> {
+        self.max_total_cost
+            .map(|limit| (limit - ledger.spent - ledger.reserved).max(Decimal::ZERO))
+    }
+
+    /// Replaces a reservation with `cost`, if any, in one step.
+    fn settle_reservation(&self, estimate: Option<Decimal>, cost: Option<Decimal>) {
+        let mut ledger = self.ledger.lock().unwrap();
+        if let Some(estimate) = estimate {
+            ledger.reserved = (ledger.reserved - estimate).max(Decimal::ZERO);
+        }
+        if let Some(cost) = cost {
+            ledger.spent += cost;
+        }
+    }
+}
+
+/// An estimate reserved against a [`CostBudget`] for a request in flight. Dropping it
+/// unsettled, e.g. because the request failed, releases the reservation.
+#[cfg(feature = "reqwest")]
+pub(crate) struct Reservation {
+    budget: Arc<CostBudget>,
+    estimate: Option<Decimal>,
+}
+
+#[cfg(feature = "reqwest")]
+impl Reservation {
+    /// The estimated cost reserved.
+    pub(crate) fn estimate(&self) -> Option<Decimal> {
+        self.estimate
+    }
+
+    /// Charges the reservation at its estimated cost.
+    pub(crate) fn commit(mut self) {
+        let estimate = self.estimate.take();
+        self.budget.settle_reservation(estimate, estimate);
+    }
+
+    /// Replaces the reservation with the cost of the completed request: the cost reported
+    /// by the API if present, otherwise the reported usage priced with the model's pricing,
+    /// otherwise the estimate.
+    pub(crate) fn settle(mut self, response: &ChatCompletionResponse) {
+        let estimate = self.estimate.take();
+        let usage_cost = response.usage.as_ref().and_then(|usage| {
+            usage.cost.or_else(|| {
+                self.budget
+                    .pricing
+                    .get(&response.model)
+                    .map(|pricing| pricing.cost(usage.prompt_tokens, usage.completion_tokens))
+            })
+        });
+        self.budget
+            .settle_reservation(estimate, usage_cost.or(estimate));
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.budget.release(self.estimate.take());
+    }
+}
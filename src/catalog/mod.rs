@@ -0,0 +1,193 @@
+/*!
+   # Catalog Module
+
+   An in-client cache of the models catalog. Install a [`ModelCache`] with `with_model_cache()`
+   on the builder and `models().list()` serves the cached catalog until its TTL expires, so
+   capability checks and cost estimation don't hit the network on every request.
+
+   With background refresh enabled, an expired catalog is still returned immediately while a
+   fresh copy is fetched in the background; otherwise the next `list()` call after expiry
+   fetches synchronously.
+
+   Once a catalog is cached, chat requests are checked against the target model's context
+   window before they are sent: if the estimated prompt tokens plus `max_tokens` exceed it,
+   the request fails with [`Error::ContextLengthExceeded`] without a round trip.
+
+   With the `offline-catalog` feature, a compiled-in [`snapshot`] of model metadata (ids,
+   context lengths, and pricing at release time) is returned by `models().list()` when the
+   live endpoint is unreachable, so cost estimation and validation still work offline.
+*/
+
+use crate::error::{Error, Result};
+use crate::types::chat::Message;
+use crate::types::models::{Model, ModelsResponse};
+use crate::utils::tokens::estimate_messages_tokens;
+#[cfg(feature = "reqwest")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "offline-catalog")]
+use std::sync::OnceLock;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A cached copy of the models catalog with a time-to-live.
+#[derive(Debug)]
+pub struct ModelCache {
+    ttl: Duration,
+    background_refresh: bool,
+    entry: Mutex<Option<(Instant, Arc<ModelsResponse>)>>,
+    #[cfg(feature = "reqwest")]
+    refreshing: AtomicBool,
+}
+
+/// State of the cache when a catalog is requested.
+#[cfg(feature = "reqwest")]
+pub(crate) enum CacheLookup {
+    /// A catalog younger than the TTL.
+    Fresh(Arc<ModelsResponse>),
+    /// An expired catalog that may be served while a refresh runs in the background.
+    Stale(Arc<ModelsResponse>),
+    /// Nothing usable; the catalog must be fetched.
+    Miss,
+}
+
+impl ModelCache {
+    /// Creates an empty cache whose entries expire after `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            background_refresh: false,
+            entry: Mutex::new(None),
+            #[cfg(feature = "reqwest")]
+            refreshing: AtomicBool::new(false),
+        }
+    }
+
+    /// Serves the expired catalog while refreshing it in the background, instead of
+    /// making the caller wait for the fetch.
+    pub fn with_background_refresh(mut self, enabled: bool) -> Self {
+        self.background_refresh = enabled;
+        self
+    }
+
+    /// The time-to-live of a cached catalog.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Returns the cached catalog regardless of its age, without any network call.
+    pub fn catalog(&self) -> Option<Arc<ModelsResponse>> {
+        self.entry
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|(_, catalog)| catalog.clone())
+    }
+
+    /// Returns the cached entry for `id`, without any network call.
+    pub fn get(&self, id: &str) -> Option<Model> {
+        self.catalog()?.get(id).cloned()
+    }
+
+    /// Returns the cached context length of `model`, without any network call.
+    pub fn context_length(&self, model: &str) -> Option<u64> {
+        self.catalog()?.get(model)?.context_length
+    }
+
+    /// Returns the completion tokens left in `model`'s context window after the estimated
+    /// prompt of `messages` and `margin` spare tokens, or `None` if the context length is
+    /// unknown or nothing is left.
+    pub fn remaining_context(&self, model: &str, messages: &[Message], margin: u32) -> Option<u32> {
+        let used = estimate_messages_tokens(messages) as u64 + u64::from(margin);
+        let remaining = self.context_length(model)?.checked_sub(used)?;
+        (remaining > 0).then(|| u32::try_from(remaining).unwrap_or(u32::MAX))
+    }
+
+    /// Checks that `messages` plus `max_tokens` completion tokens fit in `model`'s context
+    /// window, using the cached catalog regardless of its age and the heuristic token
+    /// estimate. Passes if no catalog is cached or the model's context length is unknown.
+    pub fn check_context_length(
+        &self,
+        model: &str,
+        messages: &[Message],
+        max_tokens: Option<u32>,
+    ) -> Result<()> {
+        let Some(limit) = self.context_length(model) else {
+            return Ok(());
+        };
+        let tokens = estimate_messages_tokens(messages) as u64 + u64::from(max_tokens.unwrap_or(0));
+        if tokens > limit {
+            return Err(Error::ContextLengthExceeded {
+                model: model.to_string(),
+                tokens,
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns true if a catalog is cached and younger than the TTL.
+    pub fn is_fresh(&self) -> bool {
+        self.entry
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|(fetched, _)| fetched.elapsed() < self.ttl)
+    }
+
+    /// Replaces the cached catalog.
+    pub fn store(&self, catalog: ModelsResponse) {
+        *self.entry.lock().unwrap() = Some((Instant::now(), Arc::new(catalog)));
+    }
+
+    /// Drops the cached catalog, so the next lookup fetches it again.
+    pub fn invalidate(&self) {
+        *self.entry.lock().unwrap() = None;
+    }
+
+    #[cfg(feature = "reqwest")]
+    pub(crate) fn lookup(&self) -> CacheLookup {
+        match *self.entry.lock().unwrap() {
+            Some((fetched, ref catalog)) if fetched.elapsed() < self.ttl => {
+                CacheLookup::Fresh(catalog.clone())
+            }
+            Some((_, ref catalog)) if self.background_refresh => {
+                CacheLookup::Stale(catalog.clone())
+            }
+            _ => CacheLookup::Miss,
+        }
+    }
+
+    /// Claims the background refresh; returns false if one is already running.
+    #[cfg(feature = "reqwest")]
+    pub(crate) fn begin_refresh(&self) -> bool {
+        !self.refreshing.swap(true, Ordering::AcqRel)
+    }
+
+    /// Releases the claim taken by [`begin_refresh`](Self::begin_refresh).
+    #[cfg(feature = "reqwest")]
+    pub(crate) fn end_refresh(&self) {
+        self.refreshing.store(false, Ordering::Release);
+    }
+}
+
+/// The date the embedded catalog snapshot was taken.
+#[cfg(feature = "offline-catalog")]
+pub const SNAPSHOT_DATE: &str = "2026-10-14";
+
+/// Returns the model catalog compiled into the crate. Prices and context lengths reflect
+/// [`SNAPSHOT_DATE`] and may have changed since.
+#[cfg(feature = "offline-catalog")]
+pub fn snapshot() -> &'static ModelsResponse {
+    static SNAPSHOT: OnceLock<ModelsResponse> = OnceLock::new();
+    SNAPSHOT.get_or_init(|| {
+        serde_json::from_str(include_str!("snapshot.json"))
+            .expect("embedded catalog snapshot is valid")
+    })
+}
+
+/// Returns true if `error` means the models endpoint could not be reached or failed on the
+/// server side, as opposed to rejecting the request.
+#[cfg(all(feature = "offline-catalog", feature = "reqwest"))]
+pub(crate) fn is_unreachable(error: &crate::error::Error) -> bool {
+    error.status_code().is_none_or(|code| code >= 500)
+}
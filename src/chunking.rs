@@ -0,0 +1,119 @@
+/*!
+   # Chunking Module
+
+   Splits long documents into overlapping, token-bounded pieces for map-reduce summarization
+   or retrieval pipelines built on this client. A [`Chunker`] keeps every [`TextChunk`] within
+   its token limit, as measured by the estimator in [`utils::tokens`](crate::utils::tokens),
+   and prefers to cut at paragraph breaks, then line breaks, then sentence ends, then
+   whitespace. Consecutive chunks share roughly `overlap` tokens of context so facts spanning
+   a cut are not lost.
+*/
+
+use crate::utils::tokens::{estimate_tokens_for_chars, CHARS_PER_TOKEN};
+
+/// A piece of a larger text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextChunk {
+    /// The text of the chunk.
+    pub text: String,
+    /// Byte offset of the chunk in the original text.
+    pub start: usize,
+    /// Byte offset just past the chunk in the original text.
+    pub end: usize,
+    /// Estimated number of tokens in the chunk.
+    pub tokens: usize,
+}
+
+/// Splits texts into overlapping chunks of at most `max_tokens` estimated tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chunker {
+    max_tokens: usize,
+    overlap: usize,
+}
+
+impl Chunker {
+    /// Creates a chunker producing chunks of at most `max_tokens` tokens (at least 1),
+    /// without overlap.
+    pub fn new(max_tokens: usize) -> Self {
+        Self {
+            max_tokens: max_tokens.max(1),
+            overlap: 0,
+        }
+    }
+
+    /// Repeats about `tokens` tokens of each chunk at the start of the next one. The overlap
+    /// is capped at half the chunk size so every chunk makes progress.
+    pub fn with_overlap(mut self, tokens: usize) -> Self {
+        self.overlap = tokens.min(self.max_tokens / 2);
+        self
+    }
+
+    /// The maximum number of tokens per chunk.
+    pub fn max_tokens(&self) -> usize {
+        self.max_tokens
+    }
+
+    /// The number of tokens repeated between consecutive chunks.
+    pub fn overlap(&self) -> usize {
+        self.overlap
+    }
+
+    /// Splits `text` into chunks, in order. Empty text yields no chunks.
+    pub fn chunk(&self, text: &str) -> Vec<TextChunk> {
+        let chars: Vec<char> = text.chars().collect();
+        let offsets: Vec<usize> = text
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain(std::iter::once(text.len()))
+            .collect();
+        let max_chars = self.max_tokens * CHARS_PER_TOKEN;
+        let overlap_chars = self.overlap * CHARS_PER_TOKEN;
+
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        while start < chars.len() {
+            let limit = (start + max_chars).min(chars.len());
+            let end = if limit == chars.len() {
+                limit
+            } else {
+                best_break(&chars, start + max_chars / 2, limit).unwrap_or(limit)
+            };
+            chunks.push(TextChunk {
+                text: text[offsets[start]..offsets[end]].to_string(),
+                start: offsets[start],
+                end: offsets[end],
+                tokens: estimate_tokens_for_chars(end - start),
+            });
+            if end == chars.len() {
+                break;
+            }
+            // Start the next chunk at a word boundary inside the overlap, if there is one.
+            let overlap_start = end.saturating_sub(overlap_chars).max(start + 1);
+            start = (overlap_start..end)
+                .find(|&i| chars[i - 1].is_whitespace() && !chars[i].is_whitespace())
+                .unwrap_or(if overlap_chars == 0 {
+                    end
+                } else {
+                    overlap_start
+                });
+        }
+        chunks
+    }
+}
+
+/// Finds the preferred cut in `chars[min..=max]`, searching backwards from `max`: after a
+/// blank line, a line break, a sentence end, or whitespace, in that order of preference.
+fn best_break(chars: &[char], min: usize, max: usize) -> Option<usize> {
+    let cuts = || (min.max(1)..=max).rev();
+    let paragraph = |i: usize| i >= 2 && chars[i - 1] == '\n' && chars[i - 2] == '\n';
+    let line = |i: usize| chars[i - 1] == '\n';
+    let sentence = |i: usize| {
+        i >= 2 && chars[i - 1].is_whitespace() && matches!(chars[i - 2], '.' | '!' | '?')
+    };
+    let word = |i: usize| chars[i - 1].is_whitespace();
+    cuts()
+        .find(|&i| paragraph(i))
+        .or_else(|| cuts().find(|&i| line(i)))
+        .or_else(|| cuts().find(|&i| sentence(i)))
+        .or_else(|| cuts().find(|&i| word(i)))
+}
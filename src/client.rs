@@ -1,13 +1,122 @@
 // openrouter_api/src/client.rs
 
+use crate::audit::AuditSink;
+#[cfg(feature = "reqwest")]
+use crate::balancer::LoadBalancer;
+use crate::budget::CostBudget;
+use crate::catalog::ModelCache;
 use crate::error::{Error, Result};
-#[allow(unused_imports)]
+#[cfg(feature = "reqwest")]
+use crate::lifecycle::Lifecycle;
+use crate::models::parameters::DefaultParams;
+use crate::models::provider_preferences::{ProviderPreferences, ProviderSort};
+use crate::moderation::ModerationHook;
+use crate::redaction::{redact_body, Redactor};
+use crate::retry::{ExponentialBackoff, RetryPolicy};
+use crate::scheduler::{Priority, Scheduler};
+#[cfg(feature = "reqwest")]
+use crate::throttle::TokenRateLimiter;
+use crate::transport::Transport;
 use crate::types;
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use http::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE, USER_AGENT};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "reqwest")]
 use std::marker::PhantomData;
+use std::sync::Arc;
 use std::time::Duration;
 use url::Url;
 
+/// The `User-Agent` product token identifying this crate.
+pub const DEFAULT_USER_AGENT: &str =
+    concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Requests `join_all` keeps in flight at once unless configured otherwise.
+const DEFAULT_MAX_CONCURRENCY: usize = 8;
+
+/// A fully prepared HTTP request, exactly as the client would send it.
+///
+/// Returned by the `dry_run` methods for debugging and golden-file testing.
+#[derive(Debug, Clone)]
+pub struct PreparedRequest {
+    /// The HTTP method.
+    pub method: http::Method,
+    /// The absolute endpoint URL.
+    pub url: Url,
+    /// The request headers, including authorization.
+    pub headers: HeaderMap,
+    /// The JSON request body.
+    pub body: serde_json::Value,
+    /// Labels attached by the client and the request; never sent to the API.
+    pub tags: BTreeMap<String, String>,
+}
+
+impl PreparedRequest {
+    /// Renders this request as a copy-pasteable curl command.
+    ///
+    /// The API key is masked: the `Authorization` header references the
+    /// `$OPENROUTER_API_KEY` environment variable instead of the real key.
+    pub fn to_curl(&self) -> String {
+        let mut command = format!("curl -X {} {}", self.method, shell_quote(self.url.as_str()));
+        for (name, value) in &self.headers {
+            if name == AUTHORIZATION {
+                command.push_str(" \\\n  -H \"Authorization: Bearer $OPENROUTER_API_KEY\"");
+            } else {
+                let header = format!("{}: {}", name, value.to_str().unwrap_or_default());
+                command.push_str(&format!(" \\\n  -H {}", shell_quote(&header)));
+            }
+        }
+        command.push_str(&format!(
+            " \\\n  -d {}",
+            shell_quote(&self.body.to_string())
+        ));
+        command
+    }
+}
+
+/// Parses a base URL for the API. The URL must include a trailing slash.
+#[cfg(feature = "reqwest")]
+pub(crate) fn parse_base_url(base_url: &str) -> Result<Url> {
+    Url::parse(base_url).map_err(|e| Error::ApiError {
+        code: 400,
+        message: format!("Invalid base URL: {}", e),
+        metadata: None,
+    })
+}
+
+/// Quotes a value for POSIX shells using single quotes.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// TLS implementation used by the HTTP client.
+///
+/// Only backends enabled through Cargo features are available; when both are
+/// compiled in, the choice can be made at runtime with `with_tls_backend()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlsBackend {
+    /// rustls (feature `rustls`, enabled by default).
+    #[cfg(feature = "rustls")]
+    Rustls,
+    /// The platform TLS library, e.g. OpenSSL (feature `native-tls`).
+    #[cfg(feature = "native-tls")]
+    NativeTls,
+}
+
+/// Named bundles of client settings for common deployment profiles.
+///
+/// Apply with [`ClientConfig::preset`] or `with_preset()` on the builder, then
+/// tweak individual settings as needed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// Short timeout, a single quick retry, and latency-sorted providers.
+    LowLatency,
+    /// Generous timeout, several retries, and price-sorted providers.
+    Cheapest,
+    /// Providers must support every request parameter and fallbacks are disabled.
+    Strict,
+}
+
 /// Client configuration containing API key, base URL, and additional settings.
 #[derive(Debug, Clone)]
 pub struct ClientConfig {
@@ -15,10 +124,182 @@ pub struct ClientConfig {
     pub base_url: Url,
     pub http_referer: Option<String>,
     pub site_title: Option<String>,
+    /// Application product identifier appended to the default `User-Agent`, e.g. `myapp/1.2`.
+    pub user_agent: Option<String>,
     pub timeout: Duration,
+    /// System prompt prepended to chat requests that do not define their own.
+    pub system_prompt: Option<String>,
+    /// Provider preferences used for chat requests that do not set their own.
+    pub default_provider: Option<ProviderPreferences>,
+    /// Number of times a failed request is retried (0 disables retries).
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled on each subsequent attempt.
+    pub retry_backoff: Duration,
+    /// Custom retry policy used instead of the built-in exponential backoff.
+    pub retry_policy: Option<Arc<dyn RetryPolicy>>,
+    /// Maximum number of requests `join_all` keeps in flight at once.
+    pub max_concurrency: usize,
+    /// Priority scheduler gating every HTTP request, if any.
+    pub scheduler: Option<Arc<Scheduler>>,
+    /// Priority of requests sent with this configuration.
+    pub priority: Priority,
+    /// Model used by requests that don't name one.
+    pub default_model: Option<String>,
+    /// Generation parameters applied to requests that don't set them.
+    pub default_params: DefaultParams,
+    /// Default parameters for requests to specific models, keyed by model id or alias.
+    /// They take precedence over `default_params`.
+    pub model_params: BTreeMap<String, DefaultParams>,
+    /// Logical model names mapped to model ids, resolved when a request is prepared.
+    pub model_aliases: HashMap<String, String>,
+    /// Scrubber applied to outbound message content and prompts.
+    pub redactor: Option<Arc<dyn Redactor>>,
+    /// Spending limits checked before every chat completion.
+    pub budget: Option<Arc<CostBudget>>,
+    /// Hook that may veto chat requests before they are sent.
+    pub moderation: Option<Arc<dyn ModerationHook>>,
+    /// Cache of the models catalog consulted by `models().list()`.
+    pub model_cache: Option<Arc<ModelCache>>,
+    /// Safety margin, in tokens, for filling in `max_tokens` from the model's context window;
+    /// `None` leaves unset `max_tokens` to the API.
+    pub auto_max_tokens: Option<u32>,
+    /// Whether to move each chat request's token limit into the field its model accepts
+    /// (`max_tokens` or `max_completion_tokens`).
+    pub map_token_limit: bool,
+    /// Follow-up requests allowed per chat completion to continue a reply truncated by the
+    /// token limit; 0 disables auto-continue.
+    pub max_continuations: usize,
+    /// Sink receiving an audit record for every chat completion.
+    pub audit_sink: Option<Arc<dyn AuditSink>>,
+    /// If true, audit records include a hash of the prompt messages.
+    pub audit_prompt_hash: bool,
+    /// Custom transport used instead of the built-in HTTP client.
+    pub transport: Option<Arc<dyn Transport>>,
+    /// How chat streams behave when their consumer reads slower than chunks arrive.
+    #[cfg(feature = "reqwest")]
+    pub stream_buffer: crate::api::stream::StreamBuffer,
+    /// Labels attached to every request sent with this configuration.
+    pub tags: BTreeMap<String, String>,
+    /// How long a stream may go without receiving any bytes before it fails with
+    /// `Error::StreamStalled`; `None` waits indefinitely.
+    pub stream_idle_timeout: Option<Duration>,
+    /// Backends requests are spread over instead of being sent to `base_url`.
+    #[cfg(feature = "reqwest")]
+    pub load_balancer: Option<Arc<LoadBalancer>>,
+    /// Tokens-per-minute limiter pacing chat completions, if any.
+    #[cfg(feature = "reqwest")]
+    pub token_limiter: Option<Arc<TokenRateLimiter>>,
+    /// In-flight request tracking shared by every handle created from the client.
+    #[cfg(feature = "reqwest")]
+    pub(crate) lifecycle: Arc<Lifecycle>,
+    /// Additional root certificates to trust, e.g. for TLS-intercepting proxies.
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    pub root_certificates: Vec<reqwest::Certificate>,
+    /// Client certificate presented for mutual TLS.
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    pub identity: Option<reqwest::Identity>,
+    /// If true, system proxy settings (such as `HTTPS_PROXY`) are ignored.
+    pub no_proxy: bool,
+    /// Explicit TLS backend; `None` uses reqwest's default for the enabled features.
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    pub tls_backend: Option<TlsBackend>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            api_key: None,
+            // Default base URL; can be overridden with with_base_url().
+            base_url: "https://openrouter.ai/api/v1/".parse().unwrap(),
+            http_referer: None,
+            site_title: None,
+            user_agent: None,
+            timeout: Duration::from_secs(30),
+            system_prompt: None,
+            default_provider: None,
+            max_retries: 0,
+            retry_backoff: Duration::from_millis(500),
+            retry_policy: None,
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            scheduler: None,
+            priority: Priority::default(),
+            default_model: None,
+            default_params: DefaultParams::default(),
+            model_params: BTreeMap::new(),
+            model_aliases: HashMap::new(),
+            redactor: None,
+            budget: None,
+            moderation: None,
+            model_cache: None,
+            auto_max_tokens: None,
+            map_token_limit: false,
+            max_continuations: 0,
+            audit_sink: None,
+            audit_prompt_hash: false,
+            transport: None,
+            #[cfg(feature = "reqwest")]
+            stream_buffer: Default::default(),
+            stream_idle_timeout: None,
+            tags: BTreeMap::new(),
+            #[cfg(feature = "reqwest")]
+            load_balancer: None,
+            #[cfg(feature = "reqwest")]
+            token_limiter: None,
+            #[cfg(feature = "reqwest")]
+            lifecycle: Arc::default(),
+            #[cfg(any(feature = "rustls", feature = "native-tls"))]
+            root_certificates: Vec::new(),
+            #[cfg(any(feature = "rustls", feature = "native-tls"))]
+            identity: None,
+            no_proxy: false,
+            #[cfg(any(feature = "rustls", feature = "native-tls"))]
+            tls_backend: None,
+        }
+    }
 }
 
 impl ClientConfig {
+    /// Returns a default configuration with the given preset applied.
+    pub fn preset(preset: Preset) -> Self {
+        let mut config = Self::default();
+        config.apply_preset(preset);
+        config
+    }
+
+    /// Applies a preset's timeout, retry, and provider routing settings to this configuration.
+    pub fn apply_preset(&mut self, preset: Preset) {
+        match preset {
+            Preset::LowLatency => {
+                self.timeout = Duration::from_secs(15);
+                self.max_retries = 1;
+                self.retry_backoff = Duration::from_millis(200);
+                self.default_provider = Some(ProviderPreferences {
+                    sort: Some(ProviderSort::Latency),
+                    ..Default::default()
+                });
+            }
+            Preset::Cheapest => {
+                self.timeout = Duration::from_secs(120);
+                self.max_retries = 3;
+                self.retry_backoff = Duration::from_secs(1);
+                self.default_provider = Some(ProviderPreferences {
+                    sort: Some(ProviderSort::Price),
+                    ..Default::default()
+                });
+            }
+            Preset::Strict => {
+                self.timeout = Duration::from_secs(60);
+                self.max_retries = 2;
+                self.retry_backoff = Duration::from_millis(500);
+                self.default_provider = Some(ProviderPreferences {
+                    require_parameters: Some(true),
+                    allow_fallbacks: Some(false),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
     /// Build HTTP headers required for making API calls.
     /// Returns an error if any header value cannot be constructed.
     pub fn build_headers(&self) -> Result<HeaderMap> {
@@ -30,6 +311,9 @@ impl ClientConfig {
         }
         // Content-Type header is always valid.
         headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        let user_agent = HeaderValue::from_str(&self.user_agent_string())
+            .map_err(|e| Error::ConfigError(format!("Invalid User-Agent header: {}", e)))?;
+        headers.insert(USER_AGENT, user_agent);
         if let Some(ref referer) = self.http_referer {
             let ref_value = HeaderValue::from_str(referer)
                 .map_err(|e| Error::ConfigError(format!("Invalid Referer header: {}", e)))?;
@@ -42,13 +326,209 @@ impl ClientConfig {
         }
         Ok(headers)
     }
+
+    /// Returns the delay before retrying after `error` on the given attempt (starting at 1),
+    /// or `None` to give up, as decided by the retry policy or the built-in backoff.
+    pub fn retry_delay(&self, error: &Error, attempt: u32) -> Option<Duration> {
+        match self.retry_policy {
+            Some(ref policy) => policy.should_retry(error, attempt),
+            None => ExponentialBackoff::new(self.max_retries, self.retry_backoff)
+                .should_retry(error, attempt),
+        }
+    }
+
+    /// Returns the `User-Agent` sent with every request: [`DEFAULT_USER_AGENT`], followed by
+    /// the application's product identifier if one is configured.
+    pub fn user_agent_string(&self) -> String {
+        match self.user_agent {
+            Some(ref product) => format!("{} {}", DEFAULT_USER_AGENT, product),
+            None => DEFAULT_USER_AGENT.to_string(),
+        }
+    }
+
+    /// Returns the default parameters that apply to requests to `model`, most specific
+    /// first: the model's own defaults, if any, then the client-wide ones. Model defaults
+    /// may be keyed by an alias or by the id it stands for; the defaults keyed by the name
+    /// the request uses win, then those keyed by the id, then those of the first other
+    /// alias of the id in alphabetical order.
+    fn params_for(&self, model: &str) -> impl Iterator<Item = &DefaultParams> {
+        let id = self.resolve_model(model);
+        self.model_params
+            .get(model)
+            .or_else(|| self.model_params.get(id))
+            .or_else(|| {
+                self.model_params
+                    .iter()
+                    .find(|(key, _)| self.resolve_model(key) == id)
+                    .map(|(_, params)| params)
+            })
+            .into_iter()
+            .chain(std::iter::once(&self.default_params))
+    }
+
+    /// Applies client-level defaults to a chat request before it is sent.
+    ///
+    /// This prepends the configured system prompt unless the request already
+    /// contains a system message, and fills in the default model, generation
+    /// parameters, and provider preferences for any the request leaves unset.
+    /// Defaults configured for the request's model take precedence over client-wide ones.
+    pub fn apply_chat_defaults(&self, request: &mut types::chat::ChatCompletionRequest) {
+        if request.model.trim().is_empty() {
+            if let Some(ref model) = self.default_model {
+                request.model.clone_from(model);
+            }
+        }
+        for defaults in self.params_for(&request.model) {
+            request.sampling.fill_from(&defaults.sampling);
+            if request.max_completion_tokens.is_none() {
+                request.max_tokens = request.max_tokens.or(defaults.max_tokens);
+            }
+            if request.provider.is_none() {
+                request.provider.clone_from(&defaults.provider);
+            }
+        }
+        if request.provider.is_none() {
+            request.provider = self.default_provider.clone();
+        }
+        if let Some(ref prompt) = self.system_prompt {
+            if !request.messages.iter().any(|m| m.role == "system") {
+                request
+                    .messages
+                    .insert(0, types::chat::Message::system(prompt.as_str()));
+            }
+        }
+    }
+
+    /// Applies client-level defaults to a text completion request before it is sent.
+    ///
+    /// The default model is used if the request names none, and default generation
+    /// parameters are added to `extra_params` unless the request already sets them,
+    /// either in `sampling` or in `extra_params`. Defaults configured for the request's
+    /// model take precedence over client-wide ones.
+    pub fn apply_completion_defaults(&self, request: &mut types::completion::CompletionRequest) {
+        if request.model.trim().is_empty() {
+            if let Some(ref model) = self.default_model {
+                request.model.clone_from(model);
+            }
+        }
+        let layers: Vec<&DefaultParams> = self
+            .params_for(&request.model)
+            .filter(|defaults| !defaults.is_empty())
+            .collect();
+        if layers.is_empty() {
+            return;
+        }
+        if !request.extra_params.is_object() {
+            request.extra_params = serde_json::Value::Object(serde_json::Map::new());
+        }
+        let explicit = serde_json::to_value(&request.sampling).unwrap_or_default();
+        for layer in layers {
+            if let (Some(params), Ok(serde_json::Value::Object(defaults))) = (
+                request.extra_params.as_object_mut(),
+                serde_json::to_value(layer),
+            ) {
+                for (key, value) in defaults {
+                    if explicit.get(&key).is_none() {
+                        params.entry(key).or_insert(value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the model id for `model`, resolving it through the alias map.
+    pub fn resolve_model<'a>(&'a self, model: &'a str) -> &'a str {
+        self.model_aliases.get(model).map_or(model, String::as_str)
+    }
+
+    /// Replaces aliased names in the body's `model` and `models` fields with their ids.
+    fn resolve_model_aliases(&self, body: &mut serde_json::Value) {
+        if self.model_aliases.is_empty() {
+            return;
+        }
+        let resolve = |value: &mut serde_json::Value| {
+            if let Some(id) = value.as_str().and_then(|name| self.model_aliases.get(name)) {
+                *value = serde_json::Value::String(id.clone());
+            }
+        };
+        if let Some(model) = body.get_mut("model") {
+            resolve(model);
+        }
+        if let Some(models) = body.get_mut("models").and_then(|m| m.as_array_mut()) {
+            models.iter_mut().for_each(resolve);
+        }
+    }
+
+    /// Builds the URL, headers, and JSON body for a call to the endpoint at `path`
+    /// (relative to the base URL). A `Value::Null` body is not sent. Model aliases are
+    /// resolved, and the configured redactor, if any, is applied to message content and
+    /// prompts in the body.
+    pub fn prepare_request<T: Serialize>(
+        &self,
+        method: http::Method,
+        path: &str,
+        body: &T,
+    ) -> Result<PreparedRequest> {
+        let url = self.base_url.join(path).map_err(|e| Error::ApiError {
+            code: 400,
+            message: format!("Invalid URL for {}: {}", path, e),
+            metadata: None,
+        })?;
+        let mut body = serde_json::to_value(body).map_err(|e| Error::ApiError {
+            code: 500,
+            message: format!("Request serialization error: {}", e),
+            metadata: None,
+        })?;
+        self.resolve_model_aliases(&mut body);
+        if let Some(ref redactor) = self.redactor {
+            redact_body(redactor.as_ref(), &mut body);
+        }
+        Ok(PreparedRequest {
+            method,
+            url,
+            headers: self.build_headers()?,
+            body,
+            tags: self.tags.clone(),
+        })
+    }
+
+    /// Applies defaults, validates, and serializes a chat request into the exact
+    /// URL, headers, and body that will be sent. Once a models catalog is cached, the
+    /// request is also checked against the model's context window.
+    pub fn prepare_chat_request(
+        &self,
+        request: &types::chat::ChatCompletionRequest,
+    ) -> Result<PreparedRequest> {
+        let mut request = request.clone();
+        self.apply_chat_defaults(&mut request);
+        request.validate()?;
+        if let Some(ref cache) = self.model_cache {
+            let model = self.resolve_model(&request.model);
+            if let (None, Some(margin)) = (request.token_limit(), self.auto_max_tokens) {
+                request.max_tokens = cache.remaining_context(model, &request.messages, margin);
+            }
+            cache.check_context_length(model, &request.messages, request.token_limit())?;
+        }
+        if self.map_token_limit {
+            let model = self.resolve_model(&request.model).to_string();
+            request.map_token_limit(&model);
+        }
+        let mut prepared =
+            self.prepare_request(http::Method::POST, "chat/completions", &request)?;
+        prepared.tags.extend(request.tags);
+        Ok(prepared)
+    }
 }
 
 // Type‑state markers.
+#[cfg(feature = "reqwest")]
 pub struct Unconfigured;
+#[cfg(feature = "reqwest")]
 pub struct NoAuth;
+#[cfg(feature = "reqwest")]
 pub struct Ready;
 
+#[cfg(feature = "reqwest")]
 /// Main OpenRouter client using a type‑state builder pattern.
 pub struct OpenRouterClient<State = Unconfigured> {
     pub config: ClientConfig,
@@ -56,18 +536,19 @@ pub struct OpenRouterClient<State = Unconfigured> {
     pub _state: PhantomData<State>,
 }
 
+#[cfg(feature = "reqwest")]
+impl Default for OpenRouterClient<Unconfigured> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "reqwest")]
 impl OpenRouterClient<Unconfigured> {
     /// Creates a new unconfigured client.
     pub fn new() -> Self {
         Self {
-            config: ClientConfig {
-                api_key: None,
-                // Default base URL; can be overridden with with_base_url().
-                base_url: "https://openrouter.ai/api/v1/".parse().unwrap(),
-                http_referer: None,
-                site_title: None,
-                timeout: Duration::from_secs(30),
-            },
+            config: ClientConfig::default(),
             http_client: None,
             _state: PhantomData,
         }
@@ -79,11 +560,7 @@ impl OpenRouterClient<Unconfigured> {
         mut self,
         base_url: impl Into<String>,
     ) -> Result<OpenRouterClient<NoAuth>> {
-        self.config.base_url = Url::parse(&base_url.into()).map_err(|e| Error::ApiError {
-            code: 400,
-            message: format!("Invalid base URL: {}", e),
-            metadata: None,
-        })?;
+        self.config.base_url = parse_base_url(&base_url.into())?;
         Ok(self.transition_to_no_auth())
     }
 
@@ -96,6 +573,7 @@ impl OpenRouterClient<Unconfigured> {
     }
 }
 
+#[cfg(feature = "reqwest")]
 impl OpenRouterClient<NoAuth> {
     /// Supplies the API key and transitions to the Ready state.
     pub fn with_api_key(mut self, api_key: impl Into<String>) -> Result<OpenRouterClient<Ready>> {
@@ -109,6 +587,170 @@ impl OpenRouterClient<NoAuth> {
         self
     }
 
+    /// Applies a named preset of timeout, retry, and provider routing settings.
+    /// Settings made after this call override the preset.
+    pub fn with_preset(mut self, preset: Preset) -> Self {
+        self.config.apply_preset(preset);
+        self
+    }
+
+    /// Optionally sets how many times failed requests are retried, and the
+    /// initial backoff delay (doubled on each attempt).
+    pub fn with_retries(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.config.max_retries = max_retries;
+        self.config.retry_backoff = backoff;
+        self
+    }
+
+    /// Optionally replaces the built-in exponential backoff with a custom [`RetryPolicy`].
+    /// `max_retries` and the backoff delay are ignored while a custom policy is set.
+    pub fn with_retry_policy(mut self, policy: Arc<dyn RetryPolicy>) -> Self {
+        self.config.retry_policy = Some(policy);
+        self
+    }
+
+    /// Optionally sets how many requests `join_all` keeps in flight at once (default 8).
+    /// Values below 1 are treated as 1.
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.config.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Optionally routes every request through `scheduler`, which caps the requests in
+    /// flight and admits waiting ones by priority. Share one scheduler between clients to
+    /// cap them together.
+    pub fn with_scheduler(mut self, scheduler: Arc<Scheduler>) -> Self {
+        self.config.scheduler = Some(scheduler);
+        self
+    }
+
+    /// Optionally sets the default scheduling priority of requests sent by this client.
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.config.priority = priority;
+        self
+    }
+
+    /// Optionally sets the model used by chat and text completion requests that leave
+    /// `model` empty.
+    pub fn with_default_model(mut self, model: impl Into<String>) -> Self {
+        self.config.default_model = Some(model.into());
+        self
+    }
+
+    /// Optionally sets generation parameters (sampling parameters and max_tokens) applied
+    /// to chat and text completion requests that don't set them.
+    pub fn with_default_params(mut self, params: DefaultParams) -> Self {
+        self.config.default_params = params;
+        self
+    }
+
+    /// Optionally sets default parameters (sampling parameters, max_tokens, and provider
+    /// preferences) for requests to `model`, given as an id or alias. They override the
+    /// client-wide defaults from `with_default_params()`, and a request's own settings
+    /// override both.
+    pub fn with_model_params(mut self, model: impl Into<String>, params: DefaultParams) -> Self {
+        self.config.model_params.insert(model.into(), params);
+        self
+    }
+
+    /// Optionally maps the logical model name `alias` (e.g. "fast") to the model id
+    /// `model`. Aliases are resolved in the `model` and `models` fields of every request,
+    /// so application code can use stable names while the underlying models change.
+    pub fn with_model_alias(mut self, alias: impl Into<String>, model: impl Into<String>) -> Self {
+        self.config.model_aliases.insert(alias.into(), model.into());
+        self
+    }
+
+    /// Optionally adds several model aliases at once, e.g. loaded from a config file.
+    pub fn with_model_aliases<I, K, V>(mut self, aliases: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.config.model_aliases.extend(
+            aliases
+                .into_iter()
+                .map(|(alias, model)| (alias.into(), model.into())),
+        );
+        self
+    }
+
+    /// Optionally scrubs message content and prompts with `redactor` before any request
+    /// leaves the process.
+    pub fn with_redactor(mut self, redactor: Arc<dyn Redactor>) -> Self {
+        self.config.redactor = Some(redactor);
+        self
+    }
+
+    /// Optionally enforces spending limits on chat completions. Requests that would
+    /// exceed the budget fail with `Error::BudgetExceeded` before being sent.
+    pub fn with_budget(mut self, budget: Arc<CostBudget>) -> Self {
+        self.config.budget = Some(budget);
+        self
+    }
+
+    /// Optionally caches the models catalog, so `models().list()` only hits the network
+    /// once the cached copy is older than the cache's TTL.
+    pub fn with_model_cache(mut self, cache: Arc<ModelCache>) -> Self {
+        self.config.model_cache = Some(cache);
+        self
+    }
+
+    /// Fills in `max_tokens` for chat requests that don't set it (directly or through the
+    /// default parameters) with whatever room the model's context window leaves after the
+    /// estimated prompt, minus `margin` tokens. Requires a models cache holding the catalog
+    /// (see `with_model_cache()`); without one, or for models of unknown context length,
+    /// `max_tokens` is left unset.
+    pub fn with_auto_max_tokens(mut self, margin: u32) -> Self {
+        self.config.auto_max_tokens = Some(margin);
+        self
+    }
+
+    /// Sends each chat request's token limit as `max_completion_tokens` to models that reject
+    /// `max_tokens` (OpenAI's o-series and GPT-5) and as `max_tokens` to all others, whichever
+    /// field the request set, so the same request works across models.
+    pub fn with_token_limit_mapping(mut self) -> Self {
+        self.config.map_token_limit = true;
+        self
+    }
+
+    /// Continues chat completions truncated by the token limit (`finish_reason` of
+    /// `length`) with up to `max_continuations` follow-up requests, each sending the partial
+    /// reply as an assistant prefill, and stitches the pieces into one response. Streams are
+    /// not continued.
+    pub fn with_auto_continue(mut self, max_continuations: usize) -> Self {
+        self.config.max_continuations = max_continuations;
+        self
+    }
+
+    /// Optionally runs `hook` on every chat request before it is sent; requests it vetoes
+    /// fail with `Error::BlockedByPolicy` without reaching the API.
+    pub fn with_moderation(mut self, hook: Arc<dyn ModerationHook>) -> Self {
+        self.config.moderation = Some(hook);
+        self
+    }
+
+    /// Optionally sends an [`AuditRecord`](crate::audit::AuditRecord) for every chat
+    /// completion to `sink`.
+    pub fn with_audit_sink(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.config.audit_sink = Some(sink);
+        self
+    }
+
+    /// Optionally includes a hash of the prompt messages in audit records, so identical
+    /// prompts can be correlated without storing their content.
+    pub fn with_audit_prompt_hash(mut self, enabled: bool) -> Self {
+        self.config.audit_prompt_hash = enabled;
+        self
+    }
+
+    /// Optionally sets provider preferences applied to chat requests without their own.
+    pub fn with_default_provider_preferences(mut self, preferences: ProviderPreferences) -> Self {
+        self.config.default_provider = Some(preferences);
+        self
+    }
+
     /// Optionally sets the HTTP referer header.
     pub fn with_http_referer(mut self, referer: impl Into<String>) -> Self {
         self.config.http_referer = Some(referer.into());
@@ -121,11 +763,121 @@ impl OpenRouterClient<NoAuth> {
         self
     }
 
+    /// Sets how chat streams behave when their consumer reads slower than chunks arrive:
+    /// backpressure on the connection (the default), a bounded read-ahead buffer, or
+    /// coalescing deltas into fewer, larger chunks.
+    pub fn with_stream_buffer(mut self, buffer: crate::api::stream::StreamBuffer) -> Self {
+        self.config.stream_buffer = buffer;
+        self
+    }
+
+    /// Attaches the label `key = value` to every request, e.g. the tenant or service name.
+    /// Labels are never sent to the API; they travel with each prepared request to
+    /// transports, audit records, and logs for cost attribution.
+    pub fn with_tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.tags.insert(key.into(), value.into());
+        self
+    }
+
+    /// Fails chat streams with `Error::StreamStalled` when no bytes, not even keep-alive
+    /// comments, arrive for `timeout`, so a hung upstream connection cannot block the
+    /// consumer forever. A stall before the first chunk is retried like other transient
+    /// failures.
+    pub fn with_stream_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.config.stream_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Optionally spreads requests over the backends of `balancer`, failing over between
+    /// them, instead of sending everything to the base URL. Endpoint paths are resolved
+    /// against the chosen backend.
+    pub fn with_load_balancer(mut self, balancer: Arc<LoadBalancer>) -> Self {
+        self.config.load_balancer = Some(balancer);
+        self
+    }
+
+    /// Optionally paces chat completions with `limiter`, holding each request back until
+    /// its estimated prompt and completion tokens fit in the tokens-per-minute allowance.
+    /// Share one limiter between clients that draw from the same quota.
+    pub fn with_token_rate_limiter(mut self, limiter: Arc<TokenRateLimiter>) -> Self {
+        self.config.token_limiter = Some(limiter);
+        self
+    }
+
+    /// Optionally appends an application product identifier, such as `myapp/1.2`, to the
+    /// default `User-Agent` so traffic is attributable in upstream logs.
+    pub fn with_user_agent(mut self, product: impl Into<String>) -> Self {
+        self.config.user_agent = Some(product.into());
+        self
+    }
+
+    /// Optionally replaces the built-in HTTP client with a custom [`Transport`],
+    /// such as the record/replay transport from the `replay` feature.
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.config.transport = Some(transport);
+        self
+    }
+
+    /// Optionally trusts an additional root certificate, for example the CA of a
+    /// TLS-intercepting corporate proxy. May be called multiple times.
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    pub fn with_root_certificate(mut self, certificate: reqwest::Certificate) -> Self {
+        self.config.root_certificates.push(certificate);
+        self
+    }
+
+    /// Optionally sets the client certificate used for mutual TLS.
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    pub fn with_identity(mut self, identity: reqwest::Identity) -> Self {
+        self.config.identity = Some(identity);
+        self
+    }
+
+    /// Optionally selects the TLS backend, when more than one is compiled in.
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    pub fn with_tls_backend(mut self, backend: TlsBackend) -> Self {
+        self.config.tls_backend = Some(backend);
+        self
+    }
+
+    /// Optionally disables the use of system proxy settings.
+    pub fn with_no_proxy(mut self) -> Self {
+        self.config.no_proxy = true;
+        self
+    }
+
+    /// Optionally sets a system prompt that is prepended to every chat request
+    /// which does not already contain a system message.
+    pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.config.system_prompt = Some(prompt.into());
+        self
+    }
+
     fn transition_to_ready(self) -> Result<OpenRouterClient<Ready>> {
         let headers = self.config.build_headers()?;
-        let http_client = reqwest::Client::builder()
+        let mut builder = reqwest::Client::builder()
             .timeout(self.config.timeout)
-            .default_headers(headers)
+            .default_headers(headers);
+        #[cfg(any(feature = "rustls", feature = "native-tls"))]
+        {
+            for certificate in &self.config.root_certificates {
+                builder = builder.add_root_certificate(certificate.clone());
+            }
+            if let Some(ref identity) = self.config.identity {
+                builder = builder.identity(identity.clone());
+            }
+            builder = match self.config.tls_backend {
+                #[cfg(feature = "rustls")]
+                Some(TlsBackend::Rustls) => builder.use_rustls_tls(),
+                #[cfg(feature = "native-tls")]
+                Some(TlsBackend::NativeTls) => builder.use_native_tls(),
+                None => builder,
+            };
+        }
+        if self.config.no_proxy {
+            builder = builder.no_proxy();
+        }
+        let http_client = builder
             .build()
             .map_err(|e| Error::ConfigError(format!("Failed to create HTTP client: {}", e)))?;
         Ok(OpenRouterClient {
@@ -136,6 +888,7 @@ impl OpenRouterClient<NoAuth> {
     }
 }
 
+#[cfg(feature = "reqwest")]
 impl OpenRouterClient<Ready> {
     /// Provides access to the chat endpoint.
     /// Returns an error if the HTTP client is missing.
@@ -147,13 +900,49 @@ impl OpenRouterClient<Ready> {
         Ok(crate::api::chat::ChatApi::new(client, &self.config))
     }
 
-    /// Returns a new request builder for the completions endpoint.
+    /// Provides access to the generation stats endpoint.
+    /// Returns an error if the HTTP client is missing.
+    pub fn generation(&self) -> Result<crate::api::generation::GenerationApi> {
+        let client = self
+            .http_client
+            .clone()
+            .ok_or_else(|| Error::ConfigError("HTTP client is missing".into()))?;
+        Ok(crate::api::generation::GenerationApi::new(
+            client,
+            &self.config,
+        ))
+    }
+
+    /// Provides access to the usage and limits of the API key.
+    /// Returns an error if the HTTP client is missing.
+    pub fn key(&self) -> Result<crate::api::key::KeyApi> {
+        let client = self
+            .http_client
+            .clone()
+            .ok_or_else(|| Error::ConfigError("HTTP client is missing".into()))?;
+        Ok(crate::api::key::KeyApi::new(client, &self.config))
+    }
+
+    /// Provides access to the models catalog endpoint.
+    /// Returns an error if the HTTP client is missing.
+    pub fn models(&self) -> Result<crate::api::models::ModelsApi> {
+        let client = self
+            .http_client
+            .clone()
+            .ok_or_else(|| Error::ConfigError("HTTP client is missing".into()))?;
+        Ok(crate::api::models::ModelsApi::new(client, &self.config))
+    }
+
+    /// Returns a new request builder for the completions endpoint. The model and at
+    /// least one message must be supplied before the payload can be built.
     pub fn completion_request(
         &self,
-        messages: Vec<crate::types::chat::Message>,
-    ) -> crate::api::request::RequestBuilder<serde_json::Value> {
-        let extra_params = serde_json::json!({});
-        crate::api::request::RequestBuilder::new("openai/gpt-4", messages, extra_params)
+    ) -> crate::api::request::RequestBuilder<
+        serde_json::Value,
+        crate::api::request::NoModel,
+        crate::api::request::NoMessages,
+    > {
+        crate::api::request::RequestBuilder::new(serde_json::json!({}))
     }
 
     /// Provides access to the web search endpoint.
@@ -169,91 +958,170 @@ impl OpenRouterClient<Ready> {
         ))
     }
 
-    /// Example chat completion method.
-    pub async fn chat_completion(
-        &self,
-        request: crate::types::chat::ChatCompletionRequest,
-    ) -> Result<crate::types::chat::ChatCompletionResponse> {
-        // Build the full URL by joining relative path.
-        let url = self
-            .config
-            .base_url
-            .join("chat/completions")
-            .map_err(|e| Error::ApiError {
-                code: 400,
-                message: format!("URL join error: {}", e),
-                metadata: None,
-            })?;
+    /// Checks that OpenRouter is reachable and accepts the API key.
+    ///
+    /// Performs a single cheap authenticated call to the key info endpoint, without
+    /// retries, and reports the outcome as a typed status plus the round-trip latency.
+    /// Failures are reported in the returned [`Ping`](types::health::Ping) rather than as
+    /// errors, so it can back a readiness probe directly.
+    pub async fn ping(&self) -> Result<types::health::Ping> {
+        use types::health::{HealthStatus, Ping};
 
         let client = self
             .http_client
             .as_ref()
             .ok_or_else(|| Error::ConfigError("HTTP client is missing".into()))?;
+        let mut config = self.config.clone();
+        config.max_retries = 0;
+        config.retry_policy = None;
+        let prepared =
+            config.prepare_request(http::Method::GET, "key", &serde_json::Value::Null)?;
+
+        let started = std::time::Instant::now();
+        let result = match crate::transport::execute(client, &config, prepared).await {
+            Ok(response) if response.status.is_success() => response.text().await.map(drop),
+            Ok(response) => {
+                // The status alone classifies the failure, even if the body can't be read.
+                let code = response.status.as_u16();
+                Err(Error::ApiError {
+                    code,
+                    message: response
+                        .text()
+                        .await
+                        .unwrap_or_else(|error| error.to_string()),
+                    metadata: None,
+                })
+            }
+            Err(error) => Err(error),
+        };
+        let latency = started.elapsed();
+
+        let (status, error) = match result {
+            Ok(()) => (HealthStatus::Healthy, None),
+            Err(error) => {
+                let status = if error.is_auth_error() {
+                    HealthStatus::Unauthorized
+                } else if error.is_rate_limited() {
+                    HealthStatus::RateLimited
+                } else {
+                    match error.status_code() {
+                        Some(code) => HealthStatus::Degraded { code },
+                        None => HealthStatus::Unreachable,
+                    }
+                };
+                (status, Some(error.to_string()))
+            }
+        };
+        Ok(Ping {
+            status,
+            latency,
+            error,
+        })
+    }
+
+    /// Returns the exact request `chat_completion` would send, without any network call.
+    ///
+    /// All client defaults, validation, header construction, and serialization are applied.
+    pub fn dry_run(
+        &self,
+        request: &crate::types::chat::ChatCompletionRequest,
+    ) -> Result<PreparedRequest> {
+        self.config.prepare_chat_request(request)
+    }
 
-        let response = client
-            .post(url)
-            .headers(self.config.build_headers()?)
-            .json(&request)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            return Err(Error::ApiError {
-                code: response.status().as_u16(),
-                message: response.text().await?,
-                metadata: None,
-            });
+    /// Sends a chat completion request and returns the response.
+    ///
+    /// Shorthand for `self.chat()?.chat_completion(request)`: defaults, validation,
+    /// retries, budgets, moderation, and auditing are applied exactly as on
+    /// [`ChatApi`](crate::api::chat::ChatApi).
+    pub async fn chat_completion(
+        &self,
+        request: crate::types::chat::ChatCompletionRequest,
+    ) -> Result<crate::types::chat::ChatCompletionResponse> {
+        self.chat()?.chat_completion(request).await
+    }
+
+    /// Sends a chat completion request and returns the response with its HTTP status,
+    /// headers, and raw body. Shorthand for `self.chat()?.chat_completion_with_envelope(request)`.
+    pub async fn chat_completion_with_envelope(
+        &self,
+        request: crate::types::chat::ChatCompletionRequest,
+    ) -> Result<crate::types::envelope::ResponseEnvelope<crate::types::chat::ChatCompletionResponse>>
+    {
+        self.chat()?.chat_completion_with_envelope(request).await
+    }
+
+    /// Streams a chat completion. Shorthand for `self.chat()?.chat_completion_stream(request)`;
+    /// if the client cannot create the handle, the stream yields that error.
+    pub fn chat_completion_stream(
+        &self,
+        request: crate::types::chat::ChatCompletionRequest,
+    ) -> crate::api::stream::ChatCompletionStream {
+        match self.chat() {
+            Ok(chat) => chat.chat_completion_stream(request),
+            Err(error) => Box::pin(futures::stream::once(async { Err(error) })),
         }
-        let chat_response: crate::types::chat::ChatCompletionResponse =
-            self.handle_response(response).await?;
-        // Validate any tool calls in the response.
-        self.validate_tool_calls(&chat_response)?;
-        Ok(chat_response)
     }
 
-    async fn handle_response<T>(&self, response: reqwest::Response) -> Result<T>
+    /// Sends a chat completion requesting JSON that matches `schema` in strict mode and
+    /// decodes it into `T`. See [`ChatApi::chat_completion_typed`](crate::api::chat::ChatApi::chat_completion_typed).
+    pub async fn chat_completion_typed<T: serde::de::DeserializeOwned>(
+        &self,
+        request: crate::types::chat::ChatCompletionRequest,
+        name: impl Into<String>,
+        schema: crate::models::structured::JsonSchemaDefinition,
+    ) -> Result<T> {
+        self.chat()?
+            .chat_completion_typed(request, name, schema)
+            .await
+    }
+
+    /// Sends several chat completions concurrently and returns their results in input order.
+    ///
+    /// At most `max_concurrency` requests are in flight at once. Each request goes through
+    /// [`chat_completion`](Self::chat_completion), so the client's retry policy, budget, and
+    /// audit sink apply to every one of them; one failure does not cancel the others.
+    pub async fn join_all<I>(
+        &self,
+        requests: I,
+    ) -> Vec<Result<crate::types::chat::ChatCompletionResponse>>
     where
-        T: serde::de::DeserializeOwned,
+        I: IntoIterator<Item = crate::types::chat::ChatCompletionRequest>,
     {
-        let status = response.status();
-        let body = response.text().await?;
-        if !status.is_success() {
-            return Err(Error::ApiError {
-                code: status.as_u16(),
-                message: body.clone(),
-                metadata: None,
-            });
-        }
-        if body.trim().is_empty() {
-            return Err(Error::ApiError {
-                code: status.as_u16(),
-                message: "Empty response body".into(),
-                metadata: None,
-            });
-        }
-        serde_json::from_str::<T>(&body).map_err(|e| Error::ApiError {
-            code: status.as_u16(),
-            message: format!("Failed to decode JSON: {}. Body was: {}", e, body),
-            metadata: None,
-        })
+        use futures::StreamExt;
+
+        futures::stream::iter(requests)
+            .map(|request| self.chat_completion(request))
+            .buffered(self.config.max_concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Shuts the client down gracefully.
+    ///
+    /// New requests, on this client and on every handle created from it, fail with
+    /// [`Error::ShutDown`] from now on. Requests already in flight, including streams still
+    /// being read, get up to `timeout` to finish; any still running then are aborted and
+    /// listed in the returned report.
+    pub async fn shutdown(&self, timeout: Duration) -> crate::lifecycle::ShutdownReport {
+        self.config.lifecycle.shutdown(timeout).await
     }
 
+    /// Returns true once [`shutdown`](Self::shutdown) has been called.
+    pub fn is_shut_down(&self) -> bool {
+        self.config.lifecycle.is_shut_down()
+    }
+
+    /// The number of requests currently in flight, including streams still being read.
+    pub fn in_flight(&self) -> usize {
+        self.config.lifecycle.in_flight()
+    }
+
+    /// Checks that every tool call in `response` is a function call.
     pub fn validate_tool_calls(
         &self,
         response: &crate::types::chat::ChatCompletionResponse,
     ) -> Result<()> {
-        for choice in &response.choices {
-            if let Some(tool_calls) = &choice.message.tool_calls {
-                for tc in tool_calls {
-                    if tc.kind != "function" {
-                        return Err(Error::SchemaValidationError(format!(
-                            "Invalid tool call kind: {}. Expected 'function'",
-                            tc.kind
-                        )));
-                    }
-                }
-            }
-        }
-        Ok(())
+        crate::api::chat::validate_tool_calls(response)
     }
 }
@@ -0,0 +1,63 @@
+//! Token budget guardrails for conversations.
+//!
+//! A [`TokenBudget`] caps the estimated prompt size of each turn and the total number of
+//! tokens a conversation may consume over its lifetime. When a turn would exceed a limit,
+//! the [`ConversationManager`](super::ConversationManager) either truncates history to fit
+//! or fails with [`Error::TokenBudgetExceeded`](crate::error::Error::TokenBudgetExceeded).
+
+/// What the session manager does when a turn would exceed the token budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowAction {
+    /// Evict history (with the configured truncation strategy, or drop-oldest) until it fits.
+    #[default]
+    Truncate,
+    /// Fail the turn without sending it.
+    Error,
+}
+
+/// Per-conversation token limits.
+#[derive(Debug, Clone, Default)]
+pub struct TokenBudget {
+    /// Maximum estimated prompt tokens for a single turn.
+    pub max_prompt_tokens: Option<usize>,
+    /// Maximum tokens (prompt and completion) consumed by the conversation in total.
+    pub max_total_tokens: Option<u64>,
+    /// What to do when a limit would be exceeded.
+    pub on_exceeded: OverflowAction,
+}
+
+impl TokenBudget {
+    /// Creates a budget without limits that truncates on overflow.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Caps the estimated prompt size of each turn.
+    pub fn with_max_prompt_tokens(mut self, tokens: usize) -> Self {
+        self.max_prompt_tokens = Some(tokens);
+        self
+    }
+
+    /// Caps the total tokens the conversation may consume.
+    pub fn with_max_total_tokens(mut self, tokens: u64) -> Self {
+        self.max_total_tokens = Some(tokens);
+        self
+    }
+
+    /// Sets what happens when a limit would be exceeded.
+    pub fn with_action(mut self, action: OverflowAction) -> Self {
+        self.on_exceeded = action;
+        self
+    }
+
+    /// Returns the largest prompt allowed for the next turn, given the tokens already used.
+    pub fn prompt_limit(&self, tokens_used: u64) -> Option<usize> {
+        let remaining = self
+            .max_total_tokens
+            .map(|max| usize::try_from(max.saturating_sub(tokens_used)).unwrap_or(usize::MAX));
+        match (self.max_prompt_tokens, remaining) {
+            (Some(prompt), Some(remaining)) => Some(prompt.min(remaining)),
+            (prompt, remaining) => prompt.or(remaining),
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Conversation session manager.
+//!
+//! [`ConversationManager`] ties together a chat endpoint and a [`ConversationStore`]:
+//! each call loads the session, appends the new message, compresses or truncates
+//! history if configured, enforces the token budget, sends the request, and persists
+//! the assistant reply.
+
+use super::limits::{OverflowAction, TokenBudget};
+use super::store::ConversationStore;
+use super::summarize::{summarize_if_needed, SummarizationConfig};
+use super::truncation::{DropOldest, TruncationStrategy};
+use super::Conversation;
+use crate::api::chat::ChatApi;
+use crate::error::{Error, Result};
+use crate::types::chat::{ChatCompletionRequest, Message};
+use crate::utils::tokens::{estimate_message_tokens, estimate_messages_tokens};
+use std::sync::Arc;
+
+/// Manages persisted, multi-turn chat sessions.
+pub struct ConversationManager {
+    chat: ChatApi,
+    store: Arc<dyn ConversationStore>,
+    model: String,
+    summarization: Option<SummarizationConfig>,
+    truncation: Option<(Box<dyn TruncationStrategy>, usize)>,
+    token_budget: Option<TokenBudget>,
+}
+
+impl ConversationManager {
+    /// Creates a manager sending requests to `model` and persisting sessions in `store`.
+    pub fn new(chat: ChatApi, store: Arc<dyn ConversationStore>, model: impl Into<String>) -> Self {
+        Self {
+            chat,
+            store,
+            model: model.into(),
+            summarization: None,
+            truncation: None,
+            token_budget: None,
+        }
+    }
+
+    /// Enables automatic summarization of older turns once the history grows too large.
+    pub fn with_summarization(mut self, config: SummarizationConfig) -> Self {
+        self.summarization = Some(config);
+        self
+    }
+
+    /// Evicts history with `strategy` whenever the estimated prompt exceeds `max_context_tokens`.
+    ///
+    /// Truncation runs after summarization, so it only kicks in if summarizing was not enough.
+    pub fn with_truncation(
+        mut self,
+        strategy: impl TruncationStrategy + 'static,
+        max_context_tokens: usize,
+    ) -> Self {
+        self.truncation = Some((Box::new(strategy), max_context_tokens));
+        self
+    }
+
+    /// Enforces per-turn prompt and per-conversation total token limits.
+    ///
+    /// The budget is checked after summarization and truncation. On overflow, history is
+    /// evicted with the configured truncation strategy (or drop-oldest) if the budget's
+    /// action is [`OverflowAction::Truncate`]; if it still does not fit, or the action is
+    /// [`OverflowAction::Error`], the turn fails with `Error::TokenBudgetExceeded`.
+    pub fn with_token_budget(mut self, budget: TokenBudget) -> Self {
+        self.token_budget = Some(budget);
+        self
+    }
+
+    /// Truncates or rejects the conversation if the next turn would exceed the token budget.
+    fn enforce_token_budget(&self, conversation: &mut Conversation) -> Result<()> {
+        let Some(ref budget) = self.token_budget else {
+            return Ok(());
+        };
+        let Some(limit) = budget.prompt_limit(conversation.tokens_used) else {
+            return Ok(());
+        };
+        if estimate_messages_tokens(&conversation.messages) > limit
+            && budget.on_exceeded == OverflowAction::Truncate
+        {
+            match self.truncation {
+                Some((ref strategy, _)) => strategy.truncate(&mut conversation.messages, limit),
+                None => DropOldest.truncate(&mut conversation.messages, limit),
+            }
+        }
+        let tokens = estimate_messages_tokens(&conversation.messages);
+        if tokens > limit {
+            return Err(Error::TokenBudgetExceeded { tokens, limit });
+        }
+        Ok(())
+    }
+
+    /// Loads the conversation with the given id, or starts a new one.
+    pub async fn load(&self, id: &str) -> Result<Conversation> {
+        Ok(self
+            .store
+            .load(id)
+            .await?
+            .unwrap_or_else(|| Conversation::new(id)))
+    }
+
+    /// Forks the first `index` messages of conversation `id` into a new conversation
+    /// `new_id`, saves it, and returns it. Both conversations continue independently.
+    pub async fn fork(&self, id: &str, index: usize, new_id: &str) -> Result<Conversation> {
+        let branch = self.load(id).await?.fork_at(index, new_id)?;
+        self.store.save(&branch).await?;
+        Ok(branch)
+    }
+
+    /// Appends `message` to the conversation `id`, sends it, and returns the assistant reply.
+    ///
+    /// The updated conversation, including the reply, is saved back to the store.
+    pub async fn send(&self, id: &str, message: Message) -> Result<Message> {
+        let mut conversation = self.load(id).await?;
+        conversation.push(message);
+
+        if let Some(ref config) = self.summarization {
+            summarize_if_needed(&self.chat, config, &mut conversation).await?;
+        }
+        if let Some((ref strategy, max_tokens)) = self.truncation {
+            strategy.truncate(&mut conversation.messages, max_tokens);
+        }
+        self.enforce_token_budget(&mut conversation)?;
+
+        let request = ChatCompletionRequest {
+            model: self.model.clone(),
+            messages: conversation.messages.clone(),
+            ..Default::default()
+        };
+        let prompt_tokens = estimate_messages_tokens(&request.messages);
+        let response = self.chat.chat_completion(request).await?;
+        let used = match response.usage {
+            Some(ref usage) => u64::from(usage.total_tokens),
+            None => {
+                let completion_tokens = response
+                    .choices
+                    .first()
+                    .map_or(0, |choice| estimate_message_tokens(&choice.message));
+                (prompt_tokens + completion_tokens) as u64
+            }
+        };
+        conversation.tokens_used += used;
+        let reply = response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message)
+            .ok_or_else(|| Error::ApiError {
+                code: 200,
+                message: "Response contained no choices".into(),
+                metadata: None,
+            })?;
+
+        conversation.push(reply.clone());
+        self.store.save(&conversation).await?;
+        Ok(reply)
+    }
+}
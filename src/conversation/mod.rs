@@ -0,0 +1,109 @@
+/*!
+   # Conversation Module
+
+   This module provides types for keeping multi-turn chat sessions:
+
+   - **Conversation:** An identified, ordered list of chat messages, which can be forked into
+     independent branches sharing a common prefix.
+   - **ConversationStore:** An async persistence trait so sessions can live in Redis, Postgres,
+     or any other backend by implementing a single trait.
+   - **ConversationManager:** Runs chat turns against a store, optionally compressing long
+     histories by summarizing older turns with a cheaper model.
+   - **TokenBudget:** Per-turn prompt and per-conversation total token limits, enforced by
+     truncating history or failing the turn.
+   - **TruncationStrategy:** Decides what is evicted when history no longer fits in context
+     (drop-oldest, sliding window, importance-based, or a custom implementation).
+*/
+
+pub mod limits;
+#[cfg(feature = "reqwest")]
+pub mod manager;
+pub mod store;
+pub mod summarize;
+pub mod truncation;
+
+pub use limits::{OverflowAction, TokenBudget};
+#[cfg(feature = "reqwest")]
+pub use manager::ConversationManager;
+pub use store::{ConversationStore, InMemoryConversationStore};
+pub use summarize::SummarizationConfig;
+pub use truncation::{DropOldest, ImportanceBased, SlidingWindow, TruncationStrategy};
+
+use crate::error::{Error, Result};
+use crate::types::chat::Message;
+use serde::{Deserialize, Serialize};
+
+/// A chat session identified by `id`, holding its messages in order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Conversation {
+    /// Unique identifier used to load and save the conversation.
+    pub id: String,
+    /// The messages exchanged so far, oldest first.
+    pub messages: Vec<Message>,
+    /// Tokens (prompt and completion) consumed by this conversation so far.
+    #[serde(default)]
+    pub tokens_used: u64,
+    /// Where this conversation branched off, if it was created with [`fork_at`](Self::fork_at).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forked_from: Option<ForkPoint>,
+}
+
+/// The point a forked conversation branched off its parent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForkPoint {
+    /// The id of the conversation that was forked.
+    pub conversation_id: String,
+    /// The number of leading messages the branch shares with its parent.
+    pub message_index: usize,
+}
+
+impl Conversation {
+    /// Creates an empty conversation with the given identifier.
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            messages: Vec::new(),
+            tokens_used: 0,
+            forked_from: None,
+        }
+    }
+
+    /// Appends a message to the conversation.
+    pub fn push(&mut self, message: Message) {
+        self.messages.push(message);
+    }
+
+    /// Creates an independent branch `id` holding all messages so far.
+    pub fn fork(&self, id: impl Into<String>) -> Self {
+        self.branch(self.messages.len(), id.into())
+    }
+
+    /// Creates an independent branch `id` holding the first `index` messages, e.g. to
+    /// regenerate the answer to message `index - 1` without losing the original one.
+    ///
+    /// The branch inherits the tokens used so far, so per-conversation budgets still cover
+    /// the shared history. Fails with `Error::InvalidRequest` if `index` is past the end.
+    pub fn fork_at(&self, index: usize, id: impl Into<String>) -> Result<Self> {
+        if index > self.messages.len() {
+            return Err(Error::InvalidRequest(format!(
+                "cannot fork conversation {} at message {}: it has {} messages",
+                self.id,
+                index,
+                self.messages.len()
+            )));
+        }
+        Ok(self.branch(index, id.into()))
+    }
+
+    fn branch(&self, index: usize, id: String) -> Self {
+        Self {
+            id,
+            messages: self.messages[..index].to_vec(),
+            tokens_used: self.tokens_used,
+            forked_from: Some(ForkPoint {
+                conversation_id: self.id.clone(),
+                message_index: index,
+            }),
+        }
+    }
+}
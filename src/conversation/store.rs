@@ -0,0 +1,54 @@
+//! Conversation persistence.
+//!
+//! [`ConversationStore`] is object safe, so implementations can be shared as
+//! `Arc<dyn ConversationStore>` between sessions and tasks.
+
+use super::Conversation;
+use crate::error::Result;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Async storage backend for conversations, keyed by conversation id.
+pub trait ConversationStore: Send + Sync {
+    /// Loads the conversation with the given id, or `None` if it does not exist.
+    fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<Conversation>>>;
+
+    /// Saves the conversation, replacing any previous version stored under the same id.
+    fn save<'a>(&'a self, conversation: &'a Conversation) -> BoxFuture<'a, Result<()>>;
+}
+
+/// A process-local [`ConversationStore`] backed by a `HashMap`.
+///
+/// Useful for tests and single-instance services; data is lost when the process exits.
+#[derive(Debug, Default)]
+pub struct InMemoryConversationStore {
+    conversations: Mutex<HashMap<String, Conversation>>,
+}
+
+impl InMemoryConversationStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ConversationStore for InMemoryConversationStore {
+    fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<Conversation>>> {
+        let conversation = self
+            .conversations
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(id)
+            .cloned();
+        Box::pin(async move { Ok(conversation) })
+    }
+
+    fn save<'a>(&'a self, conversation: &'a Conversation) -> BoxFuture<'a, Result<()>> {
+        self.conversations
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(conversation.id.clone(), conversation.clone());
+        Box::pin(async move { Ok(()) })
+    }
+}
@@ -0,0 +1,125 @@
+//! Conversation compression by summarization.
+//!
+//! When a conversation grows past a token threshold, older turns are sent to a
+//! (typically cheap) model and replaced by a single system note containing the
+//! summary, while the most recent turns are kept verbatim.
+
+#[cfg(feature = "reqwest")]
+use super::Conversation;
+#[cfg(feature = "reqwest")]
+use crate::api::chat::ChatApi;
+#[cfg(feature = "reqwest")]
+use crate::error::Result;
+#[cfg(feature = "reqwest")]
+use crate::types::chat::{ChatCompletionRequest, Message};
+#[cfg(feature = "reqwest")]
+use crate::utils::tokens::estimate_messages_tokens;
+
+/// Default instruction given to the summarization model.
+const DEFAULT_SUMMARY_PROMPT: &str =
+    "Summarize the following conversation so that it can replace the \
+original messages as context. Preserve facts, decisions, names, and open questions. Be concise.";
+
+/// Prefix placed in front of the generated summary in the resulting system note.
+#[cfg(feature = "reqwest")]
+const SUMMARY_PREFIX: &str = "Summary of the earlier conversation: ";
+
+/// Configuration for automatic conversation summarization.
+#[derive(Debug, Clone)]
+pub struct SummarizationConfig {
+    /// Estimated prompt size (in tokens) above which older turns are summarized.
+    pub token_threshold: usize,
+    /// Model used to produce the summary.
+    pub model: String,
+    /// Number of most recent messages that are always kept verbatim.
+    pub keep_recent: usize,
+    /// Instruction given to the summarization model.
+    pub prompt: String,
+}
+
+impl SummarizationConfig {
+    /// Creates a configuration summarizing with `model` once the conversation exceeds `token_threshold`.
+    pub fn new(token_threshold: usize, model: impl Into<String>) -> Self {
+        Self {
+            token_threshold,
+            model: model.into(),
+            keep_recent: 6,
+            prompt: DEFAULT_SUMMARY_PROMPT.to_string(),
+        }
+    }
+
+    /// Sets how many recent messages are kept verbatim.
+    pub fn with_keep_recent(mut self, keep_recent: usize) -> Self {
+        self.keep_recent = keep_recent;
+        self
+    }
+
+    /// Overrides the instruction given to the summarization model.
+    pub fn with_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = prompt.into();
+        self
+    }
+}
+
+/// Summarizes older turns of `conversation` in place if it exceeds the configured threshold.
+///
+/// Leading system messages are preserved, and the split point never separates a
+/// `tool` message from the assistant message that requested it.
+/// Returns `true` if the conversation was compressed. If the summary comes back empty,
+/// the conversation is left unchanged and `false` is returned, so no history is lost.
+#[cfg(feature = "reqwest")]
+pub async fn summarize_if_needed(
+    chat: &ChatApi,
+    config: &SummarizationConfig,
+    conversation: &mut Conversation,
+) -> Result<bool> {
+    if estimate_messages_tokens(&conversation.messages) <= config.token_threshold {
+        return Ok(false);
+    }
+
+    let messages = &conversation.messages;
+    let leading_system = messages.iter().take_while(|m| m.role == "system").count();
+    let mut split = messages
+        .len()
+        .saturating_sub(config.keep_recent)
+        .max(leading_system);
+    while split > leading_system && messages.get(split).is_some_and(|m| m.role == "tool") {
+        split -= 1;
+    }
+    if split <= leading_system {
+        return Ok(false);
+    }
+
+    let transcript = messages[leading_system..split]
+        .iter()
+        .map(|m| format!("{}: {}", m.role, m.content))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let request = ChatCompletionRequest {
+        model: config.model.clone(),
+        messages: vec![
+            Message::system(config.prompt.as_str()),
+            Message::user(transcript),
+        ],
+        ..Default::default()
+    };
+    let response = chat.chat_completion(request).await?;
+    let summary = response
+        .choices
+        .first()
+        .map(|choice| choice.message.content.to_text())
+        .unwrap_or_default();
+    let summary = summary.trim();
+    if summary.is_empty() {
+        tracing::warn!("summarization returned no text, keeping the full conversation");
+        return Ok(false);
+    }
+
+    let mut compressed = Vec::with_capacity(leading_system + 1 + messages.len() - split);
+    compressed.extend_from_slice(&messages[..leading_system]);
+    compressed.push(Message::system(format!("{}{}", SUMMARY_PREFIX, summary)));
+    compressed.extend_from_slice(&messages[split..]);
+    conversation.messages = compressed;
+    Ok(true)
+}
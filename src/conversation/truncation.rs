@@ -0,0 +1,113 @@
+//! Pluggable history truncation.
+//!
+//! A [`TruncationStrategy`] decides which messages are evicted when a conversation
+//! no longer fits in the available context. System messages and the latest message
+//! are never evicted by the built-in strategies, and evicting an assistant message
+//! also evicts the `tool` results that answer its tool calls.
+
+use crate::types::chat::Message;
+use crate::utils::tokens::estimate_messages_tokens;
+
+/// Decides what gets evicted from a conversation when context is tight.
+pub trait TruncationStrategy: Send + Sync {
+    /// Removes messages from `messages` so that the estimated prompt size fits in `max_tokens`.
+    fn truncate(&self, messages: &mut Vec<Message>, max_tokens: usize);
+}
+
+/// Returns `true` if the message at `index` may be evicted.
+fn is_evictable(messages: &[Message], index: usize) -> bool {
+    index + 1 < messages.len() && messages[index].role != "system"
+}
+
+/// Removes the message at `index` together with the tool results answering its tool calls.
+fn evict(messages: &mut Vec<Message>, index: usize) {
+    let removed = messages.remove(index);
+    if let Some(tool_calls) = removed.tool_calls {
+        messages.retain(|m| {
+            m.role != "tool"
+                || !tool_calls
+                    .iter()
+                    .any(|tc| m.tool_call_id.as_deref() == Some(tc.id.as_str()))
+        });
+    }
+}
+
+/// Evicts the oldest messages first.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DropOldest;
+
+impl TruncationStrategy for DropOldest {
+    fn truncate(&self, messages: &mut Vec<Message>, max_tokens: usize) {
+        while estimate_messages_tokens(messages) > max_tokens {
+            match (0..messages.len()).find(|&i| is_evictable(messages, i)) {
+                Some(index) => evict(messages, index),
+                None => break,
+            }
+        }
+    }
+}
+
+/// Keeps at most `max_messages` non-system messages, then drops the oldest until the budget fits.
+#[derive(Debug, Clone, Copy)]
+pub struct SlidingWindow {
+    pub max_messages: usize,
+}
+
+impl SlidingWindow {
+    /// Creates a window keeping the `max_messages` most recent non-system messages.
+    pub fn new(max_messages: usize) -> Self {
+        Self { max_messages }
+    }
+}
+
+impl TruncationStrategy for SlidingWindow {
+    fn truncate(&self, messages: &mut Vec<Message>, max_tokens: usize) {
+        while messages.iter().filter(|m| m.role != "system").count() > self.max_messages.max(1) {
+            match (0..messages.len()).find(|&i| is_evictable(messages, i)) {
+                Some(index) => evict(messages, index),
+                None => break,
+            }
+        }
+        DropOldest.truncate(messages, max_tokens);
+    }
+}
+
+/// Evicts the least important messages first, as ranked by a user-provided scorer.
+///
+/// The scorer receives each message and its position; lower scores are evicted first,
+/// and ties are broken by evicting the older message.
+pub struct ImportanceBased<F>
+where
+    F: Fn(&Message, usize) -> f64 + Send + Sync,
+{
+    scorer: F,
+}
+
+impl<F> ImportanceBased<F>
+where
+    F: Fn(&Message, usize) -> f64 + Send + Sync,
+{
+    /// Creates a strategy ranking messages with `scorer`.
+    pub fn new(scorer: F) -> Self {
+        Self { scorer }
+    }
+}
+
+impl<F> TruncationStrategy for ImportanceBased<F>
+where
+    F: Fn(&Message, usize) -> f64 + Send + Sync,
+{
+    fn truncate(&self, messages: &mut Vec<Message>, max_tokens: usize) {
+        while estimate_messages_tokens(messages) > max_tokens {
+            let least_important = (0..messages.len())
+                .filter(|&i| is_evictable(messages, i))
+                .min_by(|&a, &b| {
+                    (self.scorer)(&messages[a], a).total_cmp(&(self.scorer)(&messages[b], b))
+                });
+            match least_important {
+                Some(index) => evict(messages, index),
+                None => break,
+            }
+        }
+    }
+}
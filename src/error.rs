@@ -1,3 +1,4 @@
+#[cfg(feature = "reqwest")]
 use reqwest::Response;
 use serde_json::Value;
 use thiserror::Error;
@@ -6,6 +7,7 @@ use thiserror::Error;
 /// Extended to include structured output errors.
 #[derive(Error, Debug)]
 pub enum Error {
+    #[cfg(feature = "reqwest")]
     #[error("HTTP error: {0}")]
     HttpError(#[from] reqwest::Error),
 
@@ -19,20 +21,161 @@ pub enum Error {
     #[error("Invalid configuration: {0}")]
     ConfigError(String),
 
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error("Budget exceeded: estimated cost {estimated} exceeds the limit of {limit}")]
+    BudgetExceeded {
+        estimated: rust_decimal::Decimal,
+        limit: rust_decimal::Decimal,
+    },
+
+    #[error("Blocked by policy: {reason}")]
+    BlockedByPolicy { reason: String },
+
+    #[error("Token budget exceeded: {tokens} tokens exceed the limit of {limit}")]
+    TokenBudgetExceeded { tokens: usize, limit: usize },
+
+    #[error("Context length exceeded: about {tokens} tokens exceed the {limit}-token context window of {model}")]
+    ContextLengthExceeded {
+        model: String,
+        tokens: u64,
+        limit: u64,
+    },
+
     #[error("Structured output not supported by the provider/model")]
     StructuredOutputNotSupported,
 
     #[error("Schema validation error: {0}")]
     SchemaValidationError(String),
 
+    #[error("Invalid arguments for tool `{function}` at `{path}`: {message}; arguments were: {arguments}")]
+    InvalidToolArguments {
+        function: String,
+        path: String,
+        message: String,
+        arguments: String,
+    },
+
+    #[error("Agent did not finish within {steps} steps")]
+    MaxStepsExceeded { steps: usize },
+
+    #[error("Failed to decode response (status {status}) at `{path}`: {message}")]
+    Decode {
+        status: u16,
+        /// The path of the offending field, such as `choices[0].message.role`.
+        path: String,
+        message: String,
+        /// The raw response body.
+        body: String,
+    },
+
+    #[error("Type conversion error: {0}")]
+    ConversionError(String),
+
+    #[error("Transport error: {0}")]
+    TransportError(String),
+
+    #[error("Stream stalled: no data received for {idle:?}")]
+    StreamStalled { idle: std::time::Duration },
+
+    #[error("Client is shut down")]
+    ShutDown,
+
     #[error("Unknown error")]
     Unknown,
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Decodes a JSON response body into `T`, failing with `Error::Decode` that names the
+/// offending field and keeps the body.
+#[cfg(feature = "reqwest")]
+pub(crate) fn decode_json<T: serde::de::DeserializeOwned>(status: u16, body: &str) -> Result<T> {
+    let mut deserializer = serde_json::Deserializer::from_str(body);
+    let value = serde_path_to_error::deserialize(&mut deserializer)
+        .map_err(|e| Error::decode(status, e, body))?;
+    deserializer.end().map_err(|e| Error::Decode {
+        status,
+        path: ".".into(),
+        message: e.to_string(),
+        body: body.to_string(),
+    })?;
+    Ok(value)
+}
+
+/// Returns true for HTTP statuses that indicate a transient condition worth retrying.
+pub(crate) fn is_retryable_status(code: u16) -> bool {
+    matches!(code, 408 | 429 | 500 | 502 | 503 | 504)
+}
+
 impl Error {
+    /// Builds an `Error::Decode` from a path-tracking deserialization error.
+    #[cfg(feature = "reqwest")]
+    pub(crate) fn decode(
+        status: u16,
+        error: serde_path_to_error::Error<serde_json::Error>,
+        body: &str,
+    ) -> Self {
+        Error::Decode {
+            status,
+            path: error.path().to_string(),
+            message: error.into_inner().to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    /// Returns the HTTP status code associated with this error, if any.
+    pub fn status_code(&self) -> Option<u16> {
+        match self {
+            Error::ApiError { code, .. } => Some(*code),
+            Error::Decode { status, .. } => Some(*status),
+            #[cfg(feature = "reqwest")]
+            Error::HttpError(e) => e.status().map(|s| s.as_u16()),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the error is transient and the request may succeed if retried:
+    /// rate limits, timeouts, stalled streams, upstream 5xx failures, and connection errors.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::StreamStalled { .. } => true,
+            #[cfg(feature = "reqwest")]
+            Error::HttpError(e) if e.is_connect() || e.is_timeout() => true,
+            _ => self.status_code().is_some_and(is_retryable_status),
+        }
+    }
+
+    /// Returns true if the request was rejected because of rate limiting (HTTP 429).
+    pub fn is_rate_limited(&self) -> bool {
+        self.status_code() == Some(429)
+    }
+
+    /// Returns true if the API key is missing, invalid, or lacks permission (HTTP 401/403).
+    pub fn is_auth_error(&self) -> bool {
+        matches!(self.status_code(), Some(401 | 403))
+    }
+
+    /// Returns true if the account has insufficient credits (HTTP 402).
+    pub fn is_billing_error(&self) -> bool {
+        self.status_code() == Some(402)
+    }
+
+    /// Returns true if the error stems from a malformed request or misconfiguration that
+    /// retrying will not fix, such as validation failures or HTTP 400/404/422 responses.
+    pub fn is_client_bug(&self) -> bool {
+        match self {
+            Error::ConfigError(_)
+            | Error::InvalidRequest(_)
+            | Error::ConversionError(_)
+            | Error::StructuredOutputNotSupported => true,
+            _ => matches!(self.status_code(), Some(400 | 404 | 405 | 413 | 422)),
+        }
+    }
+
     /// Creates an API error from a given HTTP response.
+    #[cfg(feature = "reqwest")]
     pub async fn from_response(response: Response) -> Result<Self> {
         let status = response.status().as_u16();
         let text = response.text().await.unwrap_or_default();
@@ -1,11 +1,38 @@
+#[cfg(feature = "reqwest")]
 pub mod api;
+pub mod audit;
+#[cfg(feature = "reqwest")]
+pub mod balancer;
+pub mod budget;
+pub mod catalog;
+pub mod chunking;
 pub mod client;
+pub mod conversation;
 pub mod error;
+#[cfg(feature = "reqwest")]
+pub mod lifecycle;
+#[cfg(feature = "mock")]
+pub mod mock;
 pub mod models;
+pub mod moderation;
+pub mod redaction;
+pub mod retry;
+pub mod scheduler;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 pub mod tests;
+#[cfg(feature = "reqwest")]
+pub mod throttle;
+pub mod transport;
 pub mod types;
+pub mod utils;
 
 pub use error::{Error, Result};
+pub use rust_decimal::Decimal;
 pub use types::*;
 
+pub use client::Preset;
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+pub use client::TlsBackend;
+#[cfg(feature = "reqwest")]
 pub use client::{NoAuth, OpenRouterClient, Ready, Unconfigured};
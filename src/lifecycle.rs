@@ -0,0 +1,201 @@
+/*!
+   # Lifecycle Module
+
+   Graceful shutdown for services embedding the client. `shutdown()` stops the client from
+   accepting new requests, waits for in-flight calls and streams to finish, and aborts
+   whatever is still running when the timeout expires. The returned [`ShutdownReport`] lists
+   the aborted requests, e.g. to log them during a rolling deploy.
+
+   Every endpoint handle created from a client shares its lifecycle, so shutting the client
+   down also drains and closes handles such as `chat()` and `models()`.
+*/
+
+use crate::client::PreparedRequest;
+use crate::error::{Error, Result};
+use futures::channel::oneshot;
+use futures::future::{AbortHandle, AbortRegistration};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A request that was still running when the shutdown timeout expired.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbortedRequest {
+    /// The HTTP method.
+    pub method: http::Method,
+    /// The endpoint URL.
+    pub url: String,
+    /// How long the request had been running when it was aborted.
+    pub age: Duration,
+}
+
+/// The outcome of a graceful shutdown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Requests that were in flight when the shutdown began and finished in time.
+    pub drained: usize,
+    /// Requests that were aborted because the timeout expired.
+    pub aborted: Vec<AbortedRequest>,
+    /// Time spent shutting down.
+    pub elapsed: Duration,
+}
+
+impl ShutdownReport {
+    /// Returns true if every in-flight request finished before the timeout.
+    pub fn is_clean(&self) -> bool {
+        self.aborted.is_empty()
+    }
+}
+
+/// Tracks the requests in flight on a client and whether it still accepts new ones.
+#[derive(Default)]
+pub struct Lifecycle {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    shut_down: bool,
+    aborting: bool,
+    next_id: u64,
+    active: BTreeMap<u64, Active>,
+    idle: Vec<oneshot::Sender<()>>,
+}
+
+struct Active {
+    method: http::Method,
+    url: String,
+    started: Instant,
+    abort: AbortHandle,
+}
+
+impl fmt::Debug for Lifecycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("Lifecycle")
+            .field("shut_down", &state.shut_down)
+            .field("in_flight", &state.active.len())
+            .finish()
+    }
+}
+
+impl Lifecycle {
+    /// Returns true once `shutdown` has been called.
+    pub fn is_shut_down(&self) -> bool {
+        self.state.lock().unwrap().shut_down
+    }
+
+    /// The number of requests currently in flight, including streams still being read.
+    pub fn in_flight(&self) -> usize {
+        self.state.lock().unwrap().active.len()
+    }
+
+    /// Registers `request` as in flight, or fails with [`Error::ShutDown`] after shutdown.
+    ///
+    /// The returned registration aborts the request when the shutdown timeout expires; the
+    /// request counts as in flight until the guard is dropped.
+    pub(crate) fn begin(
+        self: &Arc<Self>,
+        request: &PreparedRequest,
+    ) -> Result<(RequestGuard, AbortRegistration)> {
+        let mut state = self.state.lock().unwrap();
+        if state.shut_down {
+            return Err(Error::ShutDown);
+        }
+        let (abort, registration) = AbortHandle::new_pair();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.active.insert(
+            id,
+            Active {
+                method: request.method.clone(),
+                url: request.url.to_string(),
+                started: Instant::now(),
+                abort,
+            },
+        );
+        let guard = RequestGuard {
+            lifecycle: self.clone(),
+            id,
+        };
+        Ok((guard, registration))
+    }
+
+    /// Stops accepting requests and waits up to `timeout` for in-flight ones to finish,
+    /// then aborts the rest.
+    pub(crate) async fn shutdown(&self, timeout: Duration) -> ShutdownReport {
+        let started = Instant::now();
+        let (in_flight, idle) = {
+            let mut state = self.state.lock().unwrap();
+            state.shut_down = true;
+            let in_flight = state.active.len();
+            let (notify, idle) = oneshot::channel();
+            if in_flight > 0 {
+                state.idle.push(notify);
+            }
+            (in_flight, idle)
+        };
+        if in_flight == 0 || tokio::time::timeout(timeout, idle).await.is_ok() {
+            return ShutdownReport {
+                drained: in_flight,
+                aborted: Vec::new(),
+                elapsed: started.elapsed(),
+            };
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.aborting = true;
+        let aborted: Vec<AbortedRequest> = state
+            .active
+            .values()
+            .map(|active| {
+                active.abort.abort();
+                AbortedRequest {
+                    method: active.method.clone(),
+                    url: active.url.clone(),
+                    age: active.started.elapsed(),
+                }
+            })
+            .collect();
+        ShutdownReport {
+            drained: in_flight.saturating_sub(aborted.len()),
+            aborted,
+            elapsed: started.elapsed(),
+        }
+    }
+}
+
+/// Marks a request as in flight until dropped.
+pub(crate) struct RequestGuard {
+    lifecycle: Arc<Lifecycle>,
+    id: u64,
+}
+
+impl RequestGuard {
+    /// Replaces the request's abort registration, e.g. once the response head has arrived
+    /// and the body is still to be read.
+    pub(crate) fn rearm(&self) -> AbortRegistration {
+        let (abort, registration) = AbortHandle::new_pair();
+        let mut state = self.lifecycle.state.lock().unwrap();
+        if state.aborting {
+            abort.abort();
+        }
+        if let Some(active) = state.active.get_mut(&self.id) {
+            active.abort = abort;
+        }
+        registration
+    }
+}
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        let mut state = self.lifecycle.state.lock().unwrap();
+        state.active.remove(&self.id);
+        if state.active.is_empty() {
+            for notify in state.idle.drain(..) {
+                let _ = notify.send(());
+            }
+        }
+    }
+}
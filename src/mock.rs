@@ -0,0 +1,156 @@
+/*!
+   # Mock Server Module
+
+   Ready-made [wiremock](https://crates.io/crates/wiremock) matchers and responders for a local
+   mock OpenRouter, so integration tests need no hand-rolled HTTP server. Enabled with the
+   `mock` feature.
+
+   ```ignore
+   use openrouter_api::mock::MockOpenRouter;
+
+   let server = MockOpenRouter::start().await;
+   server.mock_chat_reply("Hello!").await;
+   server.mock_chat_stream(&["Hel", "lo!"]).await;
+
+   let client = server.client()?;
+   ```
+*/
+
+use crate::client::{OpenRouterClient, Ready, Unconfigured};
+use crate::error::Result;
+use crate::test_utils::{error_body, ChatCompletionChunkBuilder, ChatCompletionResponseBuilder};
+use crate::types::chat::ChatCompletionResponse;
+use serde_json::Value;
+use wiremock::matchers::{method, path};
+use wiremock::{Match, Mock, MockBuilder, MockServer, Request, ResponseTemplate};
+
+/// Starts a mock for `POST /chat/completions`; chain further matchers and a responder.
+pub fn chat_completions() -> MockBuilder {
+    Mock::given(method("POST")).and(path("/chat/completions"))
+}
+
+/// Matches requests whose JSON body names the given model.
+pub fn body_model(model: impl Into<String>) -> ModelMatcher {
+    ModelMatcher(model.into())
+}
+
+/// Matches requests whose JSON body has `"stream": true`.
+pub fn streaming() -> StreamingMatcher {
+    StreamingMatcher(true)
+}
+
+/// Matches requests whose JSON body does not enable streaming.
+pub fn not_streaming() -> StreamingMatcher {
+    StreamingMatcher(false)
+}
+
+/// Matcher created by [`body_model`].
+#[derive(Debug, Clone)]
+pub struct ModelMatcher(String);
+
+impl Match for ModelMatcher {
+    fn matches(&self, request: &Request) -> bool {
+        serde_json::from_slice::<Value>(&request.body)
+            .ok()
+            .is_some_and(|body| body["model"] == self.0.as_str())
+    }
+}
+
+/// Matcher created by [`streaming`] and [`not_streaming`].
+#[derive(Debug, Clone, Copy)]
+pub struct StreamingMatcher(bool);
+
+impl Match for StreamingMatcher {
+    fn matches(&self, request: &Request) -> bool {
+        serde_json::from_slice::<Value>(&request.body)
+            .ok()
+            .is_some_and(|body| (body["stream"] == true) == self.0)
+    }
+}
+
+/// Responds with the given chat completion as JSON.
+pub fn chat_response(response: ChatCompletionResponse) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(response)
+}
+
+/// Responds with a Server-Sent Events stream yielding one chunk per fragment, followed
+/// by a final `stop` chunk and the `[DONE]` sentinel.
+pub fn sse_response<S: AsRef<str>>(fragments: &[S]) -> ResponseTemplate {
+    let mut body = String::from(": OPENROUTER PROCESSING\n\n");
+    let chunks = fragments
+        .iter()
+        .map(|fragment| ChatCompletionChunkBuilder::new().content(fragment.as_ref()))
+        .chain(std::iter::once(
+            ChatCompletionChunkBuilder::new().finish_reason("stop"),
+        ));
+    for chunk in chunks {
+        body.push_str(&format!("data: {}\n\n", chunk.to_json()));
+    }
+    body.push_str("data: [DONE]\n\n");
+    ResponseTemplate::new(200).set_body_raw(body, "text/event-stream")
+}
+
+/// Responds with an OpenRouter-style error body and the given status.
+pub fn error_response(status: u16, message: impl Into<String>) -> ResponseTemplate {
+    ResponseTemplate::new(status).set_body_json(error_body(status, message))
+}
+
+/// A local mock OpenRouter server.
+pub struct MockOpenRouter {
+    server: MockServer,
+}
+
+impl MockOpenRouter {
+    /// Starts a mock server on a random local port.
+    pub async fn start() -> Self {
+        Self {
+            server: MockServer::start().await,
+        }
+    }
+
+    /// The underlying wiremock server, for mounting custom mocks or inspecting requests.
+    pub fn server(&self) -> &MockServer {
+        &self.server
+    }
+
+    /// The base URL to configure the client with, including the trailing slash.
+    pub fn base_url(&self) -> String {
+        format!("{}/", self.server.uri())
+    }
+
+    /// Creates a client pointed at this server with a dummy API key.
+    pub fn client(&self) -> Result<OpenRouterClient<Ready>> {
+        OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(self.base_url())?
+            .with_api_key("sk-or-mock")
+    }
+
+    /// Replies to non-streaming chat completions with an assistant message.
+    pub async fn mock_chat_reply(&self, content: impl Into<String>) {
+        let response = ChatCompletionResponseBuilder::new()
+            .content(content.into())
+            .build();
+        chat_completions()
+            .and(not_streaming())
+            .respond_with(chat_response(response))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Replies to streaming chat completions with one chunk per fragment.
+    pub async fn mock_chat_stream<S: AsRef<str>>(&self, fragments: &[S]) {
+        chat_completions()
+            .and(streaming())
+            .respond_with(sse_response(fragments))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Fails chat completions with the given status and message.
+    pub async fn mock_chat_error(&self, status: u16, message: impl Into<String>) {
+        chat_completions()
+            .respond_with(error_response(status, message))
+            .mount(&self.server)
+            .await;
+    }
+}
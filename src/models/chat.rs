@@ -21,6 +21,9 @@ pub enum ChatRole {
 pub struct ChatMessage {
     pub role: ChatRole,
     pub content: String,
+    /// Optional participant name (for multi-user transcripts).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
 }
 
 /// Conversion from the model’s ChatMessage to the types::chat::Message used in API requests.
@@ -34,9 +37,11 @@ impl From<ChatMessage> for crate::types::chat::Message {
         };
         Self {
             role: role_str,
-            content: chat_msg.content,
-            name: None,
+            content: chat_msg.content.into(),
+            name: chat_msg.name,
             tool_calls: None,
+            tool_call_id: None,
+            annotations: None,
         }
     }
 }
@@ -1,4 +1,5 @@
 pub mod chat;
+pub mod parameters;
 pub mod provider_preferences;
 pub mod structured;
 pub mod tool;
@@ -0,0 +1,224 @@
+/*!
+   # Parameters Module
+
+   Generation parameters shared by chat and text completion requests. [`SamplingParams`] groups
+   the sampling controls and checks their ranges with `validate()`; [`DefaultParams`] holds the
+   parameters the client applies to requests that don't set them. Configure defaults once with
+   `with_default_params()` on the builder instead of repeating them at every call site, and
+   tune them per model with `with_model_params()`.
+*/
+
+use crate::error::{Error, Result};
+use crate::models::provider_preferences::ProviderPreferences;
+use serde::{Deserialize, Serialize};
+
+/// Sampling controls for a generation. Unset parameters are omitted from the request, so the
+/// model's own defaults apply.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SamplingParams {
+    /// Sampling temperature, between 0 and 2.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    /// Nucleus sampling probability mass, greater than 0 and at most 1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    /// Limits sampling to the `top_k` most likely tokens, at least 1. Leave it unset to
+    /// sample from the whole vocabulary.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    /// Penalizes tokens by how often they already appear, between -2 and 2.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f64>,
+    /// Penalizes tokens that already appear at all, between -2 and 2.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f64>,
+    /// Multiplicative penalty for repeated tokens, greater than 0 and at most 2.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repetition_penalty: Option<f64>,
+    /// Seed for best-effort deterministic sampling.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+}
+
+impl SamplingParams {
+    /// Creates an empty set of sampling parameters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the sampling temperature.
+    pub fn with_temperature(mut self, temperature: f64) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Sets the nucleus sampling probability mass.
+    pub fn with_top_p(mut self, top_p: f64) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
+    /// Sets the number of most likely tokens to sample from.
+    pub fn with_top_k(mut self, top_k: u32) -> Self {
+        self.top_k = Some(top_k);
+        self
+    }
+
+    /// Sets the frequency penalty.
+    pub fn with_frequency_penalty(mut self, penalty: f64) -> Self {
+        self.frequency_penalty = Some(penalty);
+        self
+    }
+
+    /// Sets the presence penalty.
+    pub fn with_presence_penalty(mut self, penalty: f64) -> Self {
+        self.presence_penalty = Some(penalty);
+        self
+    }
+
+    /// Sets the repetition penalty.
+    pub fn with_repetition_penalty(mut self, penalty: f64) -> Self {
+        self.repetition_penalty = Some(penalty);
+        self
+    }
+
+    /// Sets the sampling seed.
+    pub fn with_seed(mut self, seed: i64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Returns true if no parameter is set.
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Fills every parameter left unset from `defaults`.
+    pub fn fill_from(&mut self, defaults: &SamplingParams) {
+        self.temperature = self.temperature.or(defaults.temperature);
+        self.top_p = self.top_p.or(defaults.top_p);
+        self.top_k = self.top_k.or(defaults.top_k);
+        self.frequency_penalty = self.frequency_penalty.or(defaults.frequency_penalty);
+        self.presence_penalty = self.presence_penalty.or(defaults.presence_penalty);
+        self.repetition_penalty = self.repetition_penalty.or(defaults.repetition_penalty);
+        self.seed = self.seed.or(defaults.seed);
+    }
+
+    /// Checks that every set parameter is within the range the API accepts.
+    pub fn validate(&self) -> Result<()> {
+        check_range(
+            "temperature",
+            self.temperature,
+            |t| (0.0..=2.0).contains(&t),
+            "between 0 and 2",
+        )?;
+        check_range(
+            "top_p",
+            self.top_p,
+            |p| p > 0.0 && p <= 1.0,
+            "greater than 0 and at most 1",
+        )?;
+        if self.top_k == Some(0) {
+            return Err(Error::InvalidRequest(
+                "top_k must be at least 1, got 0".into(),
+            ));
+        }
+        check_range(
+            "frequency_penalty",
+            self.frequency_penalty,
+            |p| (-2.0..=2.0).contains(&p),
+            "between -2 and 2",
+        )?;
+        check_range(
+            "presence_penalty",
+            self.presence_penalty,
+            |p| (-2.0..=2.0).contains(&p),
+            "between -2 and 2",
+        )?;
+        check_range(
+            "repetition_penalty",
+            self.repetition_penalty,
+            |p| p > 0.0 && p <= 2.0,
+            "greater than 0 and at most 2",
+        )?;
+        Ok(())
+    }
+}
+
+/// Fails with `Error::InvalidRequest` if `value` is set and not accepted by `valid`.
+fn check_range(
+    name: &str,
+    value: Option<f64>,
+    valid: impl Fn(f64) -> bool,
+    expected: &str,
+) -> Result<()> {
+    match value {
+        Some(value) if !valid(value) => Err(Error::InvalidRequest(format!(
+            "{} must be {}, got {}",
+            name, expected, value
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Default generation parameters; each one is only applied if the request leaves it unset.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DefaultParams {
+    /// Default sampling parameters.
+    #[serde(flatten)]
+    pub sampling: SamplingParams,
+    /// Maximum number of tokens to generate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Provider routing preferences. Only applied to chat requests.
+    #[serde(skip)]
+    pub provider: Option<ProviderPreferences>,
+}
+
+impl DefaultParams {
+    /// Creates an empty set of defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the default sampling parameters.
+    pub fn with_sampling(mut self, sampling: SamplingParams) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Sets the default sampling temperature.
+    pub fn with_temperature(mut self, temperature: f64) -> Self {
+        self.sampling.temperature = Some(temperature);
+        self
+    }
+
+    /// Sets the default nucleus sampling probability mass.
+    pub fn with_top_p(mut self, top_p: f64) -> Self {
+        self.sampling.top_p = Some(top_p);
+        self
+    }
+
+    /// Sets the default number of most likely tokens to sample from.
+    pub fn with_top_k(mut self, top_k: u32) -> Self {
+        self.sampling.top_k = Some(top_k);
+        self
+    }
+
+    /// Sets the default maximum number of tokens to generate.
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Sets the default provider routing preferences.
+    pub fn with_provider_preferences(mut self, preferences: ProviderPreferences) -> Self {
+        self.provider = Some(preferences);
+        self
+    }
+
+    /// Returns true if no defaults are set.
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
@@ -2,13 +2,50 @@
 //!
 //! This module defines strongly‑typed provider preference settings that allow
 //! users to configure routing options including provider ordering, fallback behavior,
-//! parameter requirements, data collection settings, quantizations and sorting.
+//! parameter requirements, provider allow/deny lists, data collection settings,
+//! quantizations and sorting.
 
 use crate::error::Error;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Identifier of an upstream provider as used by OpenRouter routing (e.g. "openai", "anthropic").
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ProviderSlug(pub String);
+
+impl ProviderSlug {
+    /// Creates a provider slug.
+    pub fn new(slug: impl Into<String>) -> Self {
+        Self(slug.into())
+    }
+
+    /// Returns the slug as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for ProviderSlug {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for ProviderSlug {
+    fn from(slug: &str) -> Self {
+        Self(slug.to_string())
+    }
+}
+
+impl From<String> for ProviderSlug {
+    fn from(slug: String) -> Self {
+        Self(slug)
+    }
+}
 
 /// Defines the data collection policy when selecting providers.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum DataCollection {
     Allow,
@@ -16,15 +53,16 @@ pub enum DataCollection {
 }
 
 /// Defines provider sort preferences.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ProviderSort {
     Price,
     Throughput,
+    Latency,
 }
 
 /// Defines quantization filtering options.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Quantization {
     Int4,
@@ -38,8 +76,10 @@ pub enum Quantization {
 }
 
 /// Strongly‑typed provider preferences.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+///
+/// Serialized with the snake_case field names the OpenRouter API expects
+/// (e.g. `require_parameters`, `allow_fallbacks`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ProviderPreferences {
     /// Ordered list of provider names to prefer.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -48,14 +88,20 @@ pub struct ProviderPreferences {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub allow_fallbacks: Option<bool>,
     /// Whether to require providers to support all parameters.
+    ///
+    /// When true, only providers supporting every parameter in the request (such as
+    /// `tools` or `response_format`) are used, instead of silently dropping unsupported ones.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub require_parameters: Option<bool>,
     /// Controls data collection for providers ("allow" or "deny").
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data_collection: Option<DataCollection>,
-    /// List of provider names to ignore.
+    /// Restricts routing to only these providers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub only: Option<Vec<ProviderSlug>>,
+    /// Providers that must never be used for this request.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub ignore: Option<Vec<String>>,
+    pub ignore: Option<Vec<ProviderSlug>>,
     /// List of quantization levels to filter providers.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub quantizations: Option<Vec<Quantization>>,
@@ -65,11 +111,45 @@ pub struct ProviderPreferences {
 }
 
 impl ProviderPreferences {
+    /// Restricts routing to providers that support all parameters in the request.
+    pub fn with_require_parameters(mut self, require: bool) -> Self {
+        self.require_parameters = Some(require);
+        self
+    }
+
+    /// Pins the request to the given providers.
+    pub fn with_only<I, S>(mut self, providers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<ProviderSlug>,
+    {
+        self.only = Some(providers.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Excludes the given providers from routing.
+    pub fn with_ignore<I, S>(mut self, providers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<ProviderSlug>,
+    {
+        self.ignore = Some(providers.into_iter().map(Into::into).collect());
+        self
+    }
+
     /// Validates the provider preferences.
     ///
-    /// For this phase, we simply return Ok(()) since our type‑safe enums guarantee valid values.
-    /// In the future, more complex inter‑field validations could be added here.
+    /// Enum-typed fields are valid by construction; this checks inter-field
+    /// consistency, such as a provider listed in both `only` and `ignore`.
     pub fn validate(&self) -> Result<(), Error> {
+        if let (Some(only), Some(ignore)) = (&self.only, &self.ignore) {
+            if let Some(slug) = only.iter().find(|slug| ignore.contains(slug)) {
+                return Err(Error::InvalidRequest(format!(
+                    "Provider '{}' is listed in both `only` and `ignore`",
+                    slug
+                )));
+            }
+        }
         Ok(())
     }
 }
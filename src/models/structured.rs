@@ -1,3 +1,6 @@
+use crate::error::{Error, Result};
+use crate::utils::json_repair::repair_json;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
@@ -25,8 +28,295 @@ pub struct JsonSchemaDefinition {
 pub struct JsonSchemaConfig {
     /// Name for the schema, used to identify the output type.
     pub name: String,
-    /// If true, the model response must strictly adhere to the schema.
+    /// If true, the model response must strictly adhere to the schema. Strict mode
+    /// substantially improves schema adherence on providers that support it.
+    #[serde(default)]
     pub strict: bool,
     /// The JSON Schema definition.
     pub schema: JsonSchemaDefinition,
 }
+
+impl JsonSchemaConfig {
+    /// Creates a strict schema configuration.
+    pub fn new(name: impl Into<String>, schema: JsonSchemaDefinition) -> Self {
+        Self {
+            name: name.into(),
+            strict: true,
+            schema,
+        }
+    }
+
+    /// Enables or disables strict schema adherence.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+}
+
+/// The `response_format` of a chat completion request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Plain text, the default.
+    Text,
+    /// Any valid JSON object.
+    JsonObject,
+    /// JSON matching a schema.
+    JsonSchema { json_schema: JsonSchemaConfig },
+}
+
+impl ResponseFormat {
+    /// Requests JSON output matching `config`.
+    pub fn json_schema(config: JsonSchemaConfig) -> Self {
+        ResponseFormat::JsonSchema {
+            json_schema: config,
+        }
+    }
+}
+
+/// A value parsed from model output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedJson<T> {
+    /// The deserialized value.
+    pub value: T,
+    /// True if the output was only parseable after repair.
+    pub repaired: bool,
+}
+
+/// Parses model output as JSON.
+///
+/// With `repair` enabled, output that fails to parse is passed through
+/// [`repair_json`] (stripping code fences, fixing trailing commas, and balancing
+/// brackets) and parsed again; [`ParsedJson::repaired`] records whether that happened.
+pub fn parse_json<T: DeserializeOwned>(text: &str, repair: bool) -> Result<ParsedJson<T>> {
+    let error = match serde_json::from_str(text) {
+        Ok(value) => {
+            return Ok(ParsedJson {
+                value,
+                repaired: false,
+            })
+        }
+        Err(e) => e,
+    };
+    if repair {
+        if let Ok(value) = serde_json::from_str(&repair_json(text)) {
+            return Ok(ParsedJson {
+                value,
+                repaired: true,
+            });
+        }
+    }
+    Err(Error::SchemaValidationError(format!(
+        "Response is not valid JSON: {}",
+        error
+    )))
+}
+
+/// Where the validator is within the top-level JSON object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScanState {
+    Start,
+    ExpectKey,
+    InKey,
+    ExpectColon,
+    ExpectValue,
+    InValue,
+    AfterValue,
+    Done,
+}
+
+/// Validates a structured response against a [`JsonSchemaDefinition`] while it is still
+/// being streamed.
+///
+/// Violations that can be decided early are reported as soon as the offending characters
+/// arrive: a top-level value that is not an object, a property not declared in the schema
+/// when `additionalProperties` is `false`, and a property whose value starts with the wrong
+/// JSON type. [`finish`](Self::finish) then parses the complete document and checks
+/// `required` properties.
+#[derive(Debug, Clone)]
+pub struct IncrementalSchemaValidator {
+    schema: JsonSchemaDefinition,
+    buffer: String,
+    state: ScanState,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    key: String,
+}
+
+impl IncrementalSchemaValidator {
+    /// Creates a validator for `schema`.
+    pub fn new(schema: JsonSchemaDefinition) -> Self {
+        Self {
+            schema,
+            buffer: String::new(),
+            state: ScanState::Start,
+            depth: 0,
+            in_string: false,
+            escaped: false,
+            key: String::new(),
+        }
+    }
+
+    /// The text received so far.
+    pub fn text(&self) -> &str {
+        &self.buffer
+    }
+
+    /// Feeds the next fragment of the response, failing on the first detectable violation.
+    pub fn push(&mut self, fragment: &str) -> Result<()> {
+        for c in fragment.chars() {
+            self.buffer.push(c);
+            self.scan(c)?;
+        }
+        Ok(())
+    }
+
+    /// Parses the complete response and checks it against the schema.
+    pub fn finish(&self) -> Result<Value> {
+        let value: Value = serde_json::from_str(&self.buffer).map_err(|e| {
+            Error::SchemaValidationError(format!("Response is not valid JSON: {}", e))
+        })?;
+        if let (Some(required), Some(object)) = (&self.schema.required, value.as_object()) {
+            if let Some(missing) = required.iter().find(|key| !object.contains_key(*key)) {
+                return Err(violation(format!(
+                    "missing required property `{}`",
+                    missing
+                )));
+            }
+        }
+        Ok(value)
+    }
+
+    fn scan(&mut self, c: char) -> Result<()> {
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if c == '\\' {
+                self.escaped = true;
+            } else if c == '"' {
+                self.in_string = false;
+                match self.state {
+                    ScanState::InKey => {
+                        self.check_key()?;
+                        self.state = ScanState::ExpectColon;
+                    }
+                    ScanState::InValue if self.depth == 1 => self.state = ScanState::AfterValue,
+                    _ => {}
+                }
+                return Ok(());
+            }
+            if self.state == ScanState::InKey {
+                self.key.push(c);
+            }
+            return Ok(());
+        }
+        if c.is_whitespace() && self.state != ScanState::InValue {
+            return Ok(());
+        }
+
+        match self.state {
+            ScanState::Start if c == '{' => {
+                self.depth = 1;
+                self.state = ScanState::ExpectKey;
+            }
+            ScanState::Start if self.schema.schema_type == "object" => {
+                return Err(violation(format!("expected an object but got `{}`", c)));
+            }
+            ScanState::Start => self.state = ScanState::Done,
+            ScanState::ExpectKey if c == '"' => {
+                self.in_string = true;
+                self.key.clear();
+                self.state = ScanState::InKey;
+            }
+            ScanState::ExpectKey if c == '}' => self.close_object(),
+            ScanState::ExpectColon if c == ':' => self.state = ScanState::ExpectValue,
+            ScanState::ExpectValue => {
+                self.check_value_start(c)?;
+                match c {
+                    '"' => self.in_string = true,
+                    '{' | '[' => self.depth += 1,
+                    _ => {}
+                }
+                self.state = ScanState::InValue;
+            }
+            ScanState::InValue if self.depth == 1 => match c {
+                ',' => self.state = ScanState::ExpectKey,
+                '}' => self.close_object(),
+                _ => {}
+            },
+            ScanState::InValue => match c {
+                '"' => self.in_string = true,
+                '{' | '[' => self.depth += 1,
+                '}' | ']' => {
+                    self.depth -= 1;
+                    if self.depth == 1 {
+                        self.state = ScanState::AfterValue;
+                    }
+                }
+                _ => {}
+            },
+            ScanState::AfterValue if c == ',' => self.state = ScanState::ExpectKey,
+            ScanState::AfterValue if c == '}' => self.close_object(),
+            ScanState::Done => {}
+            _ => return Err(violation(format!("unexpected `{}`", c))),
+        }
+        Ok(())
+    }
+
+    fn close_object(&mut self) {
+        self.depth = 0;
+        self.state = ScanState::Done;
+    }
+
+    fn check_key(&self) -> Result<()> {
+        if self.schema.additional_properties == Some(false)
+            && !self.schema.properties.contains_key(&self.key)
+        {
+            return Err(violation(format!("unexpected property `{}`", self.key)));
+        }
+        Ok(())
+    }
+
+    fn check_value_start(&self, c: char) -> Result<()> {
+        let actual = match c {
+            '"' => "string",
+            '{' => "object",
+            '[' => "array",
+            't' | 'f' => "boolean",
+            'n' => "null",
+            '-' | '0'..='9' => "number",
+            _ => return Err(violation(format!("unexpected `{}`", c))),
+        };
+        let Some(expected) = self
+            .schema
+            .properties
+            .get(&self.key)
+            .and_then(|property| property.get("type"))
+        else {
+            return Ok(());
+        };
+        let allows = |t: &Value| {
+            t.as_str()
+                .is_some_and(|t| t == actual || (t == "integer" && actual == "number"))
+        };
+        let matches = match expected {
+            Value::Array(types) => types.iter().any(allows),
+            single => allows(single),
+        };
+        if !matches {
+            return Err(violation(format!(
+                "property `{}` should be {} but got {}",
+                self.key, expected, actual
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn violation(message: String) -> Error {
+    Error::SchemaValidationError(format!(
+        "Streamed response violates the schema: {}",
+        message
+    ))
+}
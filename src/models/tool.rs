@@ -8,11 +8,15 @@
    - **FunctionDescription:** Describes a callable function with a name, optional description, and a JSON Schema for its parameters.
    - **Tool:** An enum representing available types of tools. Currently, only function‑type tools are supported.
    - **FunctionCall:** Represents the details of a requested tool call including the function name and JSON‑encoded arguments.
-   - **ToolCall:** Captures the tool call details as returned by the API, including a unique identifier and the associated function call details.
+   - **ToolCallDelta:** A fragment of a tool call received while streaming.
+   - **ToolCall:** Captures the tool call details as returned by the API, including a unique identifier and the associated function call details. Use `ToolCall::parse_arguments` to decode the arguments into a typed struct and `ToolCall::respond_with` to build the matching `tool` result message.
    - **ToolChoice:** Represents the possible outcomes when the model must select a tool (for example, "none", "auto", or a specific function choice).
    - **FunctionName:** A simple structure to represent a function name for tool selection.
 */
 
+use crate::error::{Error, Result};
+use crate::types::chat::{Content, Message};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -70,6 +74,59 @@ pub struct ToolCall {
     pub function_call: FunctionCall,
 }
 
+impl ToolCall {
+    /// Decodes the JSON-encoded arguments into `T`.
+    ///
+    /// On failure the error names the function, the path of the offending field (such as
+    /// `items[2].quantity`), and the raw arguments the model sent.
+    pub fn parse_arguments<T: DeserializeOwned>(&self) -> Result<T> {
+        let call = &self.function_call;
+        let mut deserializer = serde_json::Deserializer::from_str(&call.arguments);
+        serde_path_to_error::deserialize(&mut deserializer).map_err(|e| {
+            Error::InvalidToolArguments {
+                function: call.name.clone(),
+                path: e.path().to_string(),
+                message: e.inner().to_string(),
+                arguments: call.arguments.clone(),
+            }
+        })
+    }
+
+    /// Builds the `tool` message answering this call, linked through its `tool_call_id`.
+    pub fn respond_with(&self, content: impl Into<Content>) -> Message {
+        Message::tool(self.id.clone(), content)
+    }
+}
+
+/// A fragment of a tool call as delivered in a streaming chunk.
+///
+/// The `id`, type, and function name usually arrive in the first fragment for a given
+/// `index`; the JSON `arguments` string is spread across subsequent fragments.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolCallDelta {
+    /// Position of the tool call this fragment belongs to.
+    #[serde(default)]
+    pub index: usize,
+    /// The tool call identifier.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// The type of call, e.g. "function".
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// The next part of the function name and arguments.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function: Option<FunctionCallDelta>,
+}
+
+/// A fragment of a function call's name and arguments.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FunctionCallDelta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<String>,
+}
+
 /// Represents a tool selection option when the model must choose among available tools.
 ///
 /// This enum covers three cases:
@@ -0,0 +1,65 @@
+/*!
+   # Moderation Module
+
+   A [`ModerationHook`] reviews the messages of every chat completion request before it is
+   sent and can veto it, so policy violations are rejected with [`Error::BlockedByPolicy`]
+   before any tokens are billed. The hook sees the messages exactly as they would be sent,
+   after client defaults and redaction.
+
+   Implement the trait with your own rules, or by calling a moderation-capable model through
+   a separate client, and install it with `with_moderation()` on the builder.
+*/
+
+#[cfg(feature = "reqwest")]
+use crate::client::{ClientConfig, PreparedRequest};
+#[cfg(feature = "reqwest")]
+use crate::error::Error;
+use crate::error::Result;
+use crate::types::chat::Message;
+use futures::future::BoxFuture;
+use std::fmt;
+
+/// The decision of a [`ModerationHook`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// The request may be sent.
+    Allow,
+    /// The request must not be sent, for the given reason.
+    Block { reason: String },
+}
+
+impl Verdict {
+    /// Blocks the request with `reason`.
+    pub fn block(reason: impl Into<String>) -> Self {
+        Verdict::Block {
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Reviews outbound chat requests before they are sent.
+pub trait ModerationHook: Send + Sync + fmt::Debug {
+    /// Reviews the messages of a request to `model`. Returning an error fails the request
+    /// with that error, e.g. when the moderation service itself is unavailable.
+    fn review<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [Message],
+    ) -> BoxFuture<'a, Result<Verdict>>;
+}
+
+/// Runs the configured hook on a prepared chat request, failing with
+/// [`Error::BlockedByPolicy`] if it vetoes the request.
+#[cfg(feature = "reqwest")]
+pub(crate) async fn moderate(config: &ClientConfig, request: &PreparedRequest) -> Result<()> {
+    let Some(ref hook) = config.moderation else {
+        return Ok(());
+    };
+    let model = request.body["model"].as_str().unwrap_or_default();
+    let messages: Vec<Message> =
+        serde_json::from_value(request.body["messages"].clone()).unwrap_or_default();
+    match hook.review(model, &messages).await? {
+        Verdict::Allow => Ok(()),
+        Verdict::Block { reason } => Err(Error::BlockedByPolicy { reason }),
+    }
+}
@@ -0,0 +1,55 @@
+/*!
+   # Redaction Module
+
+   A [`Redactor`] rewrites outbound text, for example to scrub emails, phone numbers, or
+   secrets, before it leaves the process. Install one with `with_redactor()` on the builder.
+   It is applied while preparing every request, so chat, streaming, and text completion
+   calls (and `dry_run` output) all see the redacted text.
+*/
+
+use serde_json::Value;
+use std::fmt;
+
+/// Rewrites text before it is sent to the API.
+pub trait Redactor: Send + Sync + fmt::Debug {
+    /// Returns `text` with sensitive content removed or masked.
+    fn redact(&self, text: &str) -> String;
+}
+
+/// Redacts the user-supplied text in a serialized request body: the content of every
+/// message (plain text and text parts), the arguments of tool calls replayed in assistant
+/// messages, the `prediction` content, and a completion `prompt`.
+///
+/// Tool call arguments are redacted as a whole string, so a redactor that rewrites JSON
+/// syntax may leave them unparseable.
+pub(crate) fn redact_body(redactor: &dyn Redactor, body: &mut Value) {
+    if let Some(messages) = body.get_mut("messages").and_then(Value::as_array_mut) {
+        for message in messages {
+            match message.get_mut("content") {
+                Some(Value::String(text)) => *text = redactor.redact(text),
+                Some(Value::Array(parts)) => {
+                    for part in parts {
+                        if let Some(Value::String(text)) = part.get_mut("text") {
+                            *text = redactor.redact(text);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            if let Some(calls) = message.get_mut("tool_calls").and_then(Value::as_array_mut) {
+                for call in calls {
+                    if let Some(Value::String(arguments)) = call.pointer_mut("/function/arguments")
+                    {
+                        *arguments = redactor.redact(arguments);
+                    }
+                }
+            }
+        }
+    }
+    if let Some(Value::String(content)) = body.pointer_mut("/prediction/content") {
+        *content = redactor.redact(content);
+    }
+    if let Some(Value::String(prompt)) = body.get_mut("prompt") {
+        *prompt = redactor.redact(prompt);
+    }
+}
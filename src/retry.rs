@@ -0,0 +1,49 @@
+/*!
+   # Retry Module
+
+   Decides whether a failed request is retried and how long to wait first. The client uses
+   [`ExponentialBackoff`] built from `max_retries` and `retry_backoff` unless a custom
+   [`RetryPolicy`] is installed with `with_retry_policy()` on the builder.
+*/
+
+use crate::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+/// Decides whether a failed request should be retried.
+pub trait RetryPolicy: Send + Sync + fmt::Debug {
+    /// Returns the delay before the next attempt, or `None` to give up and return `error`.
+    ///
+    /// `attempt` is the number of attempts made so far, starting at 1. Non-success HTTP
+    /// responses are presented as [`Error::ApiError`] with the status code and body.
+    fn should_retry(&self, error: &Error, attempt: u32) -> Option<Duration>;
+}
+
+/// Retries transient errors (see [`Error::is_retryable`]) with exponentially growing delays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExponentialBackoff {
+    /// Maximum number of retries after the initial attempt.
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled on each subsequent attempt.
+    pub initial_delay: Duration,
+}
+
+impl ExponentialBackoff {
+    /// Creates a policy retrying up to `max_retries` times, starting at `initial_delay`.
+    pub fn new(max_retries: u32, initial_delay: Duration) -> Self {
+        Self {
+            max_retries,
+            initial_delay,
+        }
+    }
+}
+
+impl RetryPolicy for ExponentialBackoff {
+    fn should_retry(&self, error: &Error, attempt: u32) -> Option<Duration> {
+        if attempt > self.max_retries || !error.is_retryable() {
+            return None;
+        }
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        Some(self.initial_delay.saturating_mul(factor))
+    }
+}
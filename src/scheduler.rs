@@ -0,0 +1,175 @@
+/*!
+   # Scheduler Module
+
+   An optional priority scheduler that caps how many requests are in flight at once. When the
+   cap is reached, waiting requests are admitted highest [`Priority`] first (and in arrival
+   order within a priority), so interactive requests jump ahead of background batch jobs.
+
+   Install a [`Scheduler`] with `with_scheduler()` on the builder; it can be shared by several
+   clients to cap them together. Requests take the priority of the handle they are sent
+   through, set with `ChatApi::with_priority()`, and default to [`Priority::Normal`].
+*/
+
+use futures::channel::oneshot;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// How urgently a request should be admitted when the scheduler is saturated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Batch and background work, admitted last.
+    Background,
+    /// The default priority.
+    #[default]
+    Normal,
+    /// User-facing requests, admitted first.
+    Interactive,
+}
+
+/// Limits in-flight requests and admits waiting ones by priority.
+pub struct Scheduler {
+    max_in_flight: usize,
+    state: Mutex<State>,
+}
+
+struct State {
+    in_flight: usize,
+    next_seq: u64,
+    waiting: BinaryHeap<Waiter>,
+}
+
+struct Waiter {
+    priority: Priority,
+    seq: u64,
+    admit: oneshot::Sender<Permit>,
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority && self.seq == other.seq
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    // Higher priority first; earlier arrivals first within a priority.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl fmt::Debug for Scheduler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scheduler")
+            .field("max_in_flight", &self.max_in_flight)
+            .field("in_flight", &self.in_flight())
+            .field("queued", &self.queued())
+            .finish()
+    }
+}
+
+impl Scheduler {
+    /// Creates a scheduler admitting at most `max_in_flight` requests at once (at least 1).
+    pub fn new(max_in_flight: usize) -> Self {
+        Self {
+            max_in_flight: max_in_flight.max(1),
+            state: Mutex::new(State {
+                in_flight: 0,
+                next_seq: 0,
+                waiting: BinaryHeap::new(),
+            }),
+        }
+    }
+
+    /// The maximum number of requests in flight at once.
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+
+    /// The number of requests currently admitted.
+    pub fn in_flight(&self) -> usize {
+        self.state.lock().unwrap().in_flight
+    }
+
+    /// The number of requests waiting to be admitted.
+    pub fn queued(&self) -> usize {
+        self.state.lock().unwrap().waiting.len()
+    }
+
+    /// Waits until a request with `priority` may be sent. The slot is released when the
+    /// returned permit is dropped.
+    pub async fn acquire(self: &Arc<Self>, priority: Priority) -> Permit {
+        let admitted = {
+            let mut state = self.state.lock().unwrap();
+            if state.in_flight < self.max_in_flight && state.waiting.is_empty() {
+                state.in_flight += 1;
+                None
+            } else {
+                let (admit, admitted) = oneshot::channel();
+                let seq = state.next_seq;
+                state.next_seq += 1;
+                state.waiting.push(Waiter {
+                    priority,
+                    seq,
+                    admit,
+                });
+                Some(admitted)
+            }
+        };
+        match admitted {
+            None => Permit {
+                scheduler: Some(self.clone()),
+            },
+            // The sender is only dropped together with the scheduler, which `self` keeps alive.
+            Some(admitted) => admitted.await.expect("scheduler outlives its waiters"),
+        }
+    }
+
+    /// Hands a released slot to the highest-priority waiter still listening, or frees it.
+    fn release(self: &Arc<Self>) {
+        let mut state = self.state.lock().unwrap();
+        while let Some(waiter) = state.waiting.pop() {
+            let permit = Permit {
+                scheduler: Some(self.clone()),
+            };
+            match waiter.admit.send(permit) {
+                Ok(()) => return,
+                // The waiter gave up; disarm the permit so its drop does not re-enter `release`.
+                Err(mut permit) => {
+                    permit.scheduler = None;
+                }
+            }
+        }
+        state.in_flight -= 1;
+    }
+}
+
+/// A slot in the scheduler, held while a request is in flight.
+pub struct Permit {
+    scheduler: Option<Arc<Scheduler>>,
+}
+
+impl fmt::Debug for Permit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Permit").finish_non_exhaustive()
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(scheduler) = self.scheduler.take() {
+            scheduler.release();
+        }
+    }
+}
@@ -0,0 +1,230 @@
+/*!
+   # Test Utilities
+
+   Fixture builders for unit testing code that consumes this crate's response types, without
+   hand-writing JSON. Enabled with the `test-utils` feature.
+
+   ```ignore
+   use openrouter_api::test_utils::{tool_call, ChatCompletionResponseBuilder};
+   use serde_json::json;
+
+   let response = ChatCompletionResponseBuilder::new()
+       .model("openai/gpt-4o")
+       .tool_call(tool_call("call_1", "get_weather", json!({ "city": "Paris" })))
+       .build();
+   ```
+*/
+
+use crate::error::Error;
+use crate::models::tool::{FunctionCall, ToolCall};
+use crate::types::chat::{
+    ChatCompletionChunk, ChatCompletionResponse, Choice, ChunkChoice, Content, Delta, Message,
+    Usage,
+};
+use serde_json::{json, Value};
+
+/// Fixed creation timestamp used by the builders, so fixtures are deterministic.
+const CREATED: i64 = 1_700_000_000;
+
+/// Builds a valid [`ChatCompletionResponse`] with a single assistant choice.
+#[derive(Debug, Clone)]
+pub struct ChatCompletionResponseBuilder {
+    id: String,
+    model: String,
+    provider: Option<String>,
+    message: Message,
+    finish_reason: Option<String>,
+    usage: Option<(u32, u32)>,
+}
+
+impl Default for ChatCompletionResponseBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChatCompletionResponseBuilder {
+    /// Starts a response with an empty assistant message and finish reason `stop`.
+    pub fn new() -> Self {
+        Self {
+            id: "gen-test".to_string(),
+            model: "openai/gpt-4o".to_string(),
+            provider: None,
+            message: Message::assistant(""),
+            finish_reason: Some("stop".to_string()),
+            usage: None,
+        }
+    }
+
+    /// Sets the generation id.
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = id.into();
+        self
+    }
+
+    /// Sets the model that produced the response.
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// Sets the provider that served the response.
+    pub fn provider(mut self, provider: impl Into<String>) -> Self {
+        self.provider = Some(provider.into());
+        self
+    }
+
+    /// Sets the assistant message content.
+    pub fn content(mut self, content: impl Into<Content>) -> Self {
+        self.message.content = content.into();
+        self
+    }
+
+    /// Adds a tool call to the assistant message and sets the finish reason to `tool_calls`.
+    pub fn tool_call(mut self, call: ToolCall) -> Self {
+        self.message
+            .tool_calls
+            .get_or_insert_with(Vec::new)
+            .push(call);
+        self.finish_reason = Some("tool_calls".to_string());
+        self
+    }
+
+    /// Sets the finish reason, e.g. `length`.
+    pub fn finish_reason(mut self, reason: impl Into<String>) -> Self {
+        self.finish_reason = Some(reason.into());
+        self
+    }
+
+    /// Attaches token usage; the total is computed from the two counts.
+    pub fn usage(mut self, prompt_tokens: u32, completion_tokens: u32) -> Self {
+        self.usage = Some((prompt_tokens, completion_tokens));
+        self
+    }
+
+    /// Builds the response.
+    pub fn build(self) -> ChatCompletionResponse {
+        ChatCompletionResponse {
+            id: self.id,
+            choices: vec![Choice {
+                message: self.message,
+                native_finish_reason: self.finish_reason.clone(),
+                finish_reason: self.finish_reason,
+            }],
+            created: CREATED,
+            model: self.model,
+            provider: self.provider,
+            usage: self.usage.map(|(prompt, completion)| Usage {
+                prompt_tokens: prompt,
+                completion_tokens: completion,
+                total_tokens: prompt + completion,
+                cost: None,
+            }),
+            metrics: None,
+        }
+    }
+
+    /// Builds the response as the JSON body the API would return.
+    pub fn to_json(self) -> Value {
+        serde_json::to_value(self.build()).expect("response fixtures always serialize")
+    }
+}
+
+/// Builds a [`ChatCompletionChunk`] carrying a fragment of a streamed reply.
+#[derive(Debug, Clone)]
+pub struct ChatCompletionChunkBuilder {
+    id: String,
+    content: String,
+    finish_reason: Option<String>,
+}
+
+impl Default for ChatCompletionChunkBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChatCompletionChunkBuilder {
+    /// Starts an empty chunk.
+    pub fn new() -> Self {
+        Self {
+            id: "gen-test".to_string(),
+            content: String::new(),
+            finish_reason: None,
+        }
+    }
+
+    /// Sets the generation id shared by all chunks of a stream.
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = id.into();
+        self
+    }
+
+    /// Sets the text fragment carried by this chunk.
+    pub fn content(mut self, content: impl Into<String>) -> Self {
+        self.content = content.into();
+        self
+    }
+
+    /// Marks this chunk as the last one, with the given finish reason.
+    pub fn finish_reason(mut self, reason: impl Into<String>) -> Self {
+        self.finish_reason = Some(reason.into());
+        self
+    }
+
+    /// Builds the chunk.
+    pub fn build(self) -> ChatCompletionChunk {
+        ChatCompletionChunk {
+            id: self.id,
+            choices: vec![ChunkChoice {
+                index: 0,
+                delta: Delta {
+                    role: None,
+                    content: (!self.content.is_empty()).then_some(self.content),
+                    reasoning: None,
+                    tool_calls: None,
+                    annotations: None,
+                },
+                native_finish_reason: self.finish_reason.clone(),
+                finish_reason: self.finish_reason,
+            }],
+            metrics: None,
+        }
+    }
+
+    /// Builds the chunk as the JSON payload of an SSE `data:` line.
+    pub fn to_json(self) -> Value {
+        serde_json::to_value(self.build()).expect("chunk fixtures always serialize")
+    }
+}
+
+/// Creates a function [`ToolCall`] with the given arguments encoded as a JSON string.
+pub fn tool_call(id: impl Into<String>, name: impl Into<String>, arguments: Value) -> ToolCall {
+    ToolCall {
+        id: id.into(),
+        kind: "function".to_string(),
+        function_call: FunctionCall {
+            name: name.into(),
+            arguments: arguments.to_string(),
+        },
+    }
+}
+
+/// Creates the JSON error body OpenRouter returns for a failed request.
+pub fn error_body(code: u16, message: impl Into<String>) -> Value {
+    json!({
+        "error": {
+            "code": code,
+            "message": message.into(),
+        }
+    })
+}
+
+/// Creates the [`Error::ApiError`] the client produces for a failed request.
+pub fn api_error(code: u16, message: impl Into<String>) -> Error {
+    Error::ApiError {
+        code,
+        message: error_body(code, message).to_string(),
+        metadata: None,
+    }
+}
@@ -5,11 +5,13 @@
    Integration tests for the OpenRouter client.
 */
 
-#[cfg(test)]
+#[cfg(all(test, feature = "reqwest"))]
+#[allow(clippy::module_inception)]
 mod integration_tests {
     use crate::client::{OpenRouterClient, Unconfigured};
     #[allow(unused_imports)]
     use crate::models::chat::{ChatMessage, ChatRole};
+    use crate::models::parameters::{DefaultParams, SamplingParams};
     #[allow(unused_imports)]
     use crate::models::provider_preferences::{
         DataCollection, ProviderPreferences, ProviderSort, Quantization,
@@ -19,6 +21,7 @@ mod integration_tests {
     #[allow(unused_imports)]
     use crate::models::tool::{FunctionCall, FunctionDescription, Tool, ToolCall};
     use crate::types::chat::{ChatCompletionRequest, ChatCompletionResponse, Message};
+    use crate::types::completion::CompletionRequest;
     use serde_json::{json, Value};
     use std::env;
     use url::Url;
@@ -46,9 +49,11 @@ mod integration_tests {
             model: "openai/gpt-4o".to_string(),
             messages: vec![Message {
                 role: "user".to_string(),
-                content: "What is a phantom type in Rust?".to_string(),
+                content: "What is a phantom type in Rust?".into(),
                 name: None,
                 tool_calls: None,
+                tool_call_id: None,
+                annotations: None,
             }],
             stream: None,
             response_format: None,
@@ -56,6 +61,13 @@ mod integration_tests {
             provider: None,
             models: None,
             transforms: None,
+            sampling: Default::default(),
+            max_tokens: None,
+            max_completion_tokens: None,
+            prediction: None,
+            modalities: None,
+            route: None,
+            tags: Default::default(),
         };
 
         // For this integration test we are simulating a response.
@@ -123,6 +135,7 @@ mod integration_tests {
                 http_referer: None,
                 site_title: None,
                 timeout: std::time::Duration::from_secs(30),
+                ..Default::default()
             },
             http_client: None,
             _state: std::marker::PhantomData,
@@ -135,6 +148,7 @@ mod integration_tests {
     }
 
     #[tokio::test]
+    #[allow(clippy::len_zero)]
     async fn test_text_completion_response_deserialization(
     ) -> Result<(), Box<dyn std::error::Error>> {
         // Simulated response JSON from the text completion endpoint.
@@ -200,6 +214,7 @@ mod integration_tests {
                 http_referer: None,
                 site_title: None,
                 timeout: std::time::Duration::from_secs(30),
+                ..Default::default()
             },
             http_client: None,
             _state: std::marker::PhantomData,
@@ -228,21 +243,23 @@ mod integration_tests {
             allow_fallbacks: Some(false),
             require_parameters: Some(true),
             data_collection: Some(crate::models::provider_preferences::DataCollection::Deny),
-            ignore: Some(vec!["Azure".to_string()]),
+            ignore: Some(vec!["Azure".into()]),
             quantizations: Some(vec![
                 crate::models::provider_preferences::Quantization::Fp8,
                 crate::models::provider_preferences::Quantization::Int8,
             ]),
             sort: Some(crate::models::provider_preferences::ProviderSort::Throughput),
+            ..Default::default()
         };
 
         // Start with an empty extra parameters object.
         let extra_params = json!({});
 
         // Use the request builder to attach the provider preferences.
-        let builder =
-            crate::api::request::RequestBuilder::new("openai/gpt-4o", vec![], extra_params)
-                .with_provider_preferences(preferences);
+        let builder = crate::api::request::RequestBuilder::new(extra_params)
+            .model("openai/gpt-4o")
+            .message(Message::user("Hello"))
+            .with_provider_preferences(preferences)?;
 
         // Serialize the complete payload.
         let payload = builder.build();
@@ -252,7 +269,7 @@ mod integration_tests {
         // Check that the serialized JSON contains the "provider" key with the expected configuration.
         let payload_value: Value = serde_json::from_str(&payload_json)?;
         let provider_config = payload_value.get("provider").expect("provider key missing");
-        assert_eq!(provider_config.get("allowFallbacks").unwrap(), false);
+        assert_eq!(provider_config.get("allow_fallbacks").unwrap(), false);
         assert_eq!(provider_config.get("sort").unwrap(), "throughput");
 
         Ok(())
@@ -286,4 +303,4458 @@ mod integration_tests {
         assert_eq!(response.results.len(), 2);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_message_content_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::types::chat::{Content, ContentPart};
+
+        // Plain text content serializes as a JSON string.
+        let text: Message = serde_json::from_value(json!({
+            "role": "user",
+            "content": "Hello"
+        }))?;
+        assert_eq!(text.content.as_text(), Some("Hello"));
+        assert_eq!(serde_json::to_value(&text)?["content"], json!("Hello"));
+
+        // Multimodal content serializes as an array of typed parts.
+        let parts_json = json!([
+            { "type": "text", "text": "What is in this image?" },
+            { "type": "image_url", "image_url": { "url": "https://example.com/cat.png" } }
+        ]);
+        let multimodal: Message = serde_json::from_value(json!({
+            "role": "user",
+            "content": parts_json.clone()
+        }))?;
+        match &multimodal.content {
+            Content::Parts(parts) => {
+                assert_eq!(parts.len(), 2);
+                assert_eq!(
+                    parts[1],
+                    ContentPart::image_url("https://example.com/cat.png")
+                );
+            }
+            Content::Text(_) => panic!("Expected structured content parts"),
+        }
+        assert_eq!(multimodal.content.to_text(), "What is in this image?");
+        assert_eq!(serde_json::to_value(&multimodal)?["content"], parts_json);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_respond_with() -> Result<(), Box<dyn std::error::Error>> {
+        let call = ToolCall {
+            id: "call-042".to_string(),
+            kind: "function".to_string(),
+            function_call: FunctionCall {
+                name: "get_weather".to_string(),
+                arguments: "{\"location\": \"Boston\"}".to_string(),
+            },
+        };
+
+        let reply = call.respond_with("72F and sunny");
+        assert_eq!(reply.role, "tool");
+        assert_eq!(reply.tool_call_id.as_deref(), Some("call-042"));
+
+        let reply_json = serde_json::to_value(&reply)?;
+        assert_eq!(
+            reply_json,
+            json!({
+                "role": "tool",
+                "content": "72F and sunny",
+                "tool_call_id": "call-042"
+            })
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_message_name_serialization() -> Result<(), Box<dyn std::error::Error>> {
+        let message = Message::user("Can you review my PR?").with_name("alice");
+        let message_json = serde_json::to_value(&message)?;
+        assert_eq!(message_json["name"], "alice");
+
+        // The model-side message carries the name through to the API type.
+        let converted: Message = ChatMessage {
+            role: ChatRole::User,
+            content: "And mine too.".to_string(),
+            name: Some("bob".to_string()),
+        }
+        .into();
+        assert_eq!(converted.name.as_deref(), Some("bob"));
+
+        // Unnamed messages omit the field entirely.
+        let anonymous = serde_json::to_value(Message::user("Hi"))?;
+        assert!(anonymous.get("name").is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_default_system_prompt() -> Result<(), Box<dyn std::error::Error>> {
+        let config = crate::client::ClientConfig {
+            system_prompt: Some("You are a terse assistant.".to_string()),
+            ..Default::default()
+        };
+
+        // The system prompt is prepended when the request has none.
+        let mut request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hello")],
+            ..Default::default()
+        };
+        config.apply_chat_defaults(&mut request);
+        assert_eq!(request.messages.len(), 2);
+        assert_eq!(request.messages[0].role, "system");
+        assert_eq!(
+            request.messages[0].content.as_text(),
+            Some("You are a terse assistant.")
+        );
+
+        // A request that defines its own system message is left untouched.
+        let mut request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::system("Be verbose."), Message::user("Hello")],
+            ..Default::default()
+        };
+        config.apply_chat_defaults(&mut request);
+        assert_eq!(request.messages.len(), 2);
+        assert_eq!(request.messages[0].content.as_text(), Some("Be verbose."));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_conversation_store() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::conversation::{Conversation, ConversationStore, InMemoryConversationStore};
+        use std::sync::Arc;
+
+        let store: Arc<dyn ConversationStore> = Arc::new(InMemoryConversationStore::new());
+        assert!(store.load("session-1").await?.is_none());
+
+        let mut conversation = Conversation::new("session-1");
+        conversation.push(Message::user("Hello"));
+        conversation.push(Message::assistant("Hi there!"));
+        store.save(&conversation).await?;
+
+        let loaded = store.load("session-1").await?.expect("conversation saved");
+        assert_eq!(loaded.messages.len(), 2);
+        assert_eq!(loaded.messages[1].content.as_text(), Some("Hi there!"));
+
+        // Saving again replaces the stored version.
+        conversation.push(Message::user("Bye"));
+        store.save(&conversation).await?;
+        let loaded = store.load("session-1").await?.expect("conversation saved");
+        assert_eq!(loaded.messages.len(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_conversation_summarization() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::conversation::{
+            Conversation, ConversationManager, ConversationStore, InMemoryConversationStore,
+            SummarizationConfig,
+        };
+        use std::sync::Arc;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-summary",
+                "choices": [{
+                    "message": { "role": "assistant", "content": "Earlier turns, condensed." },
+                    "finish_reason": "stop",
+                    "native_finish_reason": "stop"
+                }],
+                "created": 1234567890,
+                "model": "openai/gpt-4o-mini"
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("test-key")?;
+
+        let store = Arc::new(InMemoryConversationStore::new());
+        let mut conversation = Conversation::new("long-chat");
+        for i in 0..2 {
+            conversation.push(Message::user(format!("Question {} {}", i, "x".repeat(200))));
+            conversation.push(Message::assistant(format!("Answer {}", i)));
+        }
+        store.save(&conversation).await?;
+
+        let manager = ConversationManager::new(client.chat()?, store.clone(), "openai/gpt-4o")
+            .with_summarization(
+                SummarizationConfig::new(50, "openai/gpt-4o-mini").with_keep_recent(2),
+            );
+        manager
+            .send("long-chat", Message::user("Next question"))
+            .await?;
+
+        // One summarization call followed by the actual chat call.
+        let requests = server.received_requests().await.expect("requests recorded");
+        assert_eq!(requests.len(), 2);
+        let summary_request: Value = serde_json::from_slice(&requests[0].body)?;
+        assert_eq!(summary_request["model"], "openai/gpt-4o-mini");
+
+        let saved = store.load("long-chat").await?.expect("conversation saved");
+        let roles: Vec<&str> = saved.messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, vec!["system", "assistant", "user", "assistant"]);
+        assert!(saved.messages[0]
+            .content
+            .to_text()
+            .contains("Earlier turns, condensed."));
+        assert_eq!(saved.messages[2].content.as_text(), Some("Next question"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_truncation_strategies() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::conversation::{DropOldest, ImportanceBased, SlidingWindow, TruncationStrategy};
+        use crate::utils::tokens::estimate_messages_tokens;
+
+        let history = || {
+            vec![
+                Message::system("Be helpful."),
+                Message::user("first ".repeat(20)),
+                Message::assistant("second ".repeat(20)),
+                Message::user("PINNED: third ".repeat(20)),
+                Message::assistant("fourth ".repeat(20)),
+                Message::user("latest"),
+            ]
+        };
+        let budget = estimate_messages_tokens(&history()[3..]) + 10;
+
+        // Drop-oldest removes the earliest non-system turns first.
+        let mut messages = history();
+        DropOldest.truncate(&mut messages, budget);
+        assert_eq!(messages[0].role, "system");
+        assert!(messages[1].content.to_text().starts_with("PINNED"));
+        assert!(estimate_messages_tokens(&messages) <= budget);
+
+        // Sliding window keeps only the most recent N non-system messages.
+        let mut messages = history();
+        SlidingWindow::new(2).truncate(&mut messages, usize::MAX);
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[2].content.as_text(), Some("latest"));
+
+        // Importance-based eviction keeps what the scorer ranks highly.
+        let mut messages = history();
+        let pinned_first = ImportanceBased::new(|m: &Message, _| {
+            if m.content.to_text().starts_with("PINNED") {
+                1.0
+            } else {
+                0.0
+            }
+        });
+        pinned_first.truncate(&mut messages, budget);
+        assert!(messages
+            .iter()
+            .any(|m| m.content.to_text().starts_with("PINNED")));
+        assert_eq!(messages.last().unwrap().content.as_text(), Some("latest"));
+
+        // Evicting an assistant tool call also evicts its tool result.
+        let call = ToolCall {
+            id: "call-1".to_string(),
+            kind: "function".to_string(),
+            function_call: FunctionCall {
+                name: "lookup".to_string(),
+                arguments: "{}".to_string(),
+            },
+        };
+        let mut messages = vec![
+            Message {
+                tool_calls: Some(vec![call.clone()]),
+                ..Message::assistant("")
+            },
+            call.respond_with("x".repeat(400)),
+            Message::user("latest"),
+        ];
+        DropOldest.truncate(&mut messages, 20);
+        assert_eq!(messages.len(), 1);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "openai")]
+    #[tokio::test]
+    async fn test_openai_interop_conversions() -> Result<(), Box<dyn std::error::Error>> {
+        use async_openai::types::chat::{
+            CreateChatCompletionRequest, CreateChatCompletionResponse,
+        };
+
+        let openai_request: CreateChatCompletionRequest = serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "messages": [
+                { "role": "system", "content": "Be brief." },
+                { "role": "user", "content": "Weather in Boston?" },
+                { "role": "assistant", "tool_calls": [{
+                    "id": "call-1",
+                    "type": "function",
+                    "function": { "name": "get_weather", "arguments": "{}" }
+                }]},
+                { "role": "tool", "tool_call_id": "call-1", "content": "Sunny" }
+            ],
+            "response_format": {
+                "type": "json_schema",
+                "json_schema": {
+                    "name": "weather",
+                    "schema": { "type": "object", "properties": { "sky": { "type": "string" } } },
+                    "strict": true
+                }
+            },
+            "prediction": { "type": "content", "content": "Sunny in Boston." },
+            "modalities": ["text"]
+        }))?;
+        let request = ChatCompletionRequest::try_from(openai_request)?;
+        assert_eq!(request.model, "gpt-4o");
+        assert_eq!(request.messages.len(), 4);
+        assert_eq!(request.messages[3].tool_call_id.as_deref(), Some("call-1"));
+        let converted = serde_json::to_value(&request)?;
+        assert_eq!(
+            converted["response_format"]["json_schema"]["name"],
+            "weather"
+        );
+        assert_eq!(converted["prediction"]["content"], "Sunny in Boston.");
+        assert_eq!(converted["modalities"], json!(["text"]));
+
+        // OpenRouter-only fields are dropped when converting back.
+        let request = ChatCompletionRequest {
+            models: Some(vec!["openai/gpt-4o-mini".to_string()]),
+            ..request
+        };
+        let openai_request = CreateChatCompletionRequest::try_from(request)?;
+        assert_eq!(openai_request.messages.len(), 4);
+        let round_trip = serde_json::to_value(&openai_request)?;
+        assert_eq!(round_trip["response_format"], converted["response_format"]);
+        assert_eq!(round_trip["prediction"], converted["prediction"]);
+        assert_eq!(round_trip["modalities"], converted["modalities"]);
+
+        let response = deserialize_chat_response(
+            r#"{
+                "id": "gen-1",
+                "choices": [{
+                    "message": { "role": "assistant", "content": "Sunny in Boston." },
+                    "finish_reason": "stop",
+                    "native_finish_reason": "stop"
+                }],
+                "created": 1234567890,
+                "model": "openai/gpt-4o"
+            }"#,
+        );
+        let openai_response = CreateChatCompletionResponse::try_from(response)?;
+        assert_eq!(openai_response.object, "chat.completion");
+        assert_eq!(
+            openai_response.choices[0].message.content.as_deref(),
+            Some("Sunny in Boston.")
+        );
+        let round_trip = ChatCompletionResponse::try_from(openai_response)?;
+        assert_eq!(
+            round_trip.choices[0].message.content.as_text(),
+            Some("Sunny in Boston.")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dry_run() -> Result<(), Box<dyn std::error::Error>> {
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_site_title("Dry Run Tests")
+            .with_system_prompt("Answer in one word.")
+            .with_api_key("sk-or-test")?;
+
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Capital of France?")],
+            ..Default::default()
+        };
+        let prepared = client.dry_run(&request)?;
+
+        assert_eq!(prepared.method, reqwest::Method::POST);
+        assert_eq!(
+            prepared.url.as_str(),
+            "https://openrouter.ai/api/v1/chat/completions"
+        );
+        assert_eq!(prepared.headers["authorization"], "Bearer sk-or-test");
+        assert_eq!(prepared.headers["x-title"], "Dry Run Tests");
+        assert_eq!(
+            prepared.body,
+            json!({
+                "model": "openai/gpt-4o",
+                "messages": [
+                    { "role": "system", "content": "Answer in one word." },
+                    { "role": "user", "content": "Capital of France?" }
+                ]
+            })
+        );
+
+        // Validation runs as it would for a real call.
+        let invalid = ChatCompletionRequest {
+            model: String::new(),
+            messages: vec![Message::user("Capital of France?")],
+            ..Default::default()
+        };
+        assert!(matches!(
+            client.dry_run(&invalid),
+            Err(crate::error::Error::InvalidRequest(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_request_to_curl() -> Result<(), Box<dyn std::error::Error>> {
+        let config = crate::client::ClientConfig {
+            api_key: Some("sk-or-secret".to_string()),
+            ..Default::default()
+        };
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("It's a test")],
+            ..Default::default()
+        };
+
+        let curl = request.to_curl(&config)?;
+        assert!(curl.starts_with("curl -X POST 'https://openrouter.ai/api/v1/chat/completions'"));
+        assert!(curl.contains("-H \"Authorization: Bearer $OPENROUTER_API_KEY\""));
+        assert!(curl.contains("-H 'content-type: application/json'"));
+        assert!(!curl.contains("sk-or-secret"));
+        // Single quotes in the body are escaped for the shell.
+        assert!(curl.contains(r#""content":"It'\''s a test""#));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_custom_transport() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::client::PreparedRequest;
+        use crate::transport::{HttpResponse, Transport};
+        use futures::future::BoxFuture;
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Debug, Default)]
+        struct CannedTransport {
+            seen: Mutex<Vec<String>>,
+        }
+
+        impl Transport for CannedTransport {
+            fn execute(
+                &self,
+                request: PreparedRequest,
+            ) -> BoxFuture<'_, crate::error::Result<HttpResponse>> {
+                self.seen.lock().unwrap().push(request.url.to_string());
+                Box::pin(async move {
+                    Ok(HttpResponse::from_bytes(
+                        reqwest::StatusCode::OK,
+                        Default::default(),
+                        r#"{"id":"gen-canned","choices":[{"message":{"role":"assistant","content":"canned"},"finish_reason":"stop","native_finish_reason":"stop"}],"created":0,"model":"test/model"}"#,
+                    ))
+                })
+            }
+        }
+
+        let transport = Arc::new(CannedTransport::default());
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://example.invalid/api/v1/")?
+            .with_transport(transport.clone())
+            .with_api_key("sk-or-test")?;
+
+        let request = ChatCompletionRequest {
+            model: "test/model".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+        let response = client.chat()?.chat_completion(request).await?;
+        assert_eq!(
+            response.choices[0].message.content.as_text(),
+            Some("canned")
+        );
+        assert_eq!(
+            transport.seen.lock().unwrap().as_slice(),
+            ["https://example.invalid/api/v1/chat/completions"]
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "replay")]
+    #[tokio::test]
+    async fn test_replay_transport_record_then_replay() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::transport::ReplayTransport;
+        use std::sync::Arc;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let cassettes =
+            std::env::temp_dir().join(format!("openrouter_api_cassettes_{}", std::process::id()));
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-recorded",
+                "choices": [{
+                    "message": { "role": "assistant", "content": "recorded answer" },
+                    "finish_reason": "stop",
+                    "native_finish_reason": "stop"
+                }],
+                "created": 1234567890,
+                "model": "openai/gpt-4o"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let base_url = format!("{}/", server.uri());
+        let request = || ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Record me")],
+            ..Default::default()
+        };
+
+        // Record mode hits the live server and writes a cassette.
+        let recorder = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(base_url.clone())?
+            .with_transport(Arc::new(ReplayTransport::record(&cassettes)))
+            .with_api_key("sk-or-secret")?;
+        recorder.chat()?.chat_completion(request()).await?;
+
+        // The cassette never contains the API key.
+        let cassette = std::fs::read_dir(&cassettes)?
+            .next()
+            .expect("cassette")?
+            .path();
+        assert!(!std::fs::read_to_string(&cassette)?.contains("sk-or-secret"));
+
+        // Replay mode serves the recorded response without another request.
+        let replayer = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(base_url)?
+            .with_transport(Arc::new(ReplayTransport::replay(&cassettes)))
+            .with_api_key("sk-or-other")?;
+        let response = replayer.chat()?.chat_completion(request()).await?;
+        assert_eq!(response.id, "gen-recorded");
+        assert_eq!(
+            response.choices[0].message.content.as_text(),
+            Some("recorded answer")
+        );
+
+        std::fs::remove_dir_all(&cassettes)?;
+        Ok(())
+    }
+
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    #[tokio::test]
+    async fn test_custom_tls_configuration() -> Result<(), Box<dyn std::error::Error>> {
+        const TEST_CA_PEM: &str = "-----BEGIN CERTIFICATE-----
+MIIBkjCCATegAwIBAgIUe2x4+YHkl/AaoV9It31whzUw9/0wCgYIKoZIzj0EAwIw
+HTEbMBkGA1UEAwwSb3BlbnJvdXRlci10ZXN0LWNhMCAXDTI2MTAxNDA1MjU0NFoY
+DzIxMjYwOTIwMDUyNTQ0WjAdMRswGQYDVQQDDBJvcGVucm91dGVyLXRlc3QtY2Ew
+WTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAATW2JBT2zYBg6830Gg4jqvd0MTUdSKD
+OKS2rlvRUAoS0TdfxUeOfKbotsdg3KZNVPiDqlaZIDwecsowhxWlNfRYo1MwUTAd
+BgNVHQ4EFgQUfooZUPpKP5MErM331eOSkkJk2uAwHwYDVR0jBBgwFoAUfooZUPpK
+P5MErM331eOSkkJk2uAwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNJADBG
+AiEAmRmu9ax6EvonXMLdDXfhM+nxRgRQBdh7FBiA+cFDGpsCIQD1np2A41wSDwiq
+ZbeH8JvvKUjDYWNI4mpA9wrrlBJtdg==
+-----END CERTIFICATE-----
+";
+        let certificate = reqwest::Certificate::from_pem(TEST_CA_PEM.as_bytes())?;
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_root_certificate(certificate)
+            .with_no_proxy()
+            .with_api_key("sk-or-test")?;
+
+        assert_eq!(client.config.root_certificates.len(), 1);
+        assert!(client.config.tls_backend.is_none());
+        assert!(client.config.no_proxy);
+        assert!(client.http_client.is_some());
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "rustls", feature = "native-tls"))]
+    #[tokio::test]
+    async fn test_tls_backend_selection() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::client::TlsBackend;
+
+        for backend in [TlsBackend::Rustls, TlsBackend::NativeTls] {
+            let client = OpenRouterClient::<Unconfigured>::new()
+                .with_base_url("https://openrouter.ai/api/v1/")?
+                .with_tls_backend(backend)
+                .with_api_key("sk-or-test")?;
+            assert_eq!(client.config.tls_backend, Some(backend));
+            assert!(client.http_client.is_some());
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_per_request_base_url_override() -> Result<(), Box<dyn std::error::Error>> {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let gateway = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-gateway",
+                "choices": [{
+                    "message": { "role": "assistant", "content": "via gateway" },
+                    "finish_reason": "stop",
+                    "native_finish_reason": "stop"
+                }],
+                "created": 1234567890,
+                "model": "local/llama"
+            })))
+            .expect(1)
+            .mount(&gateway)
+            .await;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_api_key("sk-or-test")?;
+        let request = ChatCompletionRequest {
+            model: "local/llama".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+
+        let chat = client
+            .chat()?
+            .with_base_url(format!("{}/v1/", gateway.uri()))?;
+        assert!(chat
+            .dry_run(&request)?
+            .url
+            .as_str()
+            .starts_with(&gateway.uri()));
+        let response = chat.chat_completion(request).await?;
+        assert_eq!(response.id, "gen-gateway");
+
+        // The client itself keeps its original base URL.
+        assert_eq!(
+            client.config.base_url.as_str(),
+            "https://openrouter.ai/api/v1/"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_per_request_api_key_override() -> Result<(), Box<dyn std::error::Error>> {
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(header("authorization", "Bearer sk-or-tenant-b"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-tenant-b",
+                "choices": [{
+                    "message": { "role": "assistant", "content": "billed to tenant b" },
+                    "finish_reason": "stop",
+                    "native_finish_reason": "stop"
+                }],
+                "created": 1234567890,
+                "model": "openai/gpt-4o"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("sk-or-shared")?;
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+
+        let response = client
+            .chat()?
+            .with_api_key("sk-or-tenant-b")
+            .chat_completion(request)
+            .await?;
+        assert_eq!(response.id, "gen-tenant-b");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_provider_require_parameters() -> Result<(), Box<dyn std::error::Error>> {
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            provider: Some(ProviderPreferences::default().with_require_parameters(true)),
+            ..Default::default()
+        };
+
+        let body = serde_json::to_value(&request)?;
+        assert_eq!(body["provider"], json!({ "require_parameters": true }));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_provider_only_and_ignore_lists() -> Result<(), Box<dyn std::error::Error>> {
+        let preferences = ProviderPreferences::default()
+            .with_only(["openai", "azure"])
+            .with_ignore(["deepinfra"]);
+        preferences.validate()?;
+        assert_eq!(
+            serde_json::to_value(&preferences)?,
+            json!({ "only": ["openai", "azure"], "ignore": ["deepinfra"] })
+        );
+
+        // A provider cannot be both required and excluded.
+        let conflicting = ProviderPreferences::default()
+            .with_only(["openai"])
+            .with_ignore(["openai"]);
+        assert!(matches!(
+            conflicting.validate(),
+            Err(crate::error::Error::InvalidRequest(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_configuration_presets() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::client::{ClientConfig, Preset};
+        use std::time::Duration;
+
+        let config = ClientConfig::preset(Preset::LowLatency);
+        assert_eq!(config.timeout, Duration::from_secs(15));
+        assert_eq!(config.max_retries, 1);
+
+        // Presets can be tweaked afterwards and fill in provider routing for requests.
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_preset(Preset::Cheapest)
+            .with_timeout(Duration::from_secs(30))
+            .with_api_key("sk-or-test")?;
+        assert_eq!(client.config.timeout, Duration::from_secs(30));
+        assert_eq!(client.config.max_retries, 3);
+
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+        let prepared = client.dry_run(&request)?;
+        assert_eq!(prepared.body["provider"], json!({ "sort": "price" }));
+
+        // Explicit request preferences take precedence over the preset.
+        let explicit = ChatCompletionRequest {
+            provider: Some(ProviderPreferences::default().with_require_parameters(true)),
+            ..request
+        };
+        let prepared = client.dry_run(&explicit)?;
+        assert_eq!(
+            prepared.body["provider"],
+            json!({ "require_parameters": true })
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_failures() -> Result<(), Box<dyn std::error::Error>> {
+        use std::time::Duration;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-retried",
+                "choices": [{
+                    "message": { "role": "assistant", "content": "ok" },
+                    "finish_reason": "stop",
+                    "native_finish_reason": "stop"
+                }],
+                "created": 1234567890,
+                "model": "openai/gpt-4o"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_retries(2, Duration::from_millis(1))
+            .with_api_key("sk-or-test")?;
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+
+        let response = client.chat()?.chat_completion(request).await?;
+        assert_eq!(response.id, "gen-retried");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_error_classification() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::error::Error;
+
+        let api_error = |code| Error::ApiError {
+            code,
+            message: String::new(),
+            metadata: None,
+        };
+
+        assert!(api_error(429).is_retryable());
+        assert!(api_error(429).is_rate_limited());
+        assert!(api_error(503).is_retryable());
+        assert!(api_error(401).is_auth_error());
+        assert!(!api_error(401).is_retryable());
+        assert!(api_error(402).is_billing_error());
+        assert!(api_error(400).is_client_bug());
+        assert!(Error::InvalidRequest("model must not be empty".into()).is_client_bug());
+        assert!(!Error::Unknown.is_retryable());
+        assert_eq!(api_error(502).status_code(), Some(502));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_custom_retry_policy() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::error::Error;
+        use crate::retry::{ExponentialBackoff, RetryPolicy};
+        use std::sync::Arc;
+        use std::time::Duration;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        // The built-in backoff doubles its delay and gives up on non-transient errors.
+        let backoff = ExponentialBackoff::new(3, Duration::from_millis(100));
+        let unavailable = Error::ApiError {
+            code: 503,
+            message: String::new(),
+            metadata: None,
+        };
+        assert_eq!(
+            backoff.should_retry(&unavailable, 2),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(backoff.should_retry(&unavailable, 4), None);
+        assert_eq!(backoff.should_retry(&Error::Unknown, 1), None);
+
+        #[derive(Debug)]
+        struct NeverRetry;
+
+        impl RetryPolicy for NeverRetry {
+            fn should_retry(&self, _error: &Error, _attempt: u32) -> Option<Duration> {
+                None
+            }
+        }
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(503).set_body_string("overloaded"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        // The custom policy wins over the retry count configured on the builder.
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_retries(5, Duration::from_millis(1))
+            .with_retry_policy(Arc::new(NeverRetry))
+            .with_api_key("sk-or-test")?;
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+
+        let error = client.chat()?.chat_completion(request).await.unwrap_err();
+        assert_eq!(error.status_code(), Some(503));
+        assert!(error.to_string().contains("overloaded"));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "test-utils")]
+    #[tokio::test]
+    async fn test_fixture_builders() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::test_utils::{
+            api_error, tool_call, ChatCompletionChunkBuilder, ChatCompletionResponseBuilder,
+        };
+
+        let body = ChatCompletionResponseBuilder::new()
+            .id("gen-fixture")
+            .tool_call(tool_call(
+                "call_1",
+                "get_weather",
+                json!({ "city": "Paris" }),
+            ))
+            .usage(12, 3)
+            .to_json();
+        let response: ChatCompletionResponse = serde_json::from_value(body)?;
+        assert_eq!(response.id, "gen-fixture");
+        assert_eq!(
+            response.choices[0].finish_reason.as_deref(),
+            Some("tool_calls")
+        );
+        let calls = response.choices[0].message.tool_calls.as_ref().unwrap();
+        assert_eq!(calls[0].function_call.arguments, r#"{"city":"Paris"}"#);
+        assert_eq!(response.usage.unwrap().total_tokens, 15);
+
+        let chunk = ChatCompletionChunkBuilder::new().content("Hel").build();
+        assert_eq!(chunk.choices[0].delta.content.as_deref(), Some("Hel"));
+
+        let error = api_error(429, "Rate limit exceeded");
+        assert!(error.is_rate_limited());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn test_mock_openrouter_server() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::mock::MockOpenRouter;
+        use futures::TryStreamExt;
+
+        let server = MockOpenRouter::start().await;
+        server.mock_chat_reply("Hello!").await;
+        server.mock_chat_stream(&["Hel", "lo", "!"]).await;
+
+        let chat = server.client()?.chat()?;
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+
+        let response = chat.chat_completion(request.clone()).await?;
+        assert_eq!(response.choices[0].message.content.to_text(), "Hello!");
+
+        let chunks: Vec<_> = chat.chat_completion_stream(request).try_collect().await?;
+        let text: String = chunks
+            .iter()
+            .filter_map(|chunk| chunk.choices[0].delta.content.as_deref())
+            .collect();
+        assert_eq!(text, "Hello!");
+        assert_eq!(
+            chunks.last().unwrap().choices[0].finish_reason.as_deref(),
+            Some("stop")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_audit_sink_records_requests() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::audit::{AuditRecord, AuditSink};
+        use futures::future::BoxFuture;
+        use std::sync::{Arc, Mutex};
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        #[derive(Debug, Default)]
+        struct CollectingSink(Mutex<Vec<AuditRecord>>);
+
+        impl AuditSink for CollectingSink {
+            fn record(&self, record: AuditRecord) -> BoxFuture<'_, ()> {
+                self.0.lock().unwrap().push(record);
+                Box::pin(async {})
+            }
+        }
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-audited",
+                "choices": [{
+                    "message": { "role": "assistant", "content": "ok" },
+                    "finish_reason": "stop",
+                    "native_finish_reason": "stop"
+                }],
+                "created": 1234567890,
+                "model": "openai/gpt-4o",
+                "usage": { "prompt_tokens": 10, "completion_tokens": 2, "total_tokens": 12, "cost": 0.0004 }
+            })))
+            .mount(&server)
+            .await;
+
+        let sink = Arc::new(CollectingSink::default());
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_audit_sink(sink.clone())
+            .with_audit_prompt_hash(true)
+            .with_api_key("sk-or-test")?;
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+        client.chat()?.chat_completion(request.clone()).await?;
+
+        // Failures are recorded too, without the response body.
+        let failing = client
+            .chat()?
+            .with_base_url(format!("{}/missing/", server.uri()))?;
+        assert!(failing.chat_completion(request).await.is_err());
+
+        // Text completions are recorded as well.
+        Mock::given(method("POST"))
+            .and(path("/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-text",
+                "choices": [{ "text": "upon a time", "index": 0, "finish_reason": "stop" }]
+            })))
+            .mount(&server)
+            .await;
+        let completion = CompletionRequest {
+            model: "openai/gpt-3.5-turbo-instruct".to_string(),
+            prompt: "Once".to_string(),
+            sampling: Default::default(),
+            extra_params: json!({}),
+        };
+        let completion_api = crate::api::completion::CompletionApi::new(
+            client.http_client.clone().expect("HTTP client"),
+            &client.config,
+        );
+        completion_api.text_completion(completion).await?;
+
+        let records = sink.0.lock().unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[2].model, "openai/gpt-3.5-turbo-instruct");
+        assert_eq!(records[2].request_id.as_deref(), Some("gen-text"));
+        assert_eq!(records[2].status, Some(200));
+        assert_eq!(records[2].prompt_hash.as_ref().map(String::len), Some(16));
+        assert_eq!(records[0].model, "openai/gpt-4o");
+        assert_eq!(records[0].request_id.as_deref(), Some("gen-audited"));
+        assert_eq!(records[0].status, Some(200));
+        assert_eq!(records[0].total_tokens, Some(12));
+        assert_eq!(records[0].cost, Some(rust_decimal::Decimal::new(4, 4)));
+        assert_eq!(records[0].prompt_hash.as_ref().map(String::len), Some(16));
+        assert_eq!(records[1].status, Some(404));
+        assert_eq!(records[1].error.as_deref(), Some("API error (status 404)"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cost_budget_guardrails() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::budget::{CostBudget, ModelPricing};
+        use crate::error::Error;
+        use rust_decimal::Decimal;
+        use std::sync::Arc;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-budget",
+                "choices": [{
+                    "message": { "role": "assistant", "content": "ok" },
+                    "finish_reason": "stop",
+                    "native_finish_reason": "stop"
+                }],
+                "created": 1234567890,
+                "model": "openai/gpt-4o",
+                "usage": { "prompt_tokens": 10, "completion_tokens": 2, "total_tokens": 12, "cost": 0.6 }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let budget = Arc::new(
+            CostBudget::new()
+                .with_pricing(
+                    "openai/gpt-4o",
+                    ModelPricing::new(Decimal::new(1, 4), Decimal::new(1, 4)),
+                )
+                .with_pricing(
+                    "openai/o1-pro",
+                    ModelPricing::new(Decimal::new(1, 2), Decimal::new(1, 2)),
+                )
+                .with_max_request_cost(Decimal::ONE)
+                .with_max_total_cost(Decimal::ONE),
+        );
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_budget(budget.clone())
+            .with_api_key("sk-or-test")?;
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+
+        // A large completion limit is priced in, so the request is rejected before it is sent.
+        let long = ChatCompletionRequest {
+            max_tokens: Some(20_000),
+            ..request.clone()
+        };
+        let error = client.chat()?.chat_completion(long).await.unwrap_err();
+        assert!(
+            matches!(error, Error::BudgetExceeded { estimated, .. } if estimated > Decimal::ONE)
+        );
+
+        // An expensive model is rejected before any request is sent.
+        let expensive = ChatCompletionRequest {
+            model: "openai/o1-pro".to_string(),
+            ..request.clone()
+        };
+        let error = client.chat()?.chat_completion(expensive).await.unwrap_err();
+        assert!(matches!(error, Error::BudgetExceeded { limit, .. } if limit == Decimal::ONE));
+
+        // The reported cost is charged against the cumulative budget.
+        client.chat()?.chat_completion(request.clone()).await?;
+        assert_eq!(budget.spent(), Decimal::new(6, 1));
+
+        // The next estimate no longer fits into the remaining budget.
+        budget.record(Decimal::new(39, 2));
+        let error = client.chat()?.chat_completion(request).await.unwrap_err();
+        assert!(matches!(error, Error::BudgetExceeded { .. }));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_conversation_token_budget() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::conversation::{
+            Conversation, ConversationManager, ConversationStore, InMemoryConversationStore,
+            OverflowAction, TokenBudget,
+        };
+        use crate::error::Error;
+        use std::sync::Arc;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-budgeted",
+                "choices": [{
+                    "message": { "role": "assistant", "content": "ok" },
+                    "finish_reason": "stop",
+                    "native_finish_reason": "stop"
+                }],
+                "created": 1234567890,
+                "model": "openai/gpt-4o",
+                "usage": { "prompt_tokens": 90, "completion_tokens": 10, "total_tokens": 100 }
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("test-key")?;
+        let store = Arc::new(InMemoryConversationStore::new());
+        let mut conversation = Conversation::new("budgeted");
+        conversation.push(Message::user("x".repeat(400)));
+        conversation.push(Message::assistant("Answer"));
+        store.save(&conversation).await?;
+
+        // Truncation mode evicts the oversized history to fit the prompt limit.
+        let manager = ConversationManager::new(client.chat()?, store.clone(), "openai/gpt-4o")
+            .with_token_budget(
+                TokenBudget::new()
+                    .with_max_prompt_tokens(50)
+                    .with_max_total_tokens(150),
+            );
+        manager.send("budgeted", Message::user("Hi")).await?;
+        let saved = store.load("budgeted").await?.expect("conversation saved");
+        let contents: Vec<String> = saved.messages.iter().map(|m| m.content.to_text()).collect();
+        assert_eq!(contents, vec!["Answer", "Hi", "ok"]);
+        assert_eq!(saved.tokens_used, 100);
+
+        // Error mode rejects the turn once the conversation total is nearly spent.
+        let strict = ConversationManager::new(client.chat()?, store.clone(), "openai/gpt-4o")
+            .with_token_budget(
+                TokenBudget::new()
+                    .with_max_total_tokens(105)
+                    .with_action(OverflowAction::Error),
+            );
+        let error = strict
+            .send("budgeted", Message::user("Another question"))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::TokenBudgetExceeded { limit: 5, .. }));
+        let requests = server.received_requests().await.expect("requests recorded");
+        assert_eq!(requests.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_transform_hooks() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::api::stream::ChatStreamExt;
+        use futures::TryStreamExt;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let sse = [
+            r#"{"id":"gen-s","choices":[{"index":0,"delta":{"role":"assistant","content":""}}]}"#,
+            r#"{"id":"gen-s","choices":[{"index":0,"delta":{"content":"**Hello**"}}]}"#,
+            r#"{"id":"gen-s","choices":[{"index":0,"delta":{"content":" darn"}}]}"#,
+            r#"{"id":"gen-s","choices":[{"index":0,"delta":{"content":" world"}}]}"#,
+            r#"{"id":"gen-s","choices":[{"index":0,"delta":{},"finish_reason":"stop"}]}"#,
+        ]
+        .iter()
+        .map(|data| format!("data: {}\n\n", data))
+        .collect::<String>()
+            + "data: [DONE]\n\n";
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(sse, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("sk-or-test")?;
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+
+        let chunks: Vec<_> = client
+            .chat()?
+            .chat_completion_stream(request)
+            .filter_deltas(|delta| !delta.contains("darn"))
+            .map_deltas(|delta| delta.replace("**", ""))
+            .try_collect()
+            .await?;
+
+        let text: String = chunks
+            .iter()
+            .filter_map(|chunk| chunk.choices[0].delta.content.as_deref())
+            .collect();
+        assert_eq!(text, "Hello world");
+        // The censored chunk is dropped; the role and finish chunks are kept.
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[3].choices[0].finish_reason.as_deref(), Some("stop"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_accumulator() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::api::stream::StreamAccumulator;
+        use crate::types::chat::ChatCompletionChunk;
+
+        let chunks = [
+            json!({"id": "gen-acc", "choices": [{"index": 0, "delta": {"role": "assistant", "content": "Let me "}}]}),
+            json!({"id": "gen-acc", "choices": [{"index": 0, "delta": {"content": "check."}}]}),
+            json!({"id": "gen-acc", "choices": [{"index": 0, "delta": {"tool_calls": [
+                {"index": 0, "id": "call_1", "type": "function", "function": {"name": "get_weather", "arguments": "{\"ci"}}
+            ]}}]}),
+            json!({"id": "gen-acc", "choices": [{"index": 0, "delta": {"tool_calls": [
+                {"index": 0, "function": {"arguments": "ty\":\"Paris\"}"}}
+            ]}}]}),
+            json!({"id": "gen-acc", "choices": [{"index": 0, "delta": {}, "finish_reason": "tool_calls"}]}),
+        ];
+
+        let mut accumulator = StreamAccumulator::new();
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let chunk: ChatCompletionChunk = serde_json::from_value(chunk)?;
+            accumulator.push(&chunk);
+            if i == 2 {
+                // Partial state is visible mid-stream.
+                assert_eq!(accumulator.text(), "Let me check.");
+                assert_eq!(accumulator.tool_calls()[0].function_call.arguments, "{\"ci");
+                assert!(!accumulator.is_finished());
+            }
+        }
+
+        assert_eq!(accumulator.id(), Some("gen-acc"));
+        assert_eq!(accumulator.finish_reason(), Some("tool_calls"));
+        let message = accumulator.message();
+        let calls = message.tool_calls.expect("tool calls accumulated");
+        assert_eq!(calls[0].id, "call_1");
+        assert_eq!(calls[0].function_call.name, "get_weather");
+        assert_eq!(calls[0].function_call.arguments, r#"{"city":"Paris"}"#);
+
+        // An out-of-range index from the wire is ignored rather than allocated up to.
+        accumulator.push(&serde_json::from_value(json!({
+            "id": "gen-acc",
+            "choices": [{"index": 0, "delta": {"tool_calls": [
+                {"index": 1_000_000_000usize, "id": "call_x", "function": {"name": "boom"}}
+            ]}}]
+        }))?);
+        assert_eq!(accumulator.tool_calls().len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_incremental_schema_validation() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::api::stream::ChatStreamExt;
+        use crate::models::structured::IncrementalSchemaValidator;
+        use crate::types::chat::ChatCompletionChunk;
+        use futures::StreamExt;
+
+        let schema: JsonSchemaDefinition = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "city": { "type": "string" },
+                "temperature": { "type": "number" },
+                "tags": { "type": "array" }
+            },
+            "required": ["city", "temperature"],
+            "additionalProperties": false
+        }))?;
+
+        let mut validator = IncrementalSchemaValidator::new(schema.clone());
+        for fragment in [
+            r#"{"city": "Pa"#,
+            r#"ris, \"FR\"", "tags": ["a", "#,
+            r#"{"b": 1}], "temperature": 21.5}"#,
+        ] {
+            validator.push(fragment)?;
+        }
+        assert_eq!(validator.finish()?["temperature"], 21.5);
+
+        // A type mismatch is reported as soon as the value starts.
+        let mut validator = IncrementalSchemaValidator::new(schema.clone());
+        validator.push(r#"{"city": "Paris", "#)?;
+        assert!(validator.push(r#""temperature": "war"#).is_err());
+
+        // Undeclared properties and missing required ones are rejected.
+        let mut validator = IncrementalSchemaValidator::new(schema.clone());
+        assert!(validator.push(r#"{"country""#).is_err());
+        let mut validator = IncrementalSchemaValidator::new(schema.clone());
+        validator.push(r#"{"city": "Paris"}"#)?;
+        assert!(validator.finish().is_err());
+
+        // As a stream adapter, the stream stops at the first violation.
+        let chunk = |content: &str| -> ChatCompletionChunk {
+            serde_json::from_value(json!({
+                "id": "gen-schema",
+                "choices": [{ "index": 0, "delta": { "content": content } }]
+            }))
+            .unwrap()
+        };
+        let chunks = vec![
+            Ok(chunk(r#"{"city": "#)),
+            Ok(chunk("42")),
+            Ok(chunk(r#", "temperature": 1}"#)),
+        ];
+        let results: Vec<_> = futures::stream::iter(chunks)
+            .validate_schema(schema)
+            .collect()
+            .await;
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(matches!(
+            results[1],
+            Err(crate::error::Error::SchemaValidationError(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_outbound_redaction() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::redaction::Redactor;
+        use crate::types::chat::ContentPart;
+        use crate::types::completion::CompletionRequest;
+        use std::sync::Arc;
+
+        #[derive(Debug)]
+        struct EmailScrubber;
+
+        impl Redactor for EmailScrubber {
+            fn redact(&self, text: &str) -> String {
+                text.split(' ')
+                    .map(|word| if word.contains('@') { "[email]" } else { word })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }
+        }
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_redactor(Arc::new(EmailScrubber))
+            .with_api_key("sk-or-test")?;
+
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![
+                Message::user("Reply to jane@example.com please"),
+                Message::user(vec![
+                    ContentPart::text("cc bob@example.com"),
+                    ContentPart::image_url("https://example.com/a@b.png"),
+                ]),
+            ],
+            ..Default::default()
+        };
+        let prepared = client.dry_run(&request)?;
+        assert_eq!(
+            prepared.body["messages"][0]["content"],
+            "Reply to [email] please"
+        );
+        assert_eq!(
+            prepared.body["messages"][1]["content"][0]["text"],
+            "cc [email]"
+        );
+        // Only text is rewritten; URLs in image parts are left alone.
+        assert_eq!(
+            prepared.body["messages"][1]["content"][1]["image_url"]["url"],
+            "https://example.com/a@b.png"
+        );
+
+        // Replayed tool call arguments and predicted output are user data too.
+        let mut assistant = Message::assistant("");
+        assistant.tool_calls = Some(vec![ToolCall {
+            id: "call_1".to_string(),
+            kind: "function".to_string(),
+            function_call: FunctionCall {
+                name: "send_mail".to_string(),
+                arguments: "to: jane@example.com".to_string(),
+            },
+        }]);
+        let request = ChatCompletionRequest {
+            messages: vec![assistant],
+            prediction: Some(crate::types::chat::Prediction::content(
+                "Dear bob@example.com",
+            )),
+            ..request
+        };
+        let prepared = client.dry_run(&request)?;
+        assert_eq!(
+            prepared.body["messages"][0]["tool_calls"][0]["function"]["arguments"],
+            "to: [email]"
+        );
+        assert_eq!(prepared.body["prediction"]["content"], "Dear [email]");
+
+        let completion = CompletionRequest {
+            model: "openai/gpt-3.5-turbo-instruct".to_string(),
+            prompt: "Email ops@example.com about".to_string(),
+            sampling: Default::default(),
+            extra_params: json!({}),
+        };
+        let prepared =
+            client
+                .config
+                .prepare_request(reqwest::Method::POST, "completions", &completion)?;
+        assert_eq!(prepared.body["prompt"], "Email [email] about");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lenient_json_repair() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::models::structured::parse_json;
+        use crate::utils::json_repair::repair_json;
+
+        assert_eq!(
+            repair_json("```json\n{\"a\": [1, 2,], \"b\": \"x\",}\n```"),
+            r#"{"a": [1, 2], "b": "x"}"#
+        );
+        assert_eq!(
+            repair_json("Sure! Here it is: {\"a\": {\"b\": \"trunc"),
+            r#"{"a": {"b": "trunc"}}"#
+        );
+        assert_eq!(repair_json(r#"{"a": 1, "b":"#), r#"{"a": 1, "b":null}"#);
+        // Fences on a single line, with or without a language tag.
+        assert_eq!(repair_json(r#"```{"a":1}```"#), r#"{"a":1}"#);
+        assert_eq!(repair_json(r#"```json {"a":1,}```"#), r#"{"a":1}"#);
+
+        let strict = parse_json::<Value>(r#"{"ok": true}"#, true)?;
+        assert!(!strict.repaired);
+
+        let repaired = parse_json::<Value>("```\n{\"ok\": true,}\n```", true)?;
+        assert!(repaired.repaired);
+        assert_eq!(repaired.value, json!({ "ok": true }));
+
+        // Repair is opt-in.
+        assert!(parse_json::<Value>("{\"ok\": true,}", false).is_err());
+
+        let response: ChatCompletionResponse = serde_json::from_value(json!({
+            "id": "gen-json",
+            "choices": [{
+                "message": { "role": "assistant", "content": "{\"city\": \"Paris\"" },
+                "finish_reason": "length",
+                "native_finish_reason": "length"
+            }],
+            "created": 1234567890,
+            "model": "openai/gpt-4o"
+        }))?;
+        let parsed = response.parse_json::<Value>(true)?;
+        assert_eq!(parsed.value["city"], "Paris");
+        assert!(parsed.repaired);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_model_aliases() -> Result<(), Box<dyn std::error::Error>> {
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_model_alias("fast", "google/gemini-flash-1.5")
+            .with_model_aliases([("smart", "anthropic/claude-3.7-sonnet")])
+            .with_api_key("sk-or-test")?;
+        assert_eq!(
+            client.config.resolve_model("fast"),
+            "google/gemini-flash-1.5"
+        );
+        assert_eq!(
+            client.config.resolve_model("openai/gpt-4o"),
+            "openai/gpt-4o"
+        );
+
+        let request = ChatCompletionRequest {
+            model: "smart".to_string(),
+            messages: vec![Message::user("Hi")],
+            models: Some(vec!["fast".to_string(), "openai/gpt-4o".to_string()]),
+            ..Default::default()
+        };
+        let prepared = client.dry_run(&request)?;
+        assert_eq!(prepared.body["model"], "anthropic/claude-3.7-sonnet");
+        assert_eq!(
+            prepared.body["models"],
+            json!(["google/gemini-flash-1.5", "openai/gpt-4o"])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_default_model_and_params() -> Result<(), Box<dyn std::error::Error>> {
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_default_model("openai/gpt-4o-mini")
+            .with_default_params(
+                DefaultParams::new()
+                    .with_temperature(0.2)
+                    .with_max_tokens(256),
+            )
+            .with_api_key("sk-or-test")?;
+
+        let request = ChatCompletionRequest {
+            messages: vec![Message::user("Hi")],
+            max_tokens: Some(64),
+            ..Default::default()
+        };
+        let prepared = client.dry_run(&request)?;
+        assert_eq!(prepared.body["model"], "openai/gpt-4o-mini");
+        assert_eq!(prepared.body["temperature"], 0.2);
+        assert_eq!(prepared.body["max_tokens"], 64);
+        assert!(prepared.body.get("top_p").is_none());
+
+        let mut completion = CompletionRequest {
+            model: String::new(),
+            prompt: "Once upon a time".into(),
+            sampling: Default::default(),
+            extra_params: json!({ "temperature": 0.9 }),
+        };
+        client.config.apply_completion_defaults(&mut completion);
+        assert_eq!(completion.model, "openai/gpt-4o-mini");
+        assert_eq!(completion.extra_params["temperature"], 0.9);
+        assert_eq!(completion.extra_params["max_tokens"], 256);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_type_state_request_builder() -> Result<(), Box<dyn std::error::Error>> {
+        let payload = crate::api::request::RequestBuilder::new(json!({ "seed": 7 }))
+            .message(Message::system("Be brief."))
+            .model("openai/gpt-4o")
+            .messages(vec![Message::user("Hello")])
+            .with_temperature(0.5)?
+            .with_max_tokens(128)?
+            .build();
+        let value = serde_json::to_value(&payload)?;
+        assert_eq!(value["model"], "openai/gpt-4o");
+        assert_eq!(value["messages"].as_array().map(Vec::len), Some(2));
+        assert_eq!(value["temperature"], 0.5);
+        assert_eq!(value["max_tokens"], 128);
+        assert_eq!(value["seed"], 7);
+        assert!(value.get("top_p").is_none());
+
+        // Structured output goes out under the API's snake_case keys.
+        let schema = JsonSchemaConfig::new(
+            "answer",
+            serde_json::from_value(json!({ "type": "object", "properties": {} }))?,
+        );
+        let payload = crate::api::request::RequestBuilder::new(json!({}))
+            .model("openai/gpt-4o")
+            .message(Message::user("Hello"))
+            .with_structured_output(schema, false, false)
+            .build();
+        let value = serde_json::to_value(&payload)?;
+        assert_eq!(value["response_format"]["type"], "json_schema");
+        assert_eq!(value["response_format"]["json_schema"]["name"], "answer");
+        assert!(value.get("responseFormat").is_none());
+
+        let builder = crate::api::request::RequestBuilder::new(json!({})).model("openai/gpt-4o");
+        assert!(matches!(
+            builder.with_temperature(2.5),
+            Err(crate::error::Error::InvalidRequest(_))
+        ));
+        let builder = crate::api::request::RequestBuilder::new(json!({}));
+        assert!(builder.with_top_p(0.0).is_err());
+        let builder = crate::api::request::RequestBuilder::new(json!({}));
+        assert!(builder.with_top_k(0).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sampling_params() -> Result<(), Box<dyn std::error::Error>> {
+        let sampling = SamplingParams::new()
+            .with_temperature(0.7)
+            .with_top_k(40)
+            .with_repetition_penalty(1.1)
+            .with_seed(42);
+        sampling.validate()?;
+        assert!(SamplingParams::new()
+            .with_presence_penalty(3.0)
+            .validate()
+            .is_err());
+        assert!(SamplingParams::new().with_top_k(0).validate().is_err());
+        assert!(SamplingParams::new()
+            .with_temperature(f64::NAN)
+            .validate()
+            .is_err());
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_default_params(
+                DefaultParams::new()
+                    .with_sampling(SamplingParams::new().with_seed(1).with_top_p(0.9)),
+            )
+            .with_api_key("sk-or-test")?;
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            sampling,
+            ..Default::default()
+        };
+        let prepared = client.dry_run(&request)?;
+        assert_eq!(prepared.body["temperature"], 0.7);
+        assert_eq!(prepared.body["top_k"], 40);
+        assert_eq!(prepared.body["seed"], 42);
+        assert_eq!(prepared.body["top_p"], 0.9);
+        assert!(prepared.body.get("sampling").is_none());
+
+        let invalid = ChatCompletionRequest {
+            sampling: SamplingParams::new().with_temperature(5.0),
+            ..request
+        };
+        assert!(matches!(
+            client.dry_run(&invalid),
+            Err(crate::error::Error::InvalidRequest(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_auto_router_routing() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::types::routing::{Routing, AUTO_ROUTER_MODEL};
+
+        let request = ChatCompletionRequest::auto(vec![Message::user("Hi")]);
+        assert_eq!(request.model, AUTO_ROUTER_MODEL);
+        assert!(request.is_auto_routed());
+
+        let response = deserialize_chat_response(
+            r#"{
+                "id": "gen-auto",
+                "provider": "Anthropic",
+                "model": "anthropic/claude-3.7-sonnet",
+                "created": 1700000000,
+                "choices": [{
+                    "message": { "role": "assistant", "content": "Hello" },
+                    "finish_reason": "stop",
+                    "native_finish_reason": "end_turn"
+                }],
+                "usage": null
+            }"#,
+        );
+        let routing = response.routing(&request.model);
+        assert_eq!(
+            routing,
+            Routing::Auto {
+                model: "anthropic/claude-3.7-sonnet".into(),
+                provider: Some("Anthropic".into()),
+            }
+        );
+        assert_eq!(
+            routing.to_string(),
+            "openrouter/auto -> anthropic/claude-3.7-sonnet via Anthropic"
+        );
+        assert_eq!(serde_json::to_value(&routing)?["routing"], "auto");
+
+        let explicit = response.routing("anthropic/claude-3.7-sonnet");
+        assert!(!explicit.is_auto());
+        assert_eq!(explicit.provider(), Some("Anthropic"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ping_reports_health_status() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::types::health::HealthStatus;
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/key"))
+            .and(header("authorization", "Bearer good-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "label": "sk-or-v1-abc", "usage": 0.5, "limit": null }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/key"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+                "error": { "code": 401, "message": "No auth credentials found" }
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("good-key")?;
+        let ping = client.ping().await?;
+        assert!(ping.is_healthy());
+        assert!(ping.error.is_none());
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("bad-key")?;
+        let ping = client.ping().await?;
+        assert_eq!(ping.status, HealthStatus::Unauthorized);
+        assert!(ping.is_reachable());
+        assert_eq!(server.received_requests().await.map(|r| r.len()), Some(2));
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("http://127.0.0.1:1/")?
+            .with_api_key("good-key")?;
+        let ping = client.ping().await?;
+        assert_eq!(ping.status, HealthStatus::Unreachable);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_models_search() -> Result<(), Box<dyn std::error::Error>> {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/models"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [
+                    { "id": "openai/gpt-4o", "name": "OpenAI: GPT-4o", "context_length": 128000 },
+                    { "id": "anthropic/claude-3.5-sonnet", "name": "Anthropic: Claude 3.5 Sonnet" },
+                    { "id": "anthropic/claude-3.7-sonnet", "name": "Anthropic: Claude 3.7 Sonnet",
+                      "pricing": { "prompt": "0.000003", "completion": "0.000015" } },
+                    { "id": "sonnet/sonnet", "name": "Sonnet" },
+                    { "id": "google/gemini-flash-1.5", "name": "Google: Gemini Flash 1.5" },
+                    { "id": "zhipu/ab千", "name": "Zhipu: AB千" }
+                ]
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("test-key")?;
+        let models = client.models()?;
+
+        let ids = |matches: Vec<crate::types::models::Model>| {
+            matches.into_iter().map(|m| m.id).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ids(models.search("SONNET").await?),
+            [
+                "sonnet/sonnet",
+                "anthropic/claude-3.5-sonnet",
+                "anthropic/claude-3.7-sonnet"
+            ]
+        );
+        assert_eq!(
+            ids(models.search("c37s").await?),
+            ["anthropic/claude-3.7-sonnet"]
+        );
+        assert!(models.search("llama").await?.is_empty());
+        // Fuzzy spans are measured in bytes, so multi-byte characters must not underflow.
+        assert_eq!(ids(models.search("a千").await?), ["zhipu/ab千"]);
+
+        let catalog = models.list().await?;
+        assert_eq!(
+            catalog.get("openai/gpt-4o").and_then(|m| m.context_length),
+            Some(128000)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_model_cache_ttl() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::catalog::ModelCache;
+        use std::sync::Arc;
+        use std::time::Duration;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/models"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{ "id": "openai/gpt-4o", "name": "OpenAI: GPT-4o" }]
+            })))
+            .mount(&server)
+            .await;
+        let requests = || async { server.received_requests().await.map_or(0, |r| r.len()) };
+
+        let cache = Arc::new(ModelCache::new(Duration::from_secs(60)));
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_model_cache(cache.clone())
+            .with_api_key("test-key")?;
+        let models = client.models()?;
+        models.list().await?;
+        models.list().await?;
+        assert_eq!(requests().await, 1);
+        assert!(cache.is_fresh());
+        assert_eq!(
+            cache.get("openai/gpt-4o").map(|m| m.name),
+            Some("OpenAI: GPT-4o".to_string())
+        );
+
+        cache.invalidate();
+        models.list().await?;
+        assert_eq!(requests().await, 2);
+
+        let cache = Arc::new(ModelCache::new(Duration::ZERO).with_background_refresh(true));
+        let models = client.models()?;
+        let models = crate::api::models::ModelsApi {
+            config: crate::client::ClientConfig {
+                model_cache: Some(cache.clone()),
+                ..models.config.clone()
+            },
+            ..models
+        };
+        models.list().await?;
+        assert_eq!(requests().await, 3);
+        let stale = models.list().await?;
+        assert_eq!(stale.data.len(), 1);
+        for _ in 0..50 {
+            if requests().await == 4 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(requests().await, 4);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "offline-catalog")]
+    #[tokio::test]
+    async fn test_offline_catalog_fallback() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::budget::CostBudget;
+        use crate::catalog::snapshot;
+
+        let gpt4o = snapshot().get("openai/gpt-4o").expect("gpt-4o in snapshot");
+        assert_eq!(gpt4o.context_length, Some(128000));
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("http://127.0.0.1:1/")?
+            .with_api_key("test-key")?;
+        let catalog = client.models()?.list().await?;
+        assert_eq!(catalog.data.len(), snapshot().data.len());
+
+        let budget = CostBudget::new().with_catalog_pricing(&catalog);
+        let estimate = budget.estimate("openai/gpt-4o", &[Message::user("Hello")], None);
+        assert!(estimate.is_some_and(|cost| cost > rust_decimal::Decimal::ZERO));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_decimal_prices_and_costs() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::budget::{CostBudget, ModelPricing};
+        use crate::types::models::ModelsResponse;
+        use rust_decimal::Decimal;
+        use std::str::FromStr;
+
+        let catalog: ModelsResponse = serde_json::from_value(json!({
+            "data": [
+                { "id": "openai/gpt-4o-mini", "name": "OpenAI: GPT-4o-mini",
+                  "pricing": { "prompt": "0.00000015", "completion": "0.0000006" } },
+                { "id": "openrouter/auto", "name": "Auto Router",
+                  "pricing": { "prompt": "-1", "completion": "-1" } }
+            ]
+        }))?;
+        let pricing = catalog.data[0].pricing.as_ref().expect("priced");
+        assert_eq!(pricing.prompt, Decimal::from_str("0.00000015")?);
+        assert_eq!(serde_json::to_value(pricing)?["completion"], "0.0000006");
+
+        let pricing = ModelPricing::new(pricing.prompt, pricing.completion);
+        assert_eq!(
+            pricing.cost(1_000_000, 1_000_000),
+            Decimal::from_str("0.75")?
+        );
+
+        let budget = CostBudget::new().with_catalog_pricing(&catalog);
+        assert!(budget
+            .estimate("openrouter/auto", &[Message::user("Hi")], None)
+            .is_none());
+        for _ in 0..3 {
+            budget.record(Decimal::from_str("0.1")?);
+        }
+        assert_eq!(budget.spent(), Decimal::from_str("0.3")?);
+
+        let usage: crate::types::chat::Usage = serde_json::from_value(json!({
+            "prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2, "cost": 0.000002
+        }))?;
+        assert_eq!(usage.cost, Some(Decimal::from_str("0.000002")?));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_join_all_preserves_order() -> Result<(), Box<dyn std::error::Error>> {
+        use std::time::Duration;
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        for (model, delay) in [("slow/model", 150), ("fast/model", 0)] {
+            Mock::given(method("POST"))
+                .and(path("/chat/completions"))
+                .and(body_partial_json(json!({ "model": model })))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_json(json!({
+                            "id": "gen-1",
+                            "choices": [{
+                                "message": { "role": "assistant", "content": "Hi" },
+                                "finish_reason": "stop",
+                                "native_finish_reason": "stop"
+                            }],
+                            "created": 1700000000,
+                            "model": model
+                        }))
+                        .set_delay(Duration::from_millis(delay)),
+                )
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({ "model": "missing/model" })))
+            .respond_with(ResponseTemplate::new(404).set_body_string("No such model"))
+            .mount(&server)
+            .await;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_max_concurrency(2)
+            .with_api_key("test-key")?;
+        let request = |model: &str| ChatCompletionRequest {
+            model: model.to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+        let results = client
+            .join_all(vec![
+                request("slow/model"),
+                request("missing/model"),
+                request("fast/model"),
+            ])
+            .await;
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[0].as_ref().map(|r| r.model.as_str()).ok(),
+            Some("slow/model")
+        );
+        assert_eq!(
+            results[1].as_ref().err().and_then(|e| e.status_code()),
+            Some(404)
+        );
+        assert_eq!(
+            results[2].as_ref().map(|r| r.model.as_str()).ok(),
+            Some("fast/model")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_admits_by_priority() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::scheduler::{Priority, Scheduler};
+        use std::sync::Arc;
+        use std::time::Duration;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({
+                        "id": "gen-1",
+                        "choices": [{
+                            "message": { "role": "assistant", "content": "Hi" },
+                            "finish_reason": "stop",
+                            "native_finish_reason": "stop"
+                        }],
+                        "created": 1700000000,
+                        "model": "openai/gpt-4o"
+                    }))
+                    .set_delay(Duration::from_millis(100)),
+            )
+            .mount(&server)
+            .await;
+
+        let scheduler = Arc::new(Scheduler::new(1));
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_scheduler(scheduler.clone())
+            .with_api_key("test-key")?;
+        let send = |model: &str, priority: Priority| {
+            let chat = client.chat().expect("chat handle").with_priority(priority);
+            let request = ChatCompletionRequest {
+                model: model.to_string(),
+                messages: vec![Message::user("Hi")],
+                ..Default::default()
+            };
+            tokio::spawn(async move { chat.chat_completion(request).await })
+        };
+
+        let first = send("first", Priority::Normal);
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let batch = send("batch", Priority::Background);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let interactive = send("interactive", Priority::Interactive);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(scheduler.in_flight(), 1);
+        assert_eq!(scheduler.queued(), 2);
+
+        for handle in [first, batch, interactive] {
+            handle.await??;
+        }
+        assert_eq!(scheduler.in_flight(), 0);
+
+        let order: Vec<String> = server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|r| serde_json::from_slice::<Value>(&r.body).ok())
+            .filter_map(|body| body["model"].as_str().map(String::from))
+            .collect();
+        assert_eq!(order, ["first", "interactive", "batch"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_then_aborts() -> Result<(), Box<dyn std::error::Error>> {
+        use std::sync::Arc;
+        use std::time::Duration;
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        for (model, delay) in [("quick/model", 100), ("stuck/model", 5000)] {
+            Mock::given(method("POST"))
+                .and(path("/chat/completions"))
+                .and(body_partial_json(json!({ "model": model })))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_json(json!({
+                            "id": "gen-1",
+                            "choices": [{
+                                "message": { "role": "assistant", "content": "Hi" },
+                                "finish_reason": "stop",
+                                "native_finish_reason": "stop"
+                            }],
+                            "created": 1700000000,
+                            "model": model
+                        }))
+                        .set_delay(Duration::from_millis(delay)),
+                )
+                .mount(&server)
+                .await;
+        }
+        let new_client = || {
+            OpenRouterClient::<Unconfigured>::new()
+                .with_base_url(format!("{}/", server.uri()))?
+                .with_api_key("test-key")
+                .map(Arc::new)
+        };
+        let request = |model: &str| ChatCompletionRequest {
+            model: model.to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+
+        // A request that finishes within the timeout is drained.
+        let client = new_client()?;
+        let pending = tokio::spawn({
+            let client = client.clone();
+            let request = request("quick/model");
+            async move { client.chat_completion(request).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(client.in_flight(), 1);
+        let report = client.shutdown(Duration::from_secs(5)).await;
+        assert!(report.is_clean());
+        assert_eq!(report.drained, 1);
+        assert!(pending.await?.is_ok());
+        assert!(client.is_shut_down());
+        let rejected = client.chat_completion(request("quick/model")).await;
+        assert!(matches!(rejected, Err(crate::error::Error::ShutDown)));
+
+        // A request still running when the timeout expires is aborted and reported.
+        let client = new_client()?;
+        let pending = tokio::spawn({
+            let client = client.clone();
+            let request = request("stuck/model");
+            async move { client.chat_completion(request).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let report = client.shutdown(Duration::from_millis(50)).await;
+        assert_eq!(report.drained, 0);
+        assert_eq!(report.aborted.len(), 1);
+        assert!(report.aborted[0].url.ends_with("/chat/completions"));
+        assert!(matches!(pending.await?, Err(crate::error::Error::ShutDown)));
+        assert_eq!(client.in_flight(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_context_length_preflight() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::catalog::ModelCache;
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        let cache = Arc::new(ModelCache::new(Duration::from_secs(60)));
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_model_cache(cache.clone())
+            .with_model_alias("tiny", "tiny/model")
+            .with_api_key("test-key")?;
+        let request = |max_tokens| ChatCompletionRequest {
+            model: "tiny".to_string(),
+            messages: vec![Message::user("x".repeat(80))],
+            max_tokens,
+            ..Default::default()
+        };
+
+        // Without a cached catalog nothing is checked.
+        client.dry_run(&request(Some(1000)))?;
+
+        cache.store(serde_json::from_value(json!({
+            "data": [{ "id": "tiny/model", "name": "Tiny", "context_length": 64 }]
+        }))?);
+        // 80 characters are about 20 tokens, plus 4 tokens of message overhead.
+        client.dry_run(&request(Some(40)))?;
+        match client.dry_run(&request(Some(41))) {
+            Err(crate::error::Error::ContextLengthExceeded {
+                model,
+                tokens,
+                limit,
+            }) => {
+                assert_eq!(model, "tiny/model");
+                assert_eq!((tokens, limit), (65, 64));
+            }
+            other => panic!("expected ContextLengthExceeded, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_max_tokens_fills_remaining_context() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::catalog::ModelCache;
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        let cache = Arc::new(ModelCache::new(Duration::from_secs(60)));
+        cache.store(serde_json::from_value(json!({
+            "data": [{ "id": "tiny/model", "name": "Tiny", "context_length": 1000 }]
+        }))?);
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_model_cache(cache)
+            .with_auto_max_tokens(100)
+            .with_api_key("test-key")?;
+        let mut request = ChatCompletionRequest {
+            model: "tiny/model".to_string(),
+            // About 20 tokens, plus 4 tokens of message overhead.
+            messages: vec![Message::user("x".repeat(80))],
+            ..Default::default()
+        };
+        let prepared = client.dry_run(&request)?;
+        assert_eq!(prepared.body["max_tokens"], 876);
+
+        // An explicit max_tokens is kept, and unknown models are left alone.
+        request.max_tokens = Some(50);
+        assert_eq!(client.dry_run(&request)?.body["max_tokens"], 50);
+        request.max_tokens = None;
+        request.model = "other/model".to_string();
+        assert!(client.dry_run(&request)?.body.get("max_tokens").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_user_agent_header() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::client::DEFAULT_USER_AGENT;
+
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+        let user_agent = |client: &OpenRouterClient<crate::client::Ready>| {
+            client
+                .dry_run(&request)
+                .map(|prepared| prepared.headers["user-agent"].clone())
+        };
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_api_key("test-key")?;
+        assert_eq!(user_agent(&client)?, DEFAULT_USER_AGENT);
+        assert!(DEFAULT_USER_AGENT.starts_with("openrouter_api/"));
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_user_agent("myapp/1.2")
+            .with_api_key("test-key")?;
+        assert_eq!(
+            user_agent(&client)?,
+            format!("{} myapp/1.2", DEFAULT_USER_AGENT).as_str()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_tool_call_parse_arguments() -> Result<(), Box<dyn std::error::Error>> {
+        use serde::Deserialize;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Item {
+            sku: String,
+            quantity: u32,
+        }
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Order {
+            items: Vec<Item>,
+        }
+        let call = |arguments: &str| ToolCall {
+            id: "call_1".to_string(),
+            kind: "function".to_string(),
+            function_call: FunctionCall {
+                name: "place_order".to_string(),
+                arguments: arguments.to_string(),
+            },
+        };
+
+        let order: Order =
+            call(r#"{"items": [{"sku": "A1", "quantity": 2}]}"#).parse_arguments()?;
+        assert_eq!(order.items[0].quantity, 2);
+
+        let raw =
+            r#"{"items": [{"sku": "A1", "quantity": 2}, {"sku": "B2", "quantity": "three"}]}"#;
+        match call(raw).parse_arguments::<Order>() {
+            Err(crate::error::Error::InvalidToolArguments {
+                function,
+                path,
+                arguments,
+                ..
+            }) => {
+                assert_eq!(function, "place_order");
+                assert_eq!(path, "items[1].quantity");
+                assert_eq!(arguments, raw);
+            }
+            other => panic!("expected InvalidToolArguments, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_image_detail_serialization() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::types::chat::{Content, ContentPart, ImageDetail};
+        use crate::utils::tokens::estimate_message_tokens;
+
+        let part =
+            ContentPart::image_url_with_detail("https://example.com/cat.png", ImageDetail::Low);
+        assert_eq!(
+            serde_json::to_value(&part)?,
+            json!({
+                "type": "image_url",
+                "image_url": { "url": "https://example.com/cat.png", "detail": "low" }
+            })
+        );
+        let plain = serde_json::to_value(ContentPart::image_url("https://example.com/cat.png"))?;
+        assert!(plain["image_url"].get("detail").is_none());
+        let parsed: ContentPart = serde_json::from_value(json!({
+            "type": "image_url",
+            "image_url": { "url": "https://example.com/cat.png", "detail": "high" }
+        }))?;
+        assert_eq!(
+            parsed,
+            ContentPart::image_url_with_detail("https://example.com/cat.png", ImageDetail::High)
+        );
+
+        let image = |part: ContentPart| Message {
+            content: Content::Parts(vec![part]),
+            ..Message::user("")
+        };
+        assert!(estimate_message_tokens(&image(parsed)) > estimate_message_tokens(&image(part)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_moderation_hook_blocks_requests() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::moderation::{ModerationHook, Verdict};
+        use futures::future::BoxFuture;
+        use std::sync::Arc;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        #[derive(Debug)]
+        struct KeywordFilter;
+
+        impl ModerationHook for KeywordFilter {
+            fn review<'a>(
+                &'a self,
+                _model: &'a str,
+                messages: &'a [Message],
+            ) -> BoxFuture<'a, crate::error::Result<Verdict>> {
+                let flagged = messages
+                    .iter()
+                    .any(|m| m.content.to_string().contains("forbidden"));
+                Box::pin(async move {
+                    Ok(if flagged {
+                        Verdict::block("contains a forbidden word")
+                    } else {
+                        Verdict::Allow
+                    })
+                })
+            }
+        }
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-1",
+                "choices": [{
+                    "message": { "role": "assistant", "content": "Hi" },
+                    "finish_reason": "stop",
+                    "native_finish_reason": "stop"
+                }],
+                "created": 1700000000,
+                "model": "openai/gpt-4o"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_moderation(Arc::new(KeywordFilter))
+            .with_api_key("test-key")?;
+        let request = |text: &str| ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user(text)],
+            ..Default::default()
+        };
+
+        client.chat_completion(request("Hello")).await?;
+        match client
+            .chat()?
+            .chat_completion(request("Something forbidden"))
+            .await
+        {
+            Err(crate::error::Error::BlockedByPolicy { reason }) => {
+                assert_eq!(reason, "contains a forbidden word");
+            }
+            other => panic!("expected BlockedByPolicy, got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_load_balancer_spreads_and_fails_over() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::balancer::{Backend, LoadBalancer, Strategy};
+        use std::sync::Arc;
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let reply = || {
+            ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-1",
+                "choices": [{
+                    "message": { "role": "assistant", "content": "Hi" },
+                    "finish_reason": "stop",
+                    "native_finish_reason": "stop"
+                }],
+                "created": 1700000000,
+                "model": "openai/gpt-4o"
+            }))
+        };
+        let primary = MockServer::start().await;
+        let gateway = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(header("authorization", "Bearer test-key"))
+            .respond_with(reply())
+            .mount(&primary)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(header("authorization", "Bearer gateway-key"))
+            .respond_with(reply())
+            .mount(&gateway)
+            .await;
+        let backends = || -> crate::error::Result<Vec<Backend>> {
+            Ok(vec![
+                Backend::new(&format!("{}/v1", primary.uri()))?,
+                Backend::new(&gateway.uri())?.with_api_key("gateway-key"),
+            ])
+        };
+        let request = || ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+        async fn count(server: &MockServer) -> usize {
+            server.received_requests().await.map_or(0, |r| r.len())
+        }
+
+        // Equal weights alternate between the backends.
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_load_balancer(Arc::new(LoadBalancer::new(backends()?)))
+            .with_api_key("test-key")?;
+        for _ in 0..4 {
+            client.chat_completion(request()).await?;
+        }
+        assert_eq!((count(&primary).await, count(&gateway).await), (2, 2));
+
+        // A failing primary is skipped within the same call and taken out of rotation.
+        primary.reset().await;
+        gateway.reset().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&primary)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(reply())
+            .mount(&gateway)
+            .await;
+        let balancer = Arc::new(LoadBalancer::new(backends()?).with_strategy(Strategy::Failover));
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_load_balancer(balancer.clone())
+            .with_api_key("test-key")?;
+        client.chat_completion(request()).await?;
+        client.chat_completion(request()).await?;
+        assert_eq!((count(&primary).await, count(&gateway).await), (1, 2));
+        assert_eq!(
+            balancer.healthy_backends(),
+            vec![balancer.backends()[1].base_url().clone()]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generation_wait_polls_until_available() -> Result<(), Box<dyn std::error::Error>>
+    {
+        use crate::Decimal;
+        use std::time::Duration;
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/generation"))
+            .and(query_param("id", "gen-1"))
+            .respond_with(ResponseTemplate::new(404).set_body_string("Generation not found"))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/generation"))
+            .and(query_param("id", "gen-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": {
+                    "id": "gen-1",
+                    "model": "openai/gpt-4o",
+                    "provider_name": "OpenAI",
+                    "total_cost": 0.00042,
+                    "native_tokens_prompt": 12,
+                    "native_tokens_completion": 30
+                }
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("test-key")?;
+        let generation = client.generation()?;
+
+        let error = generation.get("gen-1").await.unwrap_err();
+        assert_eq!(error.status_code(), Some(404));
+        let stats = generation.wait("gen-1", Duration::from_secs(5)).await?;
+        assert_eq!(stats.total_cost, Decimal::new(42, 5));
+        assert_eq!(stats.provider_name.as_deref(), Some("OpenAI"));
+        assert_eq!(server.received_requests().await.map_or(0, |r| r.len()), 3);
+
+        let missing = generation.wait("gen-2", Duration::from_millis(300)).await;
+        assert_eq!(missing.unwrap_err().status_code(), Some(404));
+        Ok(())
+    }
+
+    #[test]
+    fn test_per_handle_attribution_headers() -> Result<(), Box<dyn std::error::Error>> {
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_http_referer("https://suite.example.com")
+            .with_site_title("Suite")
+            .with_api_key("test-key")?;
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+
+        let prepared = client
+            .chat()?
+            .with_http_referer("https://notes.example.com")
+            .with_site_title("Notes")
+            .dry_run(&request)?;
+        assert_eq!(prepared.headers["referer"], "https://notes.example.com");
+        assert_eq!(prepared.headers["x-title"], "Notes");
+
+        // The client's own headers are unchanged.
+        let prepared = client.dry_run(&request)?;
+        assert_eq!(prepared.headers["referer"], "https://suite.example.com");
+        assert_eq!(prepared.headers["x-title"], "Suite");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_chat_completion_typed_uses_strict_schema(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        use serde::Deserialize;
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct City {
+            name: String,
+            population: u64,
+        }
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({
+                "response_format": {
+                    "type": "json_schema",
+                    "json_schema": { "name": "city", "strict": true }
+                }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-1",
+                "choices": [{
+                    "message": {
+                        "role": "assistant",
+                        "content": "{\"name\": \"Paris\", \"population\": 2100000}"
+                    },
+                    "finish_reason": "stop",
+                    "native_finish_reason": "stop"
+                }],
+                "created": 1700000000,
+                "model": "openai/gpt-4o"
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("test-key")?;
+        let schema: JsonSchemaDefinition = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "population": { "type": "integer" }
+            },
+            "required": ["name", "population"]
+        }))?;
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Describe Paris.")],
+            ..Default::default()
+        };
+        let city: City = client
+            .chat_completion_typed(request, "city", schema)
+            .await?;
+        assert_eq!(
+            city,
+            City {
+                name: "Paris".to_string(),
+                population: 2_100_000
+            }
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_buffer_policies() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::api::stream::StreamBuffer;
+        use futures::{StreamExt, TryStreamExt};
+        use std::time::Duration;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let sse = ["Hel", "lo", ", ", "wor", "ld"]
+            .iter()
+            .map(|fragment| {
+                json!({ "id": "gen-s", "choices": [{ "index": 0, "delta": { "content": fragment } }] })
+            })
+            .chain(std::iter::once(json!({
+                "id": "gen-s",
+                "choices": [{ "index": 0, "delta": {}, "finish_reason": "stop" }]
+            })))
+            .map(|data| format!("data: {}\n\n", data))
+            .collect::<String>()
+            + "data: [DONE]\n\n";
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(sse, "text/event-stream"))
+            .mount(&server)
+            .await;
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("sk-or-test")?;
+        let request = || ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+        let text = |chunks: &[crate::types::chat::ChatCompletionChunk]| -> String {
+            chunks
+                .iter()
+                .filter_map(|chunk| chunk.choices[0].delta.content.as_deref())
+                .collect()
+        };
+
+        let buffered: Vec<_> = client
+            .chat()?
+            .with_stream_buffer(StreamBuffer::Buffer(2))
+            .chat_completion_stream(request())
+            .try_collect()
+            .await?;
+        assert_eq!(buffered.len(), 6);
+        assert_eq!(text(&buffered), "Hello, world");
+
+        // A slow consumer receives the deltas merged into fewer chunks.
+        let mut stream = client
+            .chat()?
+            .with_stream_buffer(StreamBuffer::Coalesce(1))
+            .chat_completion_stream(request());
+        let mut coalesced = vec![stream.next().await.expect("first chunk")?];
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        coalesced.extend(stream.try_collect::<Vec<_>>().await?);
+        assert!(coalesced.len() < 6);
+        assert_eq!(text(&coalesced), "Hello, world");
+        let last = coalesced.last().expect("chunks received");
+        assert_eq!(last.choices[0].finish_reason.as_deref(), Some("stop"));
+        Ok(())
+    }
+
+    #[cfg(feature = "tower")]
+    #[tokio::test]
+    async fn test_tower_service_chat_completion() -> Result<(), Box<dyn std::error::Error>> {
+        use futures::future::poll_fn;
+        use tower_service::Service;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-1",
+                "choices": [{
+                    "message": { "role": "assistant", "content": "Hi" },
+                    "finish_reason": "stop",
+                    "native_finish_reason": "stop"
+                }],
+                "created": 1700000000,
+                "model": "openai/gpt-4o"
+            })))
+            .mount(&server)
+            .await;
+
+        let mut client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("test-key")?;
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+
+        poll_fn(|cx| client.poll_ready(cx)).await?;
+        let response = client.call(request.clone()).await?;
+        assert_eq!(response.id, "gen-1");
+
+        let mut api = client.chat()?;
+        poll_fn(|cx| api.poll_ready(cx)).await?;
+        let response = api.call(request).await?;
+        assert_eq!(response.choices[0].message.content.to_string(), "Hi");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_retries_before_first_chunk() -> Result<(), Box<dyn std::error::Error>> {
+        use futures::{StreamExt, TryStreamExt};
+        use std::time::Duration;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let error = "data: {\"error\":{\"code\":502,\"message\":\"Provider returned error\"}}\n\n";
+        let chunk = |content: &str| {
+            format!(
+                "data: {}\n\n",
+                json!({ "id": "gen-r", "choices": [{ "index": 0, "delta": { "content": content } }] })
+            )
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(error, "text/event-stream"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                chunk("Hello") + &chunk(" there") + "data: [DONE]\n\n",
+                "text/event-stream",
+            ))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(chunk("Partial") + error, "text/event-stream"),
+            )
+            .mount(&server)
+            .await;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_retries(1, Duration::from_millis(1))
+            .with_api_key("sk-or-test")?;
+        let request = || ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+
+        // The in-stream error arrives before any chunk, so the request is sent again.
+        let chunks: Vec<_> = client
+            .chat()?
+            .chat_completion_stream(request())
+            .try_collect()
+            .await?;
+        let text: String = chunks
+            .iter()
+            .filter_map(|chunk| chunk.choices[0].delta.content.as_deref())
+            .collect();
+        assert_eq!(text, "Hello there");
+        assert_eq!(
+            server.received_requests().await.unwrap_or_default().len(),
+            2
+        );
+
+        // Once a chunk has been yielded, a later error ends the stream instead.
+        let mut stream = client.chat()?.chat_completion_stream(request());
+        let first = stream.next().await.expect("first chunk")?;
+        assert_eq!(first.choices[0].delta.content.as_deref(), Some("Partial"));
+        match stream.next().await {
+            Some(Err(crate::error::Error::ApiError { code, .. })) => assert_eq!(code, 502),
+            other => panic!(
+                "expected the in-stream error, got {:?}",
+                other.map(|r| r.is_ok())
+            ),
+        }
+        assert_eq!(
+            server.received_requests().await.unwrap_or_default().len(),
+            3
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_web_citations() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::api::stream::StreamAccumulator;
+        use crate::types::chat::{Annotation, ChatCompletionChunk, ChatCompletionResponse};
+
+        let response: ChatCompletionResponse = serde_json::from_value(json!({
+            "id": "gen-web",
+            "created": 1234567890,
+            "model": "openai/gpt-4o:online",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": "Rust 1.0 shipped in 2015 [rust-lang.org].",
+                    "annotations": [
+                        {
+                            "type": "url_citation",
+                            "url_citation": {
+                                "url": "https://blog.rust-lang.org/2015/05/15/Rust-1.0.html",
+                                "title": "Announcing Rust 1.0",
+                                "content": "Today we are very proud to announce the 1.0 release",
+                                "start_index": 24,
+                                "end_index": 39
+                            }
+                        },
+                        { "type": "file_citation", "file_id": "file-1" }
+                    ]
+                },
+                "finish_reason": "stop"
+            }]
+        }))?;
+        let message = &response.choices[0].message;
+        assert_eq!(message.annotations.as_ref().map(Vec::len), Some(2));
+        assert_eq!(
+            message.annotations.as_ref().unwrap()[1],
+            Annotation::Unknown
+        );
+        let citations: Vec<_> = message.citations().collect();
+        assert_eq!(citations.len(), 1);
+        assert_eq!(citations[0].title.as_deref(), Some("Announcing Rust 1.0"));
+        assert_eq!(citations[0].start_index, Some(24));
+        assert!(serde_json::to_value(Message::user("Hi"))?
+            .get("annotations")
+            .is_none());
+
+        // Streamed annotations are collected onto the assembled message.
+        let chunk: ChatCompletionChunk = serde_json::from_value(json!({
+            "id": "gen-web",
+            "choices": [{
+                "index": 0,
+                "delta": {
+                    "annotations": [{
+                        "type": "url_citation",
+                        "url_citation": { "url": "https://www.rust-lang.org/" }
+                    }]
+                },
+                "finish_reason": "stop"
+            }]
+        }))?;
+        let mut accumulator = StreamAccumulator::new();
+        accumulator.push(&serde_json::from_value(json!({
+            "id": "gen-web",
+            "choices": [{ "index": 0, "delta": { "content": "See the site." } }]
+        }))?);
+        accumulator.push(&chunk);
+        assert_eq!(accumulator.annotations().len(), 1);
+        let message = accumulator.message();
+        let urls: Vec<_> = message.citations().map(|c| c.url.as_str()).collect();
+        assert_eq!(urls, ["https://www.rust-lang.org/"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_and_chat_api_share_one_path() -> Result<(), Box<dyn std::error::Error>> {
+        use futures::TryStreamExt;
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({ "stream": true })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                "data: {\"id\":\"gen-u\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"}}]}\n\ndata: [DONE]\n\n",
+                "text/event-stream",
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-u",
+                "created": 1234567890,
+                "model": "openai/gpt-4o",
+                "choices": [{
+                    "index": 0,
+                    "message": {
+                        "role": "assistant",
+                        "content": "",
+                        "tool_calls": [{
+                            "id": "call-1",
+                            "type": "retrieval",
+                            "function": { "name": "lookup", "arguments": "{}" }
+                        }]
+                    },
+                    "finish_reason": "tool_calls"
+                }]
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("sk-or-test")?;
+        let request = || ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+
+        // Both entry points reject the unsupported tool call kind the same way.
+        assert!(matches!(
+            client.chat_completion(request()).await,
+            Err(crate::error::Error::SchemaValidationError(_))
+        ));
+        assert!(matches!(
+            client.chat()?.chat_completion(request()).await,
+            Err(crate::error::Error::SchemaValidationError(_))
+        ));
+
+        let chunks: Vec<_> = client
+            .chat_completion_stream(request())
+            .try_collect()
+            .await?;
+        assert_eq!(chunks[0].choices[0].delta.content.as_deref(), Some("Hi"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_idle_timeout() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::client::PreparedRequest;
+        use crate::transport::{HttpResponse, Transport};
+        use bytes::Bytes;
+        use futures::future::BoxFuture;
+        use futures::StreamExt;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        /// Sends the given SSE lines, then keeps the connection open without sending more.
+        #[derive(Debug)]
+        struct HangingTransport {
+            lines: Vec<&'static str>,
+            calls: AtomicUsize,
+        }
+
+        impl Transport for HangingTransport {
+            fn execute(
+                &self,
+                _request: PreparedRequest,
+            ) -> BoxFuture<'_, crate::error::Result<HttpResponse>> {
+                self.calls.fetch_add(1, Ordering::SeqCst);
+                let lines: Vec<crate::error::Result<Bytes>> = self
+                    .lines
+                    .iter()
+                    .map(|line| Ok(Bytes::from_static(line.as_bytes())))
+                    .collect();
+                Box::pin(async move {
+                    Ok(HttpResponse {
+                        status: reqwest::StatusCode::OK,
+                        headers: Default::default(),
+                        body: futures::stream::iter(lines)
+                            .chain(futures::stream::pending())
+                            .boxed(),
+                    })
+                })
+            }
+        }
+
+        let request = || ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+        let client_with = |transport: Arc<HangingTransport>| {
+            OpenRouterClient::<Unconfigured>::new()
+                .with_base_url("https://example.invalid/api/v1/")?
+                .with_transport(transport)
+                .with_retries(1, Duration::from_millis(1))
+                .with_stream_idle_timeout(Duration::from_millis(50))
+                .with_api_key("sk-or-test")
+        };
+
+        // A stall after the first chunk ends the stream without a retry.
+        let transport = Arc::new(HangingTransport {
+            lines: vec![
+                "data: {\"id\":\"gen-h\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"}}]}\n\n",
+                ": OPENROUTER PROCESSING\n\n",
+            ],
+            calls: AtomicUsize::new(0),
+        });
+        let client = client_with(transport.clone())?;
+        let mut stream = client.chat_completion_stream(request());
+        let first = stream.next().await.expect("first chunk")?;
+        assert_eq!(first.choices[0].delta.content.as_deref(), Some("Hi"));
+        match stream.next().await {
+            Some(Err(crate::error::Error::StreamStalled { idle })) => {
+                assert_eq!(idle, Duration::from_millis(50))
+            }
+            other => panic!("expected a stall, got {:?}", other.map(|r| r.is_ok())),
+        }
+        assert!(stream.next().await.is_none());
+        assert_eq!(transport.calls.load(Ordering::SeqCst), 1);
+
+        // A stall before any chunk is retried.
+        let transport = Arc::new(HangingTransport {
+            lines: Vec::new(),
+            calls: AtomicUsize::new(0),
+        });
+        let client = client_with(transport.clone())?;
+        let mut stream = client.chat_completion_stream(request());
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(crate::error::Error::StreamStalled { .. }))
+        ));
+        assert_eq!(transport.calls.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_call_metrics() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::api::stream::StreamAccumulator;
+        use crate::types::metrics::CallMetrics;
+        use futures::TryStreamExt;
+        use std::time::Duration;
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let metrics = CallMetrics {
+            time_to_first_token: Some(Duration::from_millis(500)),
+            duration: Duration::from_millis(2500),
+            completion_tokens: Some(100),
+        };
+        assert_eq!(metrics.tokens_per_second(), Some(50.0));
+        assert_eq!(CallMetrics::default().tokens_per_second(), None);
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({ "stream": true })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                "data: {\"id\":\"gen-m\",\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\"}}]}\n\n\
+                 data: {\"id\":\"gen-m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hello there\"}}]}\n\n\
+                 data: [DONE]\n\n",
+                "text/event-stream",
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_delay(Duration::from_millis(20))
+                    .set_body_json(json!({
+                        "id": "gen-m",
+                        "created": 1234567890,
+                        "model": "openai/gpt-4o",
+                        "choices": [{
+                            "index": 0,
+                            "message": { "role": "assistant", "content": "Hello there" },
+                            "finish_reason": "stop"
+                        }],
+                        "usage": { "prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7 }
+                    })),
+            )
+            .mount(&server)
+            .await;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("sk-or-test")?;
+        let request = || ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+
+        let response = client.chat_completion(request()).await?;
+        let metrics = response.metrics.expect("metrics are measured");
+        assert!(metrics.duration >= Duration::from_millis(20));
+        assert_eq!(metrics.time_to_first_token, None);
+        assert_eq!(metrics.completion_tokens, Some(2));
+        assert!(metrics.tokens_per_second().is_some());
+
+        let chunks: Vec<_> = client
+            .chat_completion_stream(request())
+            .try_collect()
+            .await?;
+        // The role-only chunk carries no token yet.
+        assert_eq!(chunks[0].metrics.and_then(|m| m.time_to_first_token), None);
+        let mut accumulator = StreamAccumulator::new();
+        chunks.iter().for_each(|chunk| accumulator.push(chunk));
+        let metrics = accumulator.metrics().expect("metrics are measured");
+        assert!(metrics.time_to_first_token.is_some());
+        assert!(metrics.time_to_first_token <= Some(metrics.duration));
+        assert_eq!(metrics.completion_tokens, Some(3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunking() {
+        use crate::chunking::Chunker;
+
+        let sentence = "The quick brown fox jumps over the lazy dog. ";
+        let text = format!(
+            "{}\n\n{}Ünïcödé tail without a period",
+            sentence.repeat(6),
+            sentence.repeat(5)
+        );
+
+        let chunker = Chunker::new(80).with_overlap(8);
+        let chunks = chunker.chunk(&text);
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks[0].start, 0);
+        assert_eq!(chunks.last().map(|c| c.end), Some(text.len()));
+        for chunk in &chunks {
+            assert!(chunk.tokens <= 80);
+            assert_eq!(chunk.text, &text[chunk.start..chunk.end]);
+        }
+        for pair in chunks.windows(2) {
+            // Consecutive chunks overlap, and later ones start at a word.
+            assert!(pair[1].start < pair[0].end);
+            assert!(pair[1].start > pair[0].start);
+            assert!(!pair[1].text.starts_with(char::is_whitespace));
+        }
+        // The first cut falls on the paragraph break.
+        assert!(chunks[0].text.ends_with("dog. \n\n"));
+
+        let plain = Chunker::new(40).chunk(&text);
+        for pair in plain.windows(2) {
+            assert_eq!(pair[1].start, pair[0].end);
+            assert!(pair[0].tokens <= 40);
+            assert!(pair[0].text.ends_with(". ") || pair[0].text.ends_with('\n'));
+        }
+        assert_eq!(
+            plain.iter().map(|c| c.text.as_str()).collect::<String>(),
+            text
+        );
+        assert!(Chunker::new(10).chunk("").is_empty());
+        assert_eq!(Chunker::new(10).with_overlap(9).overlap(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_conversation_forking() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::conversation::{
+            Conversation, ConversationManager, ConversationStore, ForkPoint,
+            InMemoryConversationStore,
+        };
+        use std::sync::Arc;
+
+        let mut conversation = Conversation::new("main");
+        conversation.push(Message::user("Name a color"));
+        conversation.push(Message::assistant("Blue"));
+        conversation.tokens_used = 12;
+
+        // Regenerate the answer on a branch; the original keeps its reply.
+        let mut branch = conversation.fork_at(1, "alt")?;
+        branch.push(Message::assistant("Green"));
+        assert_eq!(conversation.messages[1].content.as_text(), Some("Blue"));
+        assert_eq!(branch.messages[1].content.as_text(), Some("Green"));
+        assert_eq!(branch.tokens_used, 12);
+        assert_eq!(
+            branch.forked_from,
+            Some(ForkPoint {
+                conversation_id: "main".into(),
+                message_index: 1,
+            })
+        );
+        assert_eq!(conversation.fork("copy").messages.len(), 2);
+        assert!(matches!(
+            conversation.fork_at(3, "nope"),
+            Err(crate::error::Error::InvalidRequest(_))
+        ));
+
+        let json = serde_json::to_value(&branch)?;
+        assert_eq!(json["forked_from"]["conversation_id"], "main");
+        assert!(serde_json::to_value(&conversation)?
+            .get("forked_from")
+            .is_none());
+
+        let store = Arc::new(InMemoryConversationStore::new());
+        store.save(&conversation).await?;
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_api_key("sk-or-test")?;
+        let manager = ConversationManager::new(client.chat()?, store.clone(), "openai/gpt-4o");
+        let saved = manager.fork("main", 1, "alt-2").await?;
+        assert_eq!(saved.messages.len(), 1);
+        let loaded = store.load("alt-2").await?.expect("fork saved");
+        assert_eq!(loaded.forked_from, saved.forked_from);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_agent_tool_callbacks() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::api::agent::{Agent, ToolDecision, ToolOutput};
+        use crate::models::tool::{FunctionDescription, Tool};
+        use std::sync::{Arc, Mutex};
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-a1",
+                "created": 1234567890,
+                "model": "openai/gpt-4o",
+                "choices": [{
+                    "index": 0,
+                    "message": {
+                        "role": "assistant",
+                        "content": "",
+                        "tool_calls": [
+                            {
+                                "id": "call-1",
+                                "type": "function",
+                                "function": { "name": "get_weather", "arguments": "{\"city\":\"Oslo\"}" }
+                            },
+                            {
+                                "id": "call-2",
+                                "type": "function",
+                                "function": { "name": "delete_files", "arguments": "{}" }
+                            }
+                        ]
+                    },
+                    "finish_reason": "tool_calls"
+                }]
+            })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-a2",
+                "created": 1234567890,
+                "model": "openai/gpt-4o",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": "It is sunny in Oslo." },
+                    "finish_reason": "stop"
+                }]
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("sk-or-test")?;
+        let tool = |name: &str| Tool::Function {
+            function: FunctionDescription {
+                name: name.to_string(),
+                description: None,
+                parameters: json!({ "type": "object" }),
+            },
+        };
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let (calls, results) = (events.clone(), events.clone());
+        let agent = Agent::new(client.chat()?, "openai/gpt-4o")
+            .with_tool(tool("get_weather"), |call| async move {
+                let args: serde_json::Value = call.parse_arguments()?;
+                Ok(format!("sunny in {}", args["city"].as_str().unwrap_or("?")))
+            })
+            .with_tool(tool("delete_files"), |_| async {
+                Ok("deleted".to_string())
+            })
+            .on_tool_call(move |call| {
+                let calls = calls.clone();
+                async move {
+                    let name = call.function_call.name;
+                    calls.lock().unwrap().push(format!("call {}", name));
+                    if name == "delete_files" {
+                        ToolDecision::reject("the user declined")
+                    } else {
+                        ToolDecision::Run
+                    }
+                }
+            })
+            .on_tool_result(move |call, output| {
+                let results = results.clone();
+                async move {
+                    let status = match output {
+                        ToolOutput::Success(_) => "ok",
+                        ToolOutput::Failed(_) => "failed",
+                        ToolOutput::Rejected(_) => "rejected",
+                    };
+                    results
+                        .lock()
+                        .unwrap()
+                        .push(format!("result {} {}", call.function_call.name, status));
+                }
+            });
+
+        let run = agent.run(vec![Message::user("Weather in Oslo?")]).await?;
+        assert_eq!(run.steps, 2);
+        assert_eq!(
+            run.response.choices[0].message.content.as_text(),
+            Some("It is sunny in Oslo.")
+        );
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                "call get_weather",
+                "result get_weather ok",
+                "call delete_files",
+                "result delete_files rejected",
+            ]
+        );
+        // user, assistant with calls, two tool results, final reply
+        assert_eq!(run.messages.len(), 5);
+        assert_eq!(run.messages[2].content.as_text(), Some("sunny in Oslo"));
+        assert_eq!(run.messages[3].tool_call_id.as_deref(), Some("call-2"));
+
+        let requests = server.received_requests().await.unwrap_or_default();
+        let second: serde_json::Value = serde_json::from_slice(&requests[1].body)?;
+        assert_eq!(second["messages"][3]["role"], "tool");
+        assert_eq!(
+            second["messages"][3]["content"],
+            "Tool call rejected: the user declined"
+        );
+        assert_eq!(second["tools"].as_array().map(Vec::len), Some(2));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_reasoning_deltas() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::api::stream::{ChatStreamExt, StreamAccumulator, TextDelta};
+        use futures::TryStreamExt;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let sse = [
+            json!({ "reasoning": "The user greets. " }),
+            json!({ "reasoning": "Reply politely." }),
+            json!({ "content": "Hello", "reasoning": "" }),
+            json!({ "content": "!" }),
+        ]
+        .iter()
+        .map(|delta| {
+            format!(
+                "data: {}\n\n",
+                json!({ "id": "gen-r", "choices": [{ "index": 0, "delta": delta }] })
+            )
+        })
+        .collect::<String>()
+            + "data: [DONE]\n\n";
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(sse, "text/event-stream"))
+            .mount(&server)
+            .await;
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("sk-or-test")?;
+        let request = || ChatCompletionRequest {
+            model: "deepseek/deepseek-r1".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+
+        let deltas: Vec<TextDelta> = client
+            .chat_completion_stream(request())
+            .text_deltas(true)
+            .try_collect()
+            .await?;
+        assert_eq!(
+            deltas,
+            [
+                TextDelta::Reasoning("The user greets. ".into()),
+                TextDelta::Reasoning("Reply politely.".into()),
+                TextDelta::Content("Hello".into()),
+                TextDelta::Content("!".into()),
+            ]
+        );
+
+        let answer: Vec<TextDelta> = client
+            .chat_completion_stream(request())
+            .text_deltas(false)
+            .try_collect()
+            .await?;
+        assert_eq!(answer.len(), 2);
+
+        let mut accumulator = StreamAccumulator::new();
+        let chunks: Vec<_> = client
+            .chat_completion_stream(request())
+            .try_collect()
+            .await?;
+        chunks.iter().for_each(|chunk| accumulator.push(chunk));
+        assert_eq!(accumulator.reasoning(), "The user greets. Reply politely.");
+        assert_eq!(accumulator.text(), "Hello!");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_request_tags() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::audit::{AuditRecord, AuditSink};
+        use futures::future::BoxFuture;
+        use std::collections::BTreeMap;
+        use std::sync::{Arc, Mutex};
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        #[derive(Debug, Default)]
+        struct CollectingSink(Mutex<Vec<AuditRecord>>);
+
+        impl AuditSink for CollectingSink {
+            fn record(&self, record: AuditRecord) -> BoxFuture<'_, ()> {
+                self.0.lock().unwrap().push(record);
+                Box::pin(async {})
+            }
+        }
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-t",
+                "created": 1234567890,
+                "model": "openai/gpt-4o",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": "Hi" },
+                    "finish_reason": "stop"
+                }]
+            })))
+            .mount(&server)
+            .await;
+
+        let sink = Arc::new(CollectingSink::default());
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_tag("tenant", "acme")
+            .with_tag("feature", "default")
+            .with_audit_sink(sink.clone())
+            .with_api_key("sk-or-test")?;
+        let chat = client.chat()?.with_tag("feature", "search");
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        }
+        .with_tag("experiment", "b");
+
+        let expected: BTreeMap<String, String> = [
+            ("experiment", "b"),
+            ("feature", "search"),
+            ("tenant", "acme"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let prepared = chat.dry_run(&request)?;
+        assert_eq!(prepared.tags, expected);
+        assert!(prepared.body.get("tags").is_none());
+
+        chat.chat_completion(request).await?;
+        let records = sink.0.lock().unwrap();
+        assert_eq!(records[0].tags, expected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_predicted_output() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::types::chat::Prediction;
+
+        let code = "fn main() {\n    println!(\"Hello\");\n}\n";
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_api_key("sk-or-test")?;
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![
+                Message::user("Rename the greeting to \"Hi\""),
+                Message::user(code),
+            ],
+            prediction: Some(Prediction::content(code)),
+            ..Default::default()
+        };
+        let prepared = client.dry_run(&request)?;
+        assert_eq!(
+            prepared.body["prediction"],
+            json!({ "type": "content", "content": code })
+        );
+
+        let plain = ChatCompletionRequest {
+            prediction: None,
+            ..request
+        };
+        assert!(client.dry_run(&plain)?.body.get("prediction").is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_completion_tokens_mapping() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::types::chat::uses_max_completion_tokens;
+
+        assert!(uses_max_completion_tokens("openai/o3-mini"));
+        assert!(uses_max_completion_tokens("openai/gpt-5"));
+        assert!(!uses_max_completion_tokens("openai/gpt-4o"));
+        assert!(!uses_max_completion_tokens("anthropic/claude-3-opus"));
+
+        let request = |model: &str| ChatCompletionRequest {
+            model: model.to_string(),
+            messages: vec![Message::user("Hi")],
+            max_tokens: Some(100),
+            ..Default::default()
+        };
+
+        // Without mapping, the request is sent as written.
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_api_key("sk-or-test")?;
+        let body = client.dry_run(&request("openai/o1"))?.body;
+        assert_eq!(body["max_tokens"], 100);
+        assert!(body.get("max_completion_tokens").is_none());
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_model_alias("reasoner", "openai/o4-mini")
+            .with_token_limit_mapping()
+            .with_api_key("sk-or-test")?;
+        let body = client.dry_run(&request("reasoner"))?.body;
+        assert_eq!(body["max_completion_tokens"], 100);
+        assert!(body.get("max_tokens").is_none());
+
+        let mut other = request("meta-llama/llama-3-70b");
+        other.max_tokens = None;
+        other.max_completion_tokens = Some(42);
+        let body = client.dry_run(&other)?.body;
+        assert_eq!(body["max_tokens"], 42);
+        assert!(body.get("max_completion_tokens").is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_token_rate_limiter() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::throttle::TokenRateLimiter;
+        use std::sync::Arc;
+        use std::time::{Duration, Instant};
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-tpm",
+                "choices": [{
+                    "message": { "role": "assistant", "content": "ok" },
+                    "finish_reason": "stop",
+                    "native_finish_reason": "stop"
+                }],
+                "created": 1234567890,
+                "model": "openai/gpt-4o"
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        // 60k tokens per minute refill at 1000 tokens per second.
+        let limiter = Arc::new(TokenRateLimiter::new(60_000));
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_token_rate_limiter(limiter.clone())
+            .with_api_key("sk-or-test")?;
+        let messages = vec![Message::user("Hi")];
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: messages.clone(),
+            max_tokens: Some(59_000),
+            ..Default::default()
+        };
+
+        // The first request fits in the full allowance and is charged its estimate.
+        client.chat()?.chat_completion(request.clone()).await?;
+        let charged = limiter.estimate(&messages, Some(59_000));
+        assert!(limiter.available() <= 60_000 - charged + 50);
+
+        // The next one waits until the allowance has refilled enough.
+        let started = Instant::now();
+        let request = ChatCompletionRequest {
+            max_tokens: Some(1_800),
+            ..request
+        };
+        client.chat()?.chat_completion(request).await?;
+        assert!(started.elapsed() >= Duration::from_millis(800));
+
+        // Unbounded requests are charged the completion estimate.
+        let limiter = TokenRateLimiter::new(1_000).with_completion_tokens_estimate(100);
+        assert_eq!(
+            limiter.estimate(&messages, None),
+            limiter.estimate(&messages, Some(100))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_auto_continue_truncated_completion() -> Result<(), Box<dyn std::error::Error>> {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let reply = |content: &str, finish_reason: &str| {
+            ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-continue",
+                "choices": [{
+                    "message": { "role": "assistant", "content": content },
+                    "finish_reason": finish_reason,
+                    "native_finish_reason": finish_reason
+                }],
+                "created": 1234567890,
+                "model": "openai/gpt-4o",
+                "usage": { "prompt_tokens": 10, "completion_tokens": 4, "total_tokens": 14 }
+            }))
+        };
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(reply("Hello, wor", "length"))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(reply("ld!", "stop"))
+            .mount(&server)
+            .await;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("sk-or-test")?;
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Greet the world")],
+            max_tokens: Some(4),
+            ..Default::default()
+        };
+
+        // Disabled by default: the truncated reply is returned as is.
+        let response = client.chat_completion(request.clone()).await?;
+        assert_eq!(response.choices[0].finish_reason.as_deref(), Some("length"));
+
+        let response = client
+            .chat()?
+            .with_auto_continue(3)
+            .chat_completion(request)
+            .await?;
+        let choice = &response.choices[0];
+        assert_eq!(choice.message.content.to_text(), "Hello, world!");
+        assert_eq!(choice.finish_reason.as_deref(), Some("stop"));
+        let usage = response.usage.as_ref().unwrap();
+        assert_eq!((usage.completion_tokens, usage.total_tokens), (8, 28));
+
+        // The follow-up sent the partial reply as an assistant prefill.
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 3);
+        let body: serde_json::Value = serde_json::from_slice(&requests[2].body)?;
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1]["role"], "assistant");
+        assert_eq!(messages[1]["content"], "Hello, wor");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fallback_route() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::types::chat::Route;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_api_key("sk-or-test")?;
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            models: Some(vec!["anthropic/claude-3.5-sonnet".to_string()]),
+            route: Some(Route::Fallback),
+            ..Default::default()
+        };
+        let body = client.dry_run(&request)?.body;
+        assert_eq!(body["route"], "fallback");
+        assert_eq!(body["models"], json!(["anthropic/claude-3.5-sonnet"]));
+
+        let request = ChatCompletionRequest {
+            route: None,
+            ..request
+        };
+        assert!(client.dry_run(&request)?.body.get("route").is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lenient_response_decoding() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::types::chat::{ChatCompletionChunk, ChatCompletionResponse, Content};
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        // Content as an array with a bare string and an unknown part, null usage counts sent
+        // as strings, a float timestamp, and a null model.
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-quirky",
+                "choices": [{
+                    "message": {
+                        "role": "assistant",
+                        "content": [
+                            { "type": "text", "text": "Hello" },
+                            ", world",
+                            { "type": "thinking", "thinking": "..." }
+                        ],
+                        "tool_calls": null
+                    },
+                    "finish_reason": "stop"
+                }],
+                "created": 1234567890.5,
+                "model": null,
+                "usage": { "prompt_tokens": "10", "completion_tokens": 2, "total_tokens": null }
+            })))
+            .mount(&server)
+            .await;
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("sk-or-test")?;
+        let response = client
+            .chat_completion(ChatCompletionRequest {
+                model: "openai/gpt-4o".to_string(),
+                messages: vec![Message::user("Hi")],
+                ..Default::default()
+            })
+            .await?;
+        assert_eq!(
+            response.choices[0].message.content.to_text(),
+            "Hello, world"
+        );
+        assert_eq!(response.created, 1234567890);
+        assert_eq!(response.model, "");
+        let usage = response.usage.as_ref().unwrap();
+        assert_eq!(
+            (
+                usage.prompt_tokens,
+                usage.completion_tokens,
+                usage.total_tokens
+            ),
+            (10, 2, 0)
+        );
+
+        // Null content, missing usage, and a usage value of the wrong shape.
+        let response: ChatCompletionResponse = serde_json::from_value(json!({
+            "id": "gen-null",
+            "choices": [{ "message": { "role": "assistant", "content": null }, "finish_reason": "tool_calls" }],
+            "created": 1
+        }))?;
+        assert_eq!(
+            response.choices[0].message.content,
+            Content::Text(String::new())
+        );
+        assert!(response.usage.is_none());
+        let response: ChatCompletionResponse = serde_json::from_value(json!({
+            "id": "gen-odd", "choices": [], "created": 1, "model": "m", "usage": "n/a"
+        }))?;
+        assert!(response.usage.is_none());
+
+        // Stream chunks with null choices.
+        let chunk: ChatCompletionChunk =
+            serde_json::from_value(json!({ "id": null, "choices": null }))?;
+        assert!(chunk.choices.is_empty());
+
+        // An error reported with a 200 status is not mistaken for an empty response.
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "error": { "code": 400, "message": "Provider rejected the request" }
+            })))
+            .mount(&server)
+            .await;
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("sk-or-test")?;
+        let error = client
+            .chat_completion(ChatCompletionRequest {
+                model: "openai/gpt-4o".to_string(),
+                messages: vec![Message::user("Hi")],
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.status_code(), Some(400));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_key_limit_monitor() -> Result<(), Box<dyn std::error::Error>> {
+        use std::sync::{Arc, Mutex};
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        for usage in [5.0, 8.0, 8.5, 9.6, 2.0, 9.2] {
+            Mock::given(method("GET"))
+                .and(path("/key"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "data": {
+                        "label": "sk-or-v1-abc...xyz",
+                        "usage": usage,
+                        "limit": 10.0,
+                        "is_free_tier": false,
+                        "rate_limit": { "requests": 200, "interval": "10s" }
+                    }
+                })))
+                .up_to_n_times(1)
+                .mount(&server)
+                .await;
+        }
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("sk-or-test")?;
+
+        let info = client.key()?.get().await?;
+        assert_eq!(info.remaining(), Some(rust_decimal::Decimal::from(5)));
+        assert_eq!(info.usage_fraction(), Some(0.5));
+        assert_eq!(
+            info.rate_limit.as_ref().map(|limit| limit.requests),
+            Some(200)
+        );
+
+        let alerts = Arc::new(Mutex::new(Vec::new()));
+        let monitor = client.key()?.monitor().on_alert({
+            let alerts = alerts.clone();
+            move |alert| alerts.lock().unwrap().push(alert.threshold)
+        });
+        // 80% crosses 75%; 85% crosses nothing new; 96% jumps straight to 95%.
+        assert_eq!(
+            monitor.check().await?.map(|alert| alert.threshold),
+            Some(0.75)
+        );
+        assert!(monitor.check().await?.is_none());
+        assert_eq!(
+            monitor.check().await?.map(|alert| alert.usage_fraction),
+            Some(0.96)
+        );
+        // After the limit resets, crossing 90% alerts again.
+        assert!(monitor.check().await?.is_none());
+        assert!(monitor.check().await?.is_some());
+        assert_eq!(*alerts.lock().unwrap(), vec![0.75, 0.95, 0.9]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_modalities() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::types::chat::Modality;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_api_key("sk-or-test")?;
+        let request = ChatCompletionRequest {
+            model: "google/gemini-2.5-flash-image-preview".to_string(),
+            messages: vec![Message::user("Draw a lighthouse")],
+            ..Default::default()
+        }
+        .with_modalities([Modality::Image, Modality::Text]);
+        let body = client.dry_run(&request)?.body;
+        assert_eq!(body["modalities"], json!(["image", "text"]));
+
+        let empty = request.with_modalities([]);
+        assert!(matches!(
+            client.dry_run(&empty),
+            Err(crate::error::Error::InvalidRequest(_))
+        ));
+
+        let payload = crate::api::request::RequestBuilder::new(json!({}))
+            .model("openai/gpt-4o-audio-preview")
+            .message(Message::user("Say hi"))
+            .with_modalities([Modality::Text, Modality::Audio])?
+            .build();
+        assert_eq!(
+            serde_json::to_value(&payload)?["modalities"],
+            json!(["text", "audio"])
+        );
+        assert!(crate::api::request::RequestBuilder::new(json!({}))
+            .with_modalities([])
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_chat_completion_with_envelope() -> Result<(), Box<dyn std::error::Error>> {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("x-generation-id", "gen-envelope")
+                    .set_body_json(json!({
+                        "id": "gen-envelope",
+                        "choices": [{
+                            "message": { "role": "assistant", "content": "Hi!" },
+                            "finish_reason": "stop",
+                            "native_finish_reason": "stop",
+                            "logprobs": null
+                        }],
+                        "created": 1234567890,
+                        "model": "openai/gpt-4o",
+                        "system_fingerprint": "fp_123"
+                    })),
+            )
+            .mount(&server)
+            .await;
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("sk-or-test")?;
+
+        let envelope = client
+            .chat_completion_with_envelope(ChatCompletionRequest {
+                model: "openai/gpt-4o".to_string(),
+                messages: vec![Message::user("Hi")],
+                ..Default::default()
+            })
+            .await?;
+        assert_eq!(envelope.status.as_u16(), 200);
+        assert_eq!(envelope.header("x-generation-id"), Some("gen-envelope"));
+        // Fields the typed response drops are still in the raw body.
+        assert_eq!(envelope.json()?["system_fingerprint"], "fp_123");
+        let response = envelope.into_data();
+        assert_eq!(response.choices[0].message.content.to_text(), "Hi!");
+        assert!(response.metrics.is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_per_model_default_params() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::models::parameters::DefaultParams;
+        use crate::models::provider_preferences::ProviderPreferences;
+        use crate::types::completion::CompletionRequest;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url("https://openrouter.ai/api/v1/")?
+            .with_default_params(
+                DefaultParams::new()
+                    .with_temperature(0.7)
+                    .with_top_p(0.9)
+                    .with_max_tokens(512),
+            )
+            .with_model_alias("coder", "deepseek/deepseek-coder")
+            .with_model_params(
+                "coder",
+                DefaultParams::new()
+                    .with_temperature(0.1)
+                    .with_provider_preferences(
+                        ProviderPreferences::default().with_only(["deepseek"]),
+                    ),
+            )
+            .with_model_params("openai/o3-mini", DefaultParams::new().with_max_tokens(4096))
+            .with_api_key("sk-or-test")?;
+        let request = |model: &str| ChatCompletionRequest {
+            model: model.to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+
+        // The model's defaults win over the client's, which fill in the rest.
+        let body = client.dry_run(&request("coder"))?.body;
+        assert_eq!(body["model"], "deepseek/deepseek-coder");
+        assert_eq!(body["temperature"], 0.1);
+        assert_eq!(body["top_p"], 0.9);
+        assert_eq!(body["max_tokens"], 512);
+        assert_eq!(body["provider"]["only"], json!(["deepseek"]));
+
+        let body = client.dry_run(&request("openai/o3-mini"))?.body;
+        assert_eq!(body["temperature"], 0.7);
+        assert_eq!(body["max_tokens"], 4096);
+        assert!(body.get("provider").is_none());
+
+        // Explicit request settings win over both.
+        let mut explicit = request("deepseek/deepseek-coder");
+        explicit.sampling.temperature = Some(1.0);
+        let body = client.dry_run(&explicit)?.body;
+        assert_eq!(body["temperature"], 1.0);
+        assert_eq!(body["provider"]["only"], json!(["deepseek"]));
+
+        // Defaults keyed by another alias of the same id apply, first alias by name.
+        let mut config = client.config.clone();
+        for alias in ["deepseek", "zz-coder"] {
+            config
+                .model_aliases
+                .insert(alias.to_string(), "deepseek/deepseek-coder".to_string());
+        }
+        config.model_params.insert(
+            "zz-coder".to_string(),
+            DefaultParams::new().with_temperature(0.5),
+        );
+        let mut via_alias = request("deepseek");
+        config.apply_chat_defaults(&mut via_alias);
+        assert_eq!(via_alias.sampling.temperature, Some(0.1));
+
+        // Other models only get the client-wide defaults.
+        let body = client.dry_run(&request("openai/gpt-4o"))?.body;
+        assert_eq!(body["temperature"], 0.7);
+
+        // Text completions get per-model defaults too.
+        let mut completion = CompletionRequest {
+            model: "openai/o3-mini".to_string(),
+            prompt: "Once upon a time".to_string(),
+            sampling: Default::default(),
+            extra_params: json!({}),
+        };
+        client.config.apply_completion_defaults(&mut completion);
+        assert_eq!(completion.extra_params["max_tokens"], 4096);
+        assert_eq!(completion.extra_params["temperature"], 0.7);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_decode_error_names_field_path() -> Result<(), Box<dyn std::error::Error>> {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let body = json!({
+            "id": "gen-bad",
+            "choices": [{
+                "message": { "role": 42, "content": "Hi" },
+                "finish_reason": "stop"
+            }],
+            "created": 1234567890,
+            "model": "openai/gpt-4o"
+        })
+        .to_string();
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body.clone(), "application/json"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/generation"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw("{\"data\": {} } trailing", "application/json"),
+            )
+            .mount(&server)
+            .await;
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("sk-or-test")?;
+
+        let error = client
+            .chat_completion(ChatCompletionRequest {
+                model: "openai/gpt-4o".to_string(),
+                messages: vec![Message::user("Hi")],
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+        match error {
+            crate::error::Error::Decode {
+                status,
+                ref path,
+                body: ref raw,
+                ..
+            } => {
+                assert_eq!(status, 200);
+                assert_eq!(path, "choices[0].message.role");
+                assert_eq!(raw, &body);
+            }
+            other => panic!("expected a decode error, got {:?}", other),
+        }
+        assert!(!error.is_retryable());
+
+        let error = client.generation()?.get("gen-bad").await.unwrap_err();
+        assert!(matches!(error, crate::error::Error::Decode { .. }));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_filter_deltas_keeps_tool_call_chunks() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::api::stream::{ChatStreamExt, StreamAccumulator};
+        use futures::TryStreamExt;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let sse = [
+            r#"{"id":"gen-t","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"get_weather","arguments":"{\"ci"}}]}}]}"#,
+            r#"{"id":"gen-t","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"ty\":\"Paris\"}"}}]}}]}"#,
+            r#"{"id":"gen-t","choices":[{"index":0,"delta":{},"finish_reason":"tool_calls"}]}"#,
+        ]
+        .iter()
+        .map(|data| format!("data: {}\n\n", data))
+        .collect::<String>()
+            + "data: [DONE]\n\n";
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(sse, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("sk-or-test")?;
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Weather in Paris?")],
+            ..Default::default()
+        };
+
+        let chunks: Vec<_> = client
+            .chat()?
+            .chat_completion_stream(request)
+            .filter_deltas(|delta| !delta.is_empty())
+            .try_collect()
+            .await?;
+        // Chunks carrying only tool-call deltas have no content but must survive the filter.
+        assert_eq!(chunks.len(), 3);
+
+        let mut accumulator = StreamAccumulator::new();
+        for chunk in &chunks {
+            accumulator.push(chunk);
+        }
+        let calls = accumulator.tool_calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].function_call.name, "get_weather");
+        assert_eq!(calls[0].function_call.arguments, r#"{"city":"Paris"}"#);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_filter_deltas_keeps_annotation_chunks() -> Result<(), Box<dyn std::error::Error>>
+    {
+        use crate::api::stream::{ChatStreamExt, StreamAccumulator};
+        use futures::TryStreamExt;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let sse = [
+            r#"{"id":"gen-w","choices":[{"index":0,"delta":{"content":"See the site."}}]}"#,
+            r#"{"id":"gen-w","choices":[{"index":0,"delta":{"annotations":[{"type":"url_citation","url_citation":{"url":"https://www.rust-lang.org/"}}]}}]}"#,
+            r#"{"id":"gen-w","choices":[{"index":0,"delta":{},"finish_reason":"stop"}]}"#,
+        ]
+        .iter()
+        .map(|data| format!("data: {}\n\n", data))
+        .collect::<String>()
+            + "data: [DONE]\n\n";
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(sse, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_api_key("sk-or-test")?;
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o:online".to_string(),
+            messages: vec![Message::user("What is Rust?")],
+            ..Default::default()
+        };
+
+        let chunks: Vec<_> = client
+            .chat()?
+            .chat_completion_stream(request)
+            .filter_deltas(|delta| !delta.is_empty())
+            .try_collect()
+            .await?;
+        assert_eq!(chunks.len(), 3);
+
+        let mut accumulator = StreamAccumulator::new();
+        for chunk in &chunks {
+            accumulator.push(chunk);
+        }
+        let message = accumulator.message();
+        let urls: Vec<_> = message.citations().map(|c| c.url.as_str()).collect();
+        assert_eq!(urls, ["https://www.rust-lang.org/"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cost_budget_reserves_in_flight_requests() -> Result<(), Box<dyn std::error::Error>>
+    {
+        use crate::budget::{CostBudget, ModelPricing};
+        use crate::error::Error;
+        use rust_decimal::Decimal;
+        use std::sync::Arc;
+        use std::time::Duration;
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({ "model": "openai/gpt-4o" })))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_delay(Duration::from_millis(200))
+                    .set_body_json(json!({
+                        "id": "gen-reserve",
+                        "choices": [{
+                            "message": { "role": "assistant", "content": "ok" },
+                            "finish_reason": "stop"
+                        }],
+                        "created": 1234567890,
+                        "model": "openai/gpt-4o",
+                        "usage": { "prompt_tokens": 10, "completion_tokens": 2, "total_tokens": 12, "cost": 0.01 }
+                    })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({ "model": "openai/gpt-4o-mini" })))
+            .respond_with(ResponseTemplate::new(500).set_body_string("upstream failed"))
+            .mount(&server)
+            .await;
+
+        // Each request is estimated at roughly 0.05, so only one fits in flight at a time.
+        let budget = Arc::new(
+            CostBudget::new()
+                .with_pricing(
+                    "openai/gpt-4o",
+                    ModelPricing::new(Decimal::new(1, 4), Decimal::new(1, 4)),
+                )
+                .with_pricing(
+                    "openai/gpt-4o-mini",
+                    ModelPricing::new(Decimal::new(1, 4), Decimal::new(1, 4)),
+                )
+                .with_max_total_cost(Decimal::new(8, 2)),
+        );
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_budget(budget.clone())
+            .with_api_key("sk-or-test")?;
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        };
+
+        let chat = client.chat()?;
+        let (first, second) = tokio::join!(
+            chat.chat_completion(request.clone()),
+            chat.chat_completion(request.clone())
+        );
+        let rejected = [&first, &second]
+            .iter()
+            .filter(|result| matches!(result, Err(Error::BudgetExceeded { .. })))
+            .count();
+        assert_eq!(rejected, 1);
+        // The reservation is replaced by the reported cost.
+        assert_eq!(budget.reserved(), Decimal::ZERO);
+        assert_eq!(budget.spent(), Decimal::new(1, 2));
+
+        // A failed request releases its reservation without being charged.
+        let failing = ChatCompletionRequest {
+            model: "openai/gpt-4o-mini".to_string(),
+            ..request
+        };
+        assert!(chat.chat_completion(failing).await.is_err());
+        assert_eq!(budget.reserved(), Decimal::ZERO);
+        assert_eq!(budget.spent(), Decimal::new(1, 2));
+        assert_eq!(budget.remaining(), Some(Decimal::new(7, 2)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_audit_sink_records_streams() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::audit::{AuditRecord, AuditSink};
+        use crate::budget::{CostBudget, ModelPricing};
+        use futures::future::BoxFuture;
+        use futures::TryStreamExt;
+        use rust_decimal::Decimal;
+        use std::sync::{Arc, Mutex};
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        #[derive(Debug, Default)]
+        struct CollectingSink(Mutex<Vec<AuditRecord>>);
+
+        impl AuditSink for CollectingSink {
+            fn record(&self, record: AuditRecord) -> BoxFuture<'_, ()> {
+                self.0.lock().unwrap().push(record);
+                Box::pin(async {})
+            }
+        }
+
+        let sse = [
+            r#"{"id":"gen-streamed","choices":[{"index":0,"delta":{"role":"assistant","content":"ok"}}]}"#,
+            r#"{"id":"gen-streamed","choices":[{"index":0,"delta":{},"finish_reason":"stop"}]}"#,
+        ]
+        .iter()
+        .map(|data| format!("data: {}\n\n", data))
+        .collect::<String>()
+            + "data: [DONE]\n\n";
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(sse, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let budget = Arc::new(CostBudget::new().with_pricing(
+            "openai/gpt-4o",
+            ModelPricing::new(Decimal::new(1, 4), Decimal::new(1, 4)),
+        ));
+        let sink = Arc::new(CollectingSink::default());
+        let client = OpenRouterClient::<Unconfigured>::new()
+            .with_base_url(format!("{}/", server.uri()))?
+            .with_audit_sink(sink.clone())
+            .with_budget(budget.clone())
+            .with_api_key("sk-or-test")?;
+        let request = ChatCompletionRequest {
+            model: "openai/gpt-4o".to_string(),
+            messages: vec![Message::user("Hi")],
+            ..Default::default()
+        }
+        .with_tag("feature", "chat");
+
+        let chunks: Vec<_> = client
+            .chat()?
+            .chat_completion_stream(request.clone())
+            .try_collect()
+            .await?;
+        assert_eq!(chunks.len(), 2);
+
+        // A stream that fails before its first chunk is recorded without a charge.
+        let failing = client
+            .chat()?
+            .with_base_url(format!("{}/missing/", server.uri()))?;
+        let result: Result<Vec<_>, _> = failing.chat_completion_stream(request).try_collect().await;
+        assert!(result.is_err());
+
+        let records = sink.0.lock().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].request_id.as_deref(), Some("gen-streamed"));
+        assert_eq!(records[0].status, Some(200));
+        assert_eq!(records[0].cost, Some(budget.spent()));
+        assert!(budget.spent() > Decimal::ZERO);
+        assert_eq!(
+            records[0].tags.get("feature").map(String::as_str),
+            Some("chat")
+        );
+        assert_eq!(records[1].status, Some(404));
+        assert_eq!(records[1].error.as_deref(), Some("API error (status 404)"));
+        assert_eq!(records[1].cost, None);
+        assert_eq!(
+            records[1].tags.get("feature").map(String::as_str),
+            Some("chat")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_empty_summary_keeps_history() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::conversation::summarize::summarize_if_needed;
+        use crate::conversation::{Conversation, SummarizationConfig};
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let replies = [
+            json!({ "id": "gen-empty", "choices": [], "created": 1234567890, "model": "openai/gpt-4o-mini" }),
+            json!({
+                "id": "gen-blank",
+                "choices": [{
+                    "message": { "role": "assistant", "content": "  " },
+                    "finish_reason": "stop"
+                }],
+                "created": 1234567890,
+                "model": "openai/gpt-4o-mini"
+            }),
+        ];
+        for reply in replies {
+            let server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/chat/completions"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(reply))
+                .mount(&server)
+                .await;
+            let client = OpenRouterClient::<Unconfigured>::new()
+                .with_base_url(format!("{}/", server.uri()))?
+                .with_api_key("test-key")?;
+
+            let mut conversation = Conversation::new("long-chat");
+            for i in 0..2 {
+                conversation.push(Message::user(format!("Question {} {}", i, "x".repeat(200))));
+                conversation.push(Message::assistant(format!("Answer {}", i)));
+            }
+            let before = conversation.messages.len();
+            let config = SummarizationConfig::new(50, "openai/gpt-4o-mini").with_keep_recent(2);
+
+            let compressed =
+                summarize_if_needed(&client.chat()?, &config, &mut conversation).await?;
+            assert!(!compressed);
+            assert_eq!(conversation.messages.len(), before);
+            assert!(conversation.messages.iter().all(|m| m.role != "system"));
+        }
+
+        Ok(())
+    }
 }
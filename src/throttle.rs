@@ -0,0 +1,136 @@
+/*!
+   # Throttle Module
+
+   Paces chat completions by tokens per minute, which is how upstream quotas are enforced
+   for heavy batch workloads. A [`TokenRateLimiter`] charges every request its estimated
+   prompt tokens plus its completion limit (`max_tokens` or `max_completion_tokens`, or a
+   configurable estimate when neither is set) before it is sent, and holds requests back
+   until the per-minute allowance has room for them.
+
+   The allowance refills continuously, so a limiter starting full admits a burst of up to
+   one minute's worth of tokens and then settles at the configured rate. Waiting requests
+   are admitted in arrival order. This complements the scheduler, which caps how many
+   requests are in flight rather than how many tokens they use.
+
+   Install a limiter with `with_token_rate_limiter()` on the builder; it can be shared by
+   several clients drawing from the same quota.
+*/
+
+use crate::client::PreparedRequest;
+use crate::types::chat::Message;
+use crate::utils::tokens::estimate_messages_tokens;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Completion tokens assumed for requests without a token limit.
+const DEFAULT_COMPLETION_TOKENS_ESTIMATE: u32 = 512;
+
+/// Limits the estimated tokens sent per minute.
+pub struct TokenRateLimiter {
+    tokens_per_minute: u32,
+    completion_tokens_estimate: u32,
+    bucket: Mutex<Bucket>,
+    queue: tokio::sync::Mutex<()>,
+}
+
+struct Bucket {
+    available: f64,
+    updated: Instant,
+}
+
+impl fmt::Debug for TokenRateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenRateLimiter")
+            .field("tokens_per_minute", &self.tokens_per_minute)
+            .field("available", &self.available())
+            .finish()
+    }
+}
+
+impl TokenRateLimiter {
+    /// Creates a limiter allowing `tokens_per_minute` estimated tokens (at least 1) per
+    /// minute, starting with a full allowance.
+    pub fn new(tokens_per_minute: u32) -> Self {
+        let tokens_per_minute = tokens_per_minute.max(1);
+        Self {
+            tokens_per_minute,
+            completion_tokens_estimate: DEFAULT_COMPLETION_TOKENS_ESTIMATE,
+            bucket: Mutex::new(Bucket {
+                available: f64::from(tokens_per_minute),
+                updated: Instant::now(),
+            }),
+            queue: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Sets how many completion tokens are charged for requests that set no token limit
+    /// (default 512).
+    pub fn with_completion_tokens_estimate(mut self, tokens: u32) -> Self {
+        self.completion_tokens_estimate = tokens;
+        self
+    }
+
+    /// The number of tokens allowed per minute.
+    pub fn tokens_per_minute(&self) -> u32 {
+        self.tokens_per_minute
+    }
+
+    /// The number of tokens that can be spent right now without waiting.
+    pub fn available(&self) -> u32 {
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket);
+        bucket.available as u32
+    }
+
+    /// Estimates the tokens a request charges: its prompt plus `max_tokens`, or the
+    /// completion tokens estimate if `max_tokens` is `None`.
+    pub fn estimate(&self, messages: &[Message], max_tokens: Option<u32>) -> u32 {
+        let prompt_tokens = u32::try_from(estimate_messages_tokens(messages)).unwrap_or(u32::MAX);
+        prompt_tokens.saturating_add(max_tokens.unwrap_or(self.completion_tokens_estimate))
+    }
+
+    /// Waits until `tokens` can be spent, then spends them. Requests larger than the
+    /// per-minute allowance wait for a full allowance instead of forever.
+    pub async fn acquire(&self, tokens: u32) {
+        let tokens = f64::from(tokens.min(self.tokens_per_minute));
+        // Holding the queue lock while waiting admits requests in arrival order.
+        let _turn = self.queue.lock().await;
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                self.refill(&mut bucket);
+                if bucket.available >= tokens {
+                    bucket.available -= tokens;
+                    return;
+                }
+                Duration::from_secs_f64((tokens - bucket.available) / self.tokens_per_second())
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Waits until the estimated tokens of a prepared chat request can be spent.
+    pub(crate) async fn acquire_request(&self, request: &PreparedRequest) {
+        let messages: Vec<Message> =
+            serde_json::from_value(request.body["messages"].clone()).unwrap_or_default();
+        let max_tokens = ["max_completion_tokens", "max_tokens"]
+            .iter()
+            .find_map(|field| request.body[field].as_u64())
+            .map(|tokens| u32::try_from(tokens).unwrap_or(u32::MAX));
+        let tokens = self.estimate(&messages, max_tokens);
+        tracing::trace!("throttling request of {} estimated tokens", tokens);
+        self.acquire(tokens).await;
+    }
+
+    fn tokens_per_second(&self) -> f64 {
+        f64::from(self.tokens_per_minute) / 60.0
+    }
+
+    fn refill(&self, bucket: &mut Bucket) {
+        let now = Instant::now();
+        let refilled = now.duration_since(bucket.updated).as_secs_f64() * self.tokens_per_second();
+        bucket.available = (bucket.available + refilled).min(f64::from(self.tokens_per_minute));
+        bucket.updated = now;
+    }
+}
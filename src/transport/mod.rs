@@ -0,0 +1,79 @@
+/*!
+   # Transport Module
+
+   All endpoint calls go through a [`Transport`], which turns a [`PreparedRequest`] into an
+   [`HttpResponse`]. By default requests are sent with the client's `reqwest::Client`; a custom
+   transport can be installed with `with_transport()` on the builder, for example the
+   record/replay transport available behind the `replay` feature.
+
+   The trait and its request/response types do not depend on reqwest, so builds without the
+   `reqwest` feature can still prepare requests and send them over any HTTP stack.
+*/
+
+#[cfg(feature = "replay")]
+pub mod replay;
+#[cfg(feature = "reqwest")]
+mod reqwest_transport;
+
+#[cfg(feature = "reqwest")]
+pub(crate) use reqwest_transport::execute;
+#[cfg(feature = "reqwest")]
+pub use reqwest_transport::ReqwestTransport;
+
+#[cfg(feature = "replay")]
+pub use replay::{ReplayMode, ReplayTransport};
+
+use crate::client::PreparedRequest;
+use crate::error::{Error, Result};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::stream::{self, BoxStream};
+use futures::{StreamExt, TryStreamExt};
+use http::header::HeaderMap;
+use http::StatusCode;
+use std::fmt;
+
+/// An HTTP response whose body can be consumed as a whole or as a byte stream.
+pub struct HttpResponse {
+    /// The HTTP status code.
+    pub status: StatusCode,
+    /// The response headers.
+    pub headers: HeaderMap,
+    /// The response body, as a stream of chunks.
+    pub body: BoxStream<'static, Result<Bytes>>,
+}
+
+impl HttpResponse {
+    /// Creates a response with a fully buffered body.
+    pub fn from_bytes(status: StatusCode, headers: HeaderMap, body: impl Into<Bytes>) -> Self {
+        let body = body.into();
+        Self {
+            status,
+            headers,
+            body: stream::once(async move { Ok(body) }).boxed(),
+        }
+    }
+
+    /// Reads the whole body into a string.
+    pub async fn text(self) -> Result<String> {
+        let chunks: Vec<Bytes> = self.body.try_collect().await?;
+        let body: Vec<u8> = chunks.concat();
+        String::from_utf8(body)
+            .map_err(|e| Error::TransportError(format!("Invalid UTF-8 body: {}", e)))
+    }
+}
+
+impl fmt::Debug for HttpResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpResponse")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Sends prepared requests over the wire (or pretends to).
+pub trait Transport: Send + Sync + fmt::Debug {
+    /// Executes the request and returns the response.
+    fn execute(&self, request: PreparedRequest) -> BoxFuture<'_, Result<HttpResponse>>;
+}
@@ -0,0 +1,184 @@
+//! VCR-style record/replay transport.
+//!
+//! In [`ReplayMode::Record`] every request is sent live and the response is written to a
+//! cassette file in the cassette directory; in [`ReplayMode::Replay`] responses are served
+//! from those files without touching the network. [`ReplayMode::Auto`] replays when a
+//! cassette exists and records otherwise.
+//!
+//! Cassettes are keyed by method, URL, and body, and never contain the `Authorization`
+//! header, so they can be committed alongside downstream tests.
+
+use super::{HttpResponse, ReqwestTransport, Transport};
+use crate::client::PreparedRequest;
+use crate::error::{Error, Result};
+use crate::utils::hash::fnv1a;
+use futures::future::BoxFuture;
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// Whether the transport records live traffic or replays cassettes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayMode {
+    /// Send requests live and save each response to a cassette.
+    Record,
+    /// Serve responses from cassettes; missing cassettes are an error.
+    Replay,
+    /// Replay if a cassette exists, otherwise record it.
+    Auto,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CassetteRequest {
+    method: String,
+    url: String,
+    body: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CassetteResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Cassette {
+    request: CassetteRequest,
+    response: CassetteResponse,
+}
+
+/// A [`Transport`] that records responses to, and replays them from, cassette files.
+#[derive(Debug)]
+pub struct ReplayTransport {
+    mode: ReplayMode,
+    dir: PathBuf,
+    live: ReqwestTransport,
+}
+
+impl ReplayTransport {
+    /// Creates a replay transport storing cassettes in `dir`.
+    pub fn new(mode: ReplayMode, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            mode,
+            dir: dir.into(),
+            live: ReqwestTransport::new(reqwest::Client::new()),
+        }
+    }
+
+    /// Records live responses into `dir`.
+    pub fn record(dir: impl Into<PathBuf>) -> Self {
+        Self::new(ReplayMode::Record, dir)
+    }
+
+    /// Replays responses from `dir` without network access.
+    pub fn replay(dir: impl Into<PathBuf>) -> Self {
+        Self::new(ReplayMode::Replay, dir)
+    }
+
+    /// Uses `client` for live requests while recording.
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.live = ReqwestTransport::new(client);
+        self
+    }
+
+    /// Returns the cassette path for a request.
+    fn cassette_path(&self, request: &CassetteRequest) -> PathBuf {
+        let key = format!("{} {} {}", request.method, request.url, request.body);
+        self.dir
+            .join(format!("{:016x}.json", fnv1a(key.as_bytes())))
+    }
+
+    async fn replay_from(&self, path: &PathBuf) -> Result<HttpResponse> {
+        let contents = tokio::fs::read_to_string(path).await.map_err(|e| {
+            Error::TransportError(format!("Failed to read cassette {}: {}", path.display(), e))
+        })?;
+        let cassette: Cassette = serde_json::from_str(&contents).map_err(|e| {
+            Error::TransportError(format!("Invalid cassette {}: {}", path.display(), e))
+        })?;
+        let status = StatusCode::from_u16(cassette.response.status)
+            .map_err(|e| Error::TransportError(format!("Invalid cassette status: {}", e)))?;
+        let mut headers = HeaderMap::new();
+        for (name, value) in cassette.response.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(&value),
+            ) {
+                headers.append(name, value);
+            }
+        }
+        Ok(HttpResponse::from_bytes(
+            status,
+            headers,
+            cassette.response.body,
+        ))
+    }
+
+    async fn record_to(
+        &self,
+        path: &PathBuf,
+        cassette_request: CassetteRequest,
+        request: PreparedRequest,
+    ) -> Result<HttpResponse> {
+        let response = self.live.execute(request).await?;
+        let status = response.status;
+        let headers = response.headers.clone();
+        let body = response.text().await?;
+
+        let cassette = Cassette {
+            request: cassette_request,
+            response: CassetteResponse {
+                status: status.as_u16(),
+                headers: headers
+                    .iter()
+                    .filter_map(|(name, value)| {
+                        value
+                            .to_str()
+                            .ok()
+                            .map(|v| (name.to_string(), v.to_string()))
+                    })
+                    .collect(),
+                body: body.clone(),
+            },
+        };
+        let contents = serde_json::to_string_pretty(&cassette)
+            .map_err(|e| Error::TransportError(format!("Failed to encode cassette: {}", e)))?;
+        tokio::fs::create_dir_all(&self.dir).await.map_err(|e| {
+            Error::TransportError(format!("Failed to create {}: {}", self.dir.display(), e))
+        })?;
+        tokio::fs::write(path, contents).await.map_err(|e| {
+            Error::TransportError(format!(
+                "Failed to write cassette {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        Ok(HttpResponse::from_bytes(status, headers, body))
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn execute(&self, request: PreparedRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        Box::pin(async move {
+            let cassette_request = CassetteRequest {
+                method: request.method.to_string(),
+                url: request.url.to_string(),
+                body: request.body.clone(),
+            };
+            let path = self.cassette_path(&cassette_request);
+            let replay = match self.mode {
+                ReplayMode::Record => false,
+                ReplayMode::Replay => true,
+                ReplayMode::Auto => tokio::fs::try_exists(&path).await.unwrap_or(false),
+            };
+            if replay {
+                self.replay_from(&path).await
+            } else {
+                self.record_to(&path, cassette_request, request).await
+            }
+        })
+    }
+}
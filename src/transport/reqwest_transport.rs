@@ -0,0 +1,212 @@
+//! The reqwest-backed transport and the retrying execution path used by all endpoints.
+
+use super::{HttpResponse, Transport};
+use crate::balancer::LoadBalancer;
+use crate::client::{ClientConfig, PreparedRequest};
+use crate::error::{is_retryable_status, Error, Result};
+use bytes::Bytes;
+use futures::future::{Abortable, BoxFuture};
+use futures::{StreamExt, TryStreamExt};
+use http::header::{HeaderMap, RETRY_AFTER};
+use std::time::Duration;
+
+/// The default transport, backed by a `reqwest::Client`.
+#[derive(Debug, Clone)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    /// Wraps an existing `reqwest::Client`.
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn execute(&self, request: PreparedRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        Box::pin(send_with_reqwest(&self.client, request))
+    }
+}
+
+async fn send_with_reqwest(
+    client: &reqwest::Client,
+    request: PreparedRequest,
+) -> Result<HttpResponse> {
+    let mut builder = client
+        .request(request.method, request.url)
+        .headers(request.headers);
+    if !request.body.is_null() {
+        builder = builder.json(&request.body);
+    }
+    let response = builder.send().await?;
+    Ok(HttpResponse {
+        status: response.status(),
+        headers: response.headers().clone(),
+        body: response.bytes_stream().map_err(Error::from).boxed(),
+    })
+}
+
+/// Sends a request once, using the configured transport or falling back to `client`, and
+/// spreading it over the load balancer's backends if one is configured.
+///
+/// With a scheduler configured, the request first waits for a slot, which is held until
+/// the response body has been consumed or dropped.
+async fn send_once(
+    client: &reqwest::Client,
+    config: &ClientConfig,
+    request: PreparedRequest,
+) -> Result<HttpResponse> {
+    let permit = match config.scheduler {
+        Some(ref scheduler) => Some(scheduler.acquire(config.priority).await),
+        None => None,
+    };
+    let mut response = match config.load_balancer {
+        Some(ref balancer) => send_balanced(client, config, balancer, request).await,
+        None => send_direct(client, config, request).await,
+    }?;
+    if let Some(permit) = permit {
+        response.body = response
+            .body
+            .inspect(move |_| {
+                let _ = &permit;
+            })
+            .boxed();
+    }
+    Ok(response)
+}
+
+/// Sends a request through the configured transport, or through `client` if there is none.
+async fn send_direct(
+    client: &reqwest::Client,
+    config: &ClientConfig,
+    request: PreparedRequest,
+) -> Result<HttpResponse> {
+    match config.transport {
+        Some(ref transport) => transport.execute(request).await,
+        None => send_with_reqwest(client, request).await,
+    }
+}
+
+/// Sends a request to the backends of `balancer` in the order it plans, moving on to the
+/// next one while a backend is unreachable or answers with a transient error status.
+async fn send_balanced(
+    client: &reqwest::Client,
+    config: &ClientConfig,
+    balancer: &LoadBalancer,
+    request: PreparedRequest,
+) -> Result<HttpResponse> {
+    let mut last = None;
+    for index in balancer.plan() {
+        let routed = balancer.route(index, &config.base_url, request.clone())?;
+        let result = send_direct(client, config, routed).await;
+        let failed = match result {
+            Ok(ref response) => is_retryable_status(response.status.as_u16()),
+            Err(ref error) => error.is_retryable(),
+        };
+        balancer.report(index, !failed);
+        if !failed {
+            return result;
+        }
+        tracing::debug!("backend {} failed, trying the next one", index);
+        last = Some(result);
+    }
+    last.unwrap_or_else(|| Err(Error::ConfigError("Load balancer has no backends".into())))
+}
+
+/// Parses a `Retry-After` header given in seconds.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// Buffers a non-success response so it can be presented to the retry policy as an
+/// error and still be returned to the caller intact if no retry happens.
+async fn buffer_error_response(response: HttpResponse) -> Result<(HttpResponse, Error)> {
+    let chunks: Vec<Bytes> = response.body.try_collect().await?;
+    let body = Bytes::from(chunks.concat());
+    let error = Error::ApiError {
+        code: response.status.as_u16(),
+        message: String::from_utf8_lossy(&body).into_owned(),
+        metadata: None,
+    };
+    let response = HttpResponse::from_bytes(response.status, response.headers, body);
+    Ok((response, error))
+}
+
+/// Executes a prepared request, retrying failures as directed by the configured retry policy.
+///
+/// The request counts as in flight on the client's lifecycle until its response body has
+/// been consumed or dropped. After `shutdown()` it fails with `Error::ShutDown`, and when
+/// the shutdown timeout expires it is aborted, mid-stream if need be.
+pub(crate) async fn execute(
+    client: &reqwest::Client,
+    config: &ClientConfig,
+    request: PreparedRequest,
+) -> Result<HttpResponse> {
+    let (guard, registration) = config.lifecycle.begin(&request)?;
+    let mut response = Abortable::new(execute_with_retries(client, config, request), registration)
+        .await
+        .map_err(|_| Error::ShutDown)??;
+    let mut body = Abortable::new(response.body, guard.rearm());
+    response.body = async_stream::stream! {
+        let _guard = guard;
+        while let Some(chunk) = body.next().await {
+            yield chunk;
+        }
+        if body.is_aborted() {
+            yield Err(Error::ShutDown);
+        }
+    }
+    .boxed();
+    Ok(response)
+}
+
+/// Sends a request, retrying failures as directed by the configured retry policy.
+///
+/// Without a custom policy, transient errors are retried up to `config.max_retries` times
+/// with exponential backoff starting at `config.retry_backoff`. A `Retry-After` header acts
+/// as a lower bound on the delay. Only the response head of a successful response is
+/// awaited, so streaming bodies are never replayed.
+async fn execute_with_retries(
+    client: &reqwest::Client,
+    config: &ClientConfig,
+    request: PreparedRequest,
+) -> Result<HttpResponse> {
+    let mut attempt = 1;
+    loop {
+        let (result, delay) = match send_once(client, config, request.clone()).await {
+            Ok(response) if response.status.is_success() => return Ok(response),
+            Ok(response) => {
+                let server_delay = retry_after(&response.headers);
+                let (response, error) = buffer_error_response(response).await?;
+                let delay = config
+                    .retry_delay(&error, attempt)
+                    .map(|delay| server_delay.map_or(delay, |server| server.max(delay)));
+                (Ok(response), delay)
+            }
+            Err(error) => {
+                let delay = config.retry_delay(&error, attempt);
+                (Err(error), delay)
+            }
+        };
+        let Some(delay) = delay else {
+            return result;
+        };
+        tracing::debug!(
+            attempt,
+            tags = ?request.tags,
+            "retrying request to {} in {:?}",
+            request.url,
+            delay
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
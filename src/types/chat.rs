@@ -1,29 +1,309 @@
-use crate::models::tool::ToolCall;
+use crate::models::tool::{ToolCall, ToolCallDelta};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 
-/// Defines the role of a chat message (user, assistant, or system).
+/// Defines the role of a chat message (user, assistant, system, or tool).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ChatRole {
     User,
     Assistant,
     System,
+    Tool,
+}
+
+/// How closely a vision model inspects an image, which drives its token cost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageDetail {
+    /// A low-resolution pass at a small fixed token cost.
+    Low,
+    /// A high-resolution pass, billed by image size.
+    High,
+    /// Lets the model choose based on the image size.
+    Auto,
+}
+
+/// An image reference used inside an `image_url` content part.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageUrl {
+    /// Either a public URL or a base64 data URL (`data:image/png;base64,...`).
+    pub url: String,
+    /// The detail level; `None` leaves it to the model (`auto`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<ImageDetail>,
+}
+
+/// A single part of a multimodal message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    /// A plain text segment.
+    Text { text: String },
+    /// An image segment.
+    ImageUrl { image_url: ImageUrl },
+}
+
+impl ContentPart {
+    /// Creates a text part.
+    pub fn text(text: impl Into<String>) -> Self {
+        ContentPart::Text { text: text.into() }
+    }
+
+    /// Creates an image part from a URL or data URL.
+    pub fn image_url(url: impl Into<String>) -> Self {
+        ContentPart::ImageUrl {
+            image_url: ImageUrl {
+                url: url.into(),
+                detail: None,
+            },
+        }
+    }
+
+    /// Creates an image part with an explicit detail level.
+    pub fn image_url_with_detail(url: impl Into<String>, detail: ImageDetail) -> Self {
+        ContentPart::ImageUrl {
+            image_url: ImageUrl {
+                url: url.into(),
+                detail: Some(detail),
+            },
+        }
+    }
+}
+
+/// Message content: either a plain string or a list of structured parts.
+///
+/// Serialized untagged, so `Text` round-trips as a JSON string and `Parts`
+/// as a JSON array, matching what the API accepts and returns.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Content {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+impl Content {
+    /// Returns the content as a string slice if it is plain text.
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Content::Text(text) => Some(text),
+            Content::Parts(_) => None,
+        }
+    }
+
+    /// Returns all textual content, concatenating the text parts of a multimodal message.
+    pub fn to_text(&self) -> String {
+        match self {
+            Content::Text(text) => text.clone(),
+            Content::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Default for Content {
+    fn default() -> Self {
+        Content::Text(String::new())
+    }
+}
+
+impl fmt::Display for Content {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Content::Text(text) => f.write_str(text),
+            Content::Parts(_) => f.write_str(&self.to_text()),
+        }
+    }
+}
+
+impl From<String> for Content {
+    fn from(text: String) -> Self {
+        Content::Text(text)
+    }
+}
+
+impl From<&str> for Content {
+    fn from(text: &str) -> Self {
+        Content::Text(text.to_string())
+    }
+}
+
+impl From<Vec<ContentPart>> for Content {
+    fn from(parts: Vec<ContentPart>) -> Self {
+        Content::Parts(parts)
+    }
 }
 
 /// Represents a chat message with a role and content.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub role: String,
-    pub content: String,
+    #[serde(default, deserialize_with = "super::lenient::content")]
+    pub content: Content,
+    /// Optional participant name, used to tell apart multiple users in one
+    /// transcript or to name the function a tool message came from.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
     // Optionally include tool_calls when the assistant message contains a tool call.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCall>>,
+    /// For `tool` messages, the ID of the tool call this message answers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// For assistant messages, the sources the model cited, e.g. when answering with the
+    /// web plugin or an `:online` model.
+    #[serde(
+        default,
+        deserialize_with = "super::lenient::or_none",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub annotations: Option<Vec<Annotation>>,
+}
+
+impl Message {
+    /// Creates a message with the given role and content.
+    pub fn new(role: impl Into<String>, content: impl Into<Content>) -> Self {
+        Self {
+            role: role.into(),
+            content: content.into(),
+            name: None,
+            tool_calls: None,
+            tool_call_id: None,
+            annotations: None,
+        }
+    }
+
+    /// Creates a system message.
+    pub fn system(content: impl Into<Content>) -> Self {
+        Self::new("system", content)
+    }
+
+    /// Creates a user message.
+    pub fn user(content: impl Into<Content>) -> Self {
+        Self::new("user", content)
+    }
+
+    /// Creates an assistant message.
+    pub fn assistant(content: impl Into<Content>) -> Self {
+        Self::new("assistant", content)
+    }
+
+    /// Creates a `tool` message carrying the result of the tool call identified by `tool_call_id`.
+    ///
+    /// Prefer [`ToolCall::respond_with`] when the originating call is at hand, so the ID
+    /// cannot be mismatched.
+    pub fn tool(tool_call_id: impl Into<String>, content: impl Into<Content>) -> Self {
+        Self {
+            tool_call_id: Some(tool_call_id.into()),
+            ..Self::new("tool", content)
+        }
+    }
+
+    /// Sets the participant name on this message.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Returns the URL citations among this message's annotations, in the order given.
+    pub fn citations(&self) -> impl Iterator<Item = &UrlCitation> {
+        self.annotations
+            .iter()
+            .flatten()
+            .filter_map(|annotation| match annotation {
+                Annotation::UrlCitation { url_citation } => Some(url_citation),
+                _ => None,
+            })
+    }
+}
+
+/// A note attached to an assistant message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Annotation {
+    /// A web page the model cited.
+    UrlCitation { url_citation: UrlCitation },
+    /// An annotation type this crate does not know yet.
+    #[serde(other)]
+    Unknown,
+}
+
+/// A web page cited by the model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UrlCitation {
+    /// The URL of the cited page.
+    pub url: String,
+    /// The page title.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The excerpt of the page the answer is based on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// Byte offset in the message content where the cited span starts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_index: Option<u32>,
+    /// Byte offset in the message content where the cited span ends.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_index: Option<u32>,
+}
+
+/// Whether `model` rejects `max_tokens` in favor of `max_completion_tokens`: OpenAI's
+/// reasoning models (o1, o3, o4-mini, ...) and GPT-5.
+pub fn uses_max_completion_tokens(model: &str) -> bool {
+    let name = match model.split_once('/') {
+        Some(("openai", name)) => name,
+        Some(_) => return false,
+        None => model,
+    };
+    ["o1", "o3", "o4", "gpt-5"]
+        .iter()
+        .any(|family| name.starts_with(family))
+}
+
+/// A kind of output a model can produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Modality {
+    Text,
+    Image,
+    Audio,
+}
+
+/// Routing behavior for a request that lists fallback models.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Route {
+    /// Try `model` first, then each of the `models` in order when a model is unavailable,
+    /// rate limited, or refuses the request.
+    Fallback,
+}
+
+/// A predicted output for speculative decoding. Most of the response is expected to match
+/// it, as in edit-style workloads that rewrite a small part of a document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Prediction {
+    /// The expected response text.
+    Content { content: String },
+}
+
+impl Prediction {
+    /// Predicts that the response will largely be `content`.
+    pub fn content(content: impl Into<String>) -> Self {
+        Prediction::Content {
+            content: content.into(),
+        }
+    }
 }
 
 /// Chat completion request matching the OpenRouter API schema.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct ChatCompletionRequest {
     /// The model ID to use.
     pub model: String,
@@ -32,25 +312,112 @@ pub struct ChatCompletionRequest {
     /// Whether the response should be streamed.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
-    /// (Optional) Stub for response_format.
+    /// (Optional) Output format, e.g. JSON matching a schema for structured outputs.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub response_format: Option<String>,
+    pub response_format: Option<crate::models::structured::ResponseFormat>,
     /// (Optional) Tool calling field. Now uses our production‑ready tool types.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<crate::models::tool::Tool>>,
-    /// (Optional) Stub for provider preferences.
+    /// (Optional) Provider routing preferences.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub provider: Option<String>,
+    pub provider: Option<crate::models::provider_preferences::ProviderPreferences>,
     /// (Optional) Fallback models.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub models: Option<Vec<String>>,
+    /// (Optional) How the request is routed across `model` and the `models` list.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route: Option<Route>,
     /// (Optional) Message transforms.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub transforms: Option<Vec<String>>,
+    /// (Optional) Sampling parameters such as temperature and top_p.
+    #[serde(flatten)]
+    pub sampling: crate::models::parameters::SamplingParams,
+    /// (Optional) Maximum number of tokens to generate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// (Optional) Maximum number of tokens to generate, under the name newer OpenAI models
+    /// require instead of `max_tokens`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_completion_tokens: Option<u32>,
+    /// (Optional) Expected output, e.g. the file being edited, which lets supporting models
+    /// skip generating the parts that match.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prediction: Option<Prediction>,
+    /// (Optional) Output kinds to generate, e.g. `[Text, Image]` for image generation
+    /// models. Defaults to text only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modalities: Option<Vec<Modality>>,
+    /// Labels such as tenant, feature, or experiment id. They are not sent to the API but
+    /// travel with the prepared request to transports, audit records, and logs.
+    #[serde(skip)]
+    pub tags: BTreeMap<String, String>,
+}
+
+impl ChatCompletionRequest {
+    /// Attaches the label `key = value` to this request, replacing any client-level label
+    /// with the same key.
+    pub fn with_tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.insert(key.into(), value.into());
+        self
+    }
+
+    /// The completion token limit, whichever field sets it.
+    pub fn token_limit(&self) -> Option<u32> {
+        self.max_completion_tokens.or(self.max_tokens)
+    }
+
+    /// Moves the completion token limit into the field `model` accepts: `max_completion_tokens`
+    /// for models that reject `max_tokens` (see [`uses_max_completion_tokens`]), `max_tokens`
+    /// otherwise. If both fields are set, `max_completion_tokens` wins.
+    pub fn map_token_limit(&mut self, model: &str) {
+        let limit = self.max_completion_tokens.take().or(self.max_tokens.take());
+        if uses_max_completion_tokens(model) {
+            self.max_completion_tokens = limit;
+        } else {
+            self.max_tokens = limit;
+        }
+    }
+
+    /// Requests the given output kinds, e.g. `[Modality::Text, Modality::Image]`.
+    pub fn with_modalities(mut self, modalities: impl IntoIterator<Item = Modality>) -> Self {
+        self.modalities = Some(modalities.into_iter().collect());
+        self
+    }
+
+    /// Performs client-side validation of the request.
+    pub fn validate(&self) -> crate::error::Result<()> {
+        if self.model.trim().is_empty() {
+            return Err(crate::error::Error::InvalidRequest(
+                "Model must not be empty".into(),
+            ));
+        }
+        if self.messages.is_empty() {
+            return Err(crate::error::Error::InvalidRequest(
+                "At least one message is required".into(),
+            ));
+        }
+        if self.modalities.as_ref().is_some_and(Vec::is_empty) {
+            return Err(crate::error::Error::InvalidRequest(
+                "At least one modality is required when modalities are set".into(),
+            ));
+        }
+        self.sampling.validate()?;
+        if let Some(ref provider) = self.provider {
+            provider.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Renders the request as the equivalent curl command the client would issue
+    /// with `config`, with the API key masked.
+    pub fn to_curl(&self, config: &crate::client::ClientConfig) -> crate::error::Result<String> {
+        Ok(config.prepare_chat_request(self)?.to_curl())
+    }
 }
 
 /// A choice returned by the chat API.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Choice {
     pub message: Message,
     pub finish_reason: Option<String>,
@@ -59,26 +426,104 @@ pub struct Choice {
 }
 
 /// Usage data returned from the API.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Usage {
+    #[serde(default, deserialize_with = "super::lenient::count")]
     pub prompt_tokens: u32,
+    #[serde(default, deserialize_with = "super::lenient::count")]
     pub completion_tokens: u32,
+    #[serde(default, deserialize_with = "super::lenient::count")]
     pub total_tokens: u32,
+    /// Cost of the request in credits, when usage accounting is enabled.
+    #[serde(
+        default,
+        with = "rust_decimal::serde::float_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cost: Option<rust_decimal::Decimal>,
 }
 
 /// Chat completion response.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ChatCompletionResponse {
+    #[serde(default, deserialize_with = "super::lenient::null_as_default")]
     pub id: String,
+    #[serde(default, deserialize_with = "super::lenient::null_as_default")]
     pub choices: Vec<Choice>,
+    #[serde(default, deserialize_with = "super::lenient::timestamp")]
     pub created: i64,
+    /// The model that served the request; for Auto Router requests, the model it chose.
+    #[serde(default, deserialize_with = "super::lenient::null_as_default")]
     pub model: String,
+    /// The provider that served the request, when reported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Token usage; `None` if not reported or in a shape this crate does not understand.
+    #[serde(default, deserialize_with = "super::lenient::or_none")]
     pub usage: Option<Usage>,
+    /// Client-side timings, set on responses returned by `chat_completion`.
+    #[serde(skip)]
+    pub metrics: Option<crate::types::metrics::CallMetrics>,
+}
+
+impl ChatCompletionResponse {
+    /// Parses the first choice's content as JSON, optionally repairing almost-valid output.
+    /// See [`parse_json`](crate::models::structured::parse_json).
+    pub fn parse_json<T: serde::de::DeserializeOwned>(
+        &self,
+        repair: bool,
+    ) -> crate::error::Result<crate::models::structured::ParsedJson<T>> {
+        let text = self
+            .choices
+            .first()
+            .map(|choice| choice.message.content.to_text())
+            .unwrap_or_default();
+        crate::models::structured::parse_json(&text, repair)
+    }
 }
 
-/// A streaming chunk for chat completions (stub).
-#[derive(Debug, Deserialize)]
+/// The incremental part of a message carried by a streaming chunk.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Delta {
+    /// The role, usually present only on the first chunk.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    /// The next fragment of the message text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// The next fragment of a reasoning model's chain of thought, streamed separately from
+    /// the answer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
+    /// Fragments of tool calls requested by the model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCallDelta>>,
+    /// Annotations, such as web citations, usually sent with the last chunks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Vec<Annotation>>,
+}
+
+/// A choice within a streaming chunk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkChoice {
+    #[serde(default)]
+    pub index: u32,
+    #[serde(default)]
+    pub delta: Delta,
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub native_finish_reason: Option<String>,
+}
+
+/// A streaming chunk for chat completions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatCompletionChunk {
+    #[serde(default, deserialize_with = "super::lenient::null_as_default")]
     pub id: String,
-    pub choices: Vec<Choice>,
+    #[serde(default, deserialize_with = "super::lenient::null_as_default")]
+    pub choices: Vec<ChunkChoice>,
+    /// Client-side timings of the stream up to this chunk.
+    #[serde(skip)]
+    pub metrics: Option<crate::types::metrics::CallMetrics>,
 }
@@ -4,13 +4,15 @@ use serde::{Deserialize, Serialize};
 /// - `model`: The model ID to use.
 /// - `prompt`: The text prompt to be completed.
 ///
-/// Any extra parameters (e.g., `temperature`, `top_p`, etc.) can also be provided and will be flattened
-/// into the resulting JSON.
+/// Sampling parameters (e.g., `temperature`, `top_p`) are set through `sampling`. Any extra
+/// parameters can also be provided and will be flattened into the resulting JSON.
 #[derive(Debug, Serialize)]
 pub struct CompletionRequest {
     pub model: String,
     pub prompt: String,
     #[serde(flatten)]
+    pub sampling: crate::models::parameters::SamplingParams,
+    #[serde(flatten)]
     pub extra_params: serde_json::Value,
 }
 
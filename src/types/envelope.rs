@@ -0,0 +1,34 @@
+//! The HTTP exchange behind a typed response.
+
+use http::{HeaderMap, StatusCode};
+
+/// A typed response together with the HTTP status, headers, and raw body it was decoded
+/// from, e.g. for debugging, caching, or archiving exactly what the API returned.
+#[derive(Debug, Clone)]
+pub struct ResponseEnvelope<T> {
+    /// The HTTP status code.
+    pub status: StatusCode,
+    /// All response headers.
+    pub headers: HeaderMap,
+    /// The raw response body.
+    pub body: String,
+    /// The decoded response.
+    pub data: T,
+}
+
+impl<T> ResponseEnvelope<T> {
+    /// Returns the value of the header `name` if it is present and valid UTF-8.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)?.to_str().ok()
+    }
+
+    /// Parses the raw body as untyped JSON, including fields the typed response drops.
+    pub fn json(&self) -> serde_json::Result<serde_json::Value> {
+        serde_json::from_str(&self.body)
+    }
+
+    /// Discards the HTTP details and returns the decoded response.
+    pub fn into_data(self) -> T {
+        self.data
+    }
+}
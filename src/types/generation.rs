@@ -0,0 +1,55 @@
+//! Types returned by the generation stats endpoint, `GET /generation?id=...`.
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// The envelope the generation endpoint wraps its record in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationResponse {
+    pub data: Generation,
+}
+
+/// Final statistics of a completed generation, including its billed cost.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Generation {
+    /// The generation id, as returned in the completion response.
+    pub id: String,
+    /// The model that served the generation.
+    pub model: String,
+    /// The provider that served the generation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_name: Option<String>,
+    /// When the generation was created, as an RFC 3339 timestamp.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    /// Total cost billed, in credits.
+    #[serde(with = "rust_decimal::serde::float")]
+    pub total_cost: Decimal,
+    /// Prompt tokens, as counted by OpenRouter's normalized tokenizer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_prompt: Option<u32>,
+    /// Completion tokens, as counted by OpenRouter's normalized tokenizer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_completion: Option<u32>,
+    /// Prompt tokens, as counted by the model's own tokenizer (the billed count).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub native_tokens_prompt: Option<u32>,
+    /// Completion tokens, as counted by the model's own tokenizer (the billed count).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub native_tokens_completion: Option<u32>,
+    /// Time to first token, in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency: Option<u64>,
+    /// Total generation time, in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generation_time: Option<u64>,
+    /// The normalized finish reason.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<String>,
+    /// Whether the response was streamed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub streamed: Option<bool>,
+    /// Whether the generation was cancelled before it finished.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cancelled: Option<bool>,
+}
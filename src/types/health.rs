@@ -0,0 +1,47 @@
+//! Types returned by the connectivity probe, `OpenRouterClient::ping()`.
+
+use serde::Serialize;
+use std::time::Duration;
+
+/// Outcome of a connectivity probe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// OpenRouter answered and accepted the API key.
+    Healthy,
+    /// OpenRouter answered but rejected the API key (401 or 403).
+    Unauthorized,
+    /// OpenRouter answered with 429; requests may succeed after backing off.
+    RateLimited,
+    /// OpenRouter answered with another error status, e.g. a 5xx during an outage.
+    Degraded {
+        /// The HTTP status code received.
+        code: u16,
+    },
+    /// No response was received (DNS, connection, TLS, or timeout failure).
+    Unreachable,
+}
+
+/// Result of `OpenRouterClient::ping()`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Ping {
+    /// What the probe found.
+    pub status: HealthStatus,
+    /// Round-trip time of the probe, including connection setup.
+    pub latency: Duration,
+    /// The error message, if the probe did not succeed.
+    pub error: Option<String>,
+}
+
+impl Ping {
+    /// Returns true if OpenRouter is reachable and accepted the API key, e.g. for a
+    /// readiness probe.
+    pub fn is_healthy(&self) -> bool {
+        self.status == HealthStatus::Healthy
+    }
+
+    /// Returns true if OpenRouter answered at all, whatever the status.
+    pub fn is_reachable(&self) -> bool {
+        self.status != HealthStatus::Unreachable
+    }
+}
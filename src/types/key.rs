@@ -0,0 +1,71 @@
+//! Types returned by the API key endpoint, `GET /key`.
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// The envelope the key endpoint wraps its record in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyInfoResponse {
+    pub data: KeyInfo,
+}
+
+/// Usage and limits of the API key the request was made with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyInfo {
+    /// The key's label.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Credits spent with the key so far.
+    #[serde(with = "rust_decimal::serde::float")]
+    pub usage: Decimal,
+    /// The key's credit limit; `None` if it is unlimited.
+    #[serde(
+        default,
+        with = "rust_decimal::serde::float_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub limit: Option<Decimal>,
+    /// Credits left under the limit, as reported by the API.
+    #[serde(
+        default,
+        with = "rust_decimal::serde::float_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub limit_remaining: Option<Decimal>,
+    /// Whether the key belongs to a free-tier account.
+    #[serde(default)]
+    pub is_free_tier: bool,
+    /// The request rate limit applied to the key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<KeyRateLimit>,
+}
+
+impl KeyInfo {
+    /// Credits left under the limit, or `None` if the key is unlimited.
+    pub fn remaining(&self) -> Option<Decimal> {
+        self.limit_remaining.or_else(|| {
+            self.limit
+                .map(|limit| (limit - self.usage).max(Decimal::ZERO))
+        })
+    }
+
+    /// The fraction of the limit used so far, from 0.0 up; `None` if the key is unlimited.
+    pub fn usage_fraction(&self) -> Option<f64> {
+        let limit = self.limit?;
+        if limit <= Decimal::ZERO {
+            return Some(1.0);
+        }
+        let remaining = self.remaining()?;
+        ((limit - remaining) / limit).to_f64()
+    }
+}
+
+/// A request rate limit, such as 200 requests per `"10s"`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyRateLimit {
+    /// Requests allowed per interval.
+    pub requests: u32,
+    /// The interval, as reported by the API (e.g. `"10s"`).
+    pub interval: String,
+}
@@ -0,0 +1,90 @@
+//! Tolerant deserializers for response fields whose shape varies between providers.
+//!
+//! Providers behind OpenRouter disagree on details such as `null` versus missing fields,
+//! content sent as a string or as an array of parts, or token counts sent as strings. These
+//! helpers accept the variants seen in the wild and fall back to a default instead of
+//! failing the whole response.
+
+use super::chat::{Content, ContentPart};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+/// Deserializes `null` as the type's default.
+pub(crate) fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Deserializes an optional value, treating one that does not fit `T` as absent.
+pub(crate) fn or_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let value = Value::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value).ok())
+}
+
+/// Deserializes a count sent as an integer, a float, or a numeric string; anything else,
+/// including `null`, counts as 0.
+pub(crate) fn count<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+    let count = match value {
+        Value::Number(ref number) => number
+            .as_u64()
+            .or_else(|| number.as_f64().map(|float| float.max(0.0) as u64)),
+        Value::String(ref text) => text.trim().parse().ok(),
+        _ => None,
+    };
+    Ok(count.map_or(0, |count| u32::try_from(count).unwrap_or(u32::MAX)))
+}
+
+/// Deserializes a timestamp sent as an integer, a float, or a numeric string; anything
+/// else counts as 0.
+pub(crate) fn timestamp<'de, D>(deserializer: D) -> Result<i64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+    let timestamp = match value {
+        Value::Number(ref number) => number
+            .as_i64()
+            .or_else(|| number.as_f64().map(|float| float as i64)),
+        Value::String(ref text) => text.trim().parse().ok(),
+        _ => None,
+    };
+    Ok(timestamp.unwrap_or_default())
+}
+
+/// Deserializes message content sent as a string, `null`, a single part, or an array of
+/// parts. Parts may be bare strings; parts of unknown types are dropped.
+pub(crate) fn content<'de, D>(deserializer: D) -> Result<Content, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let content = match Value::deserialize(deserializer)? {
+        Value::Null => Content::default(),
+        Value::String(text) => Content::Text(text),
+        Value::Array(parts) => Content::Parts(
+            parts
+                .into_iter()
+                .filter_map(|part| match part {
+                    Value::String(text) => Some(ContentPart::Text { text }),
+                    part => serde_json::from_value(part).ok(),
+                })
+                .collect(),
+        ),
+        part => match serde_json::from_value::<ContentPart>(part.clone()) {
+            Ok(part) => Content::Parts(vec![part]),
+            Err(_) => Content::Text(part.to_string()),
+        },
+    };
+    Ok(content)
+}
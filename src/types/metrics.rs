@@ -0,0 +1,30 @@
+//! Client-side timings of a chat completion call, attached to responses and stream chunks.
+
+use serde::Serialize;
+use std::time::Duration;
+
+/// How long a call took, measured by the client from the moment the request was sent
+/// (including any retries).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct CallMetrics {
+    /// Time until the first content or tool call delta arrived. Only measured for streams;
+    /// a non-streaming response arrives all at once.
+    pub time_to_first_token: Option<Duration>,
+    /// Time until the response was complete or, on a stream chunk, until that chunk arrived.
+    pub duration: Duration,
+    /// Completion tokens: the usage reported by the API for non-streaming calls, an estimate
+    /// from the text received so far for streams.
+    pub completion_tokens: Option<u32>,
+}
+
+impl CallMetrics {
+    /// Completion tokens per second of generation, i.e. after the first token for streams.
+    /// Returns `None` if the token count is unknown or no time has elapsed.
+    pub fn tokens_per_second(&self) -> Option<f64> {
+        let tokens = self.completion_tokens?;
+        let generating = self
+            .duration
+            .saturating_sub(self.time_to_first_token.unwrap_or_default());
+        (!generating.is_zero()).then(|| f64::from(tokens) / generating.as_secs_f64())
+    }
+}
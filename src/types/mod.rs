@@ -1,14 +1,29 @@
 pub mod chat;
 pub mod common;
 pub mod completion;
+pub mod envelope;
+pub mod generation;
+pub mod health;
+pub mod key;
+mod lenient;
+pub mod metrics;
 pub mod models;
+#[cfg(feature = "openai")]
+pub mod openai;
 pub mod provider;
+pub mod routing;
 pub mod transform;
 pub mod web_search;
 
 // Re-export common types
 pub use chat::*;
 pub use completion::*;
+pub use envelope::*;
+pub use generation::*;
+pub use health::*;
+pub use key::*;
+pub use metrics::*;
 pub use models::*;
 pub use provider::*;
+pub use routing::*;
 pub use transform::*;
@@ -1,2 +1,111 @@
-// Stub for models endpoint types.
-pub struct ModelsResponse; // Placeholder
+//! Types for the models catalog endpoint (`GET /models`).
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// The list of models available through OpenRouter.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelsResponse {
+    pub data: Vec<Model>,
+}
+
+/// A model in the catalog.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Model {
+    /// The model id used in requests, e.g. `anthropic/claude-3.7-sonnet`.
+    pub id: String,
+    /// The display name, e.g. `Anthropic: Claude 3.7 Sonnet`.
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Unix timestamp of when the model was added.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<i64>,
+    /// Maximum context length in tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_length: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pricing: Option<Pricing>,
+    /// Request parameters the model supports, e.g. `tools` or `response_format`.
+    #[serde(default)]
+    pub supported_parameters: Vec<String>,
+}
+
+/// Prices in credits (USD) per token or per unit. The API sends them as decimal strings,
+/// which are parsed exactly and serialized back as strings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Pricing {
+    pub prompt: Decimal,
+    pub completion: Decimal,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<Decimal>,
+}
+
+impl ModelsResponse {
+    /// Returns the model with the given id, if listed.
+    pub fn get(&self, id: &str) -> Option<&Model> {
+        self.data.iter().find(|model| model.id == id)
+    }
+
+    /// Searches model ids and display names for `query`, case-insensitively.
+    ///
+    /// Exact matches rank first, then prefix matches, then substring matches, then fuzzy
+    /// matches where the query's characters appear in order (e.g. "c37s" finds
+    /// `claude-3.7-sonnet`). Models that don't match at all are omitted.
+    pub fn search(&self, query: &str) -> Vec<&Model> {
+        let query = query.trim().to_lowercase();
+        let mut matches: Vec<(u32, &Model)> = self
+            .data
+            .iter()
+            .filter_map(|model| match_score(&query, model).map(|score| (score, model)))
+            .collect();
+        matches.sort_by(|(a_score, a), (b_score, b)| b_score.cmp(a_score).then(a.id.cmp(&b.id)));
+        matches.into_iter().map(|(_, model)| model).collect()
+    }
+}
+
+/// Scores how well `model` matches the lowercase `query`; higher is better.
+fn match_score(query: &str, model: &Model) -> Option<u32> {
+    let id = model.id.to_lowercase();
+    let name = model.name.to_lowercase();
+    let slug = id.rsplit('/').next().unwrap_or(&id);
+    let score = if id == query || name == query {
+        1000
+    } else if slug == query {
+        900
+    } else if id.starts_with(query) || slug.starts_with(query) {
+        800
+    } else if name.starts_with(query) {
+        700
+    } else if name
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| word.starts_with(query))
+    {
+        600
+    } else if id.contains(query) {
+        500
+    } else if name.contains(query) {
+        400
+    } else {
+        return fuzzy_score(query, &id).or_else(|| fuzzy_score(query, &name));
+    };
+    Some(score)
+}
+
+/// Scores a subsequence match: the more compact the matched span, the higher (below 300).
+fn fuzzy_score(query: &str, text: &str) -> Option<u32> {
+    let mut chars = text.char_indices();
+    let mut start = None;
+    let mut end = 0;
+    for wanted in query.chars() {
+        let (index, c) = chars.by_ref().find(|&(_, c)| c == wanted)?;
+        start.get_or_insert(index);
+        end = index + c.len_utf8();
+    }
+    let span = end - start.unwrap_or(0);
+    let gaps = u32::try_from(span.saturating_sub(query.len())).unwrap_or(u32::MAX);
+    Some(299u32.saturating_sub(gaps))
+}
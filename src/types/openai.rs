@@ -0,0 +1,159 @@
+//! Conversions between this crate's chat types and [`async_openai`]'s.
+//!
+//! Enabled with the `openai` feature. Both sides model the same OpenAI-compatible wire
+//! format, so conversions go through JSON: fields that only exist on one side (such as
+//! OpenRouter's `provider`, `models`, and `transforms`) are dropped, and shapes that cannot
+//! be represented on the other side yield an [`Error::ConversionError`].
+
+use crate::error::{Error, Result};
+use crate::models::parameters::SamplingParams;
+use crate::types::chat::{ChatCompletionRequest, ChatCompletionResponse, Message};
+use async_openai::types::chat::{
+    ChatCompletionRequestMessage, CreateChatCompletionRequest, CreateChatCompletionResponse,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Request fields that are OpenRouter extensions and unknown to the OpenAI types.
+const OPENROUTER_ONLY_FIELDS: &[&str] = &[
+    "provider",
+    "models",
+    "route",
+    "transforms",
+    "top_k",
+    "repetition_penalty",
+];
+
+fn to_json<T: Serialize>(value: &T) -> Result<Value> {
+    serde_json::to_value(value).map_err(|e| Error::ConversionError(e.to_string()))
+}
+
+fn from_json<T: DeserializeOwned>(value: Value) -> Result<T> {
+    serde_json::from_value(value).map_err(|e| Error::ConversionError(e.to_string()))
+}
+
+/// Converts an optional field between the crates' equivalent types via their JSON form.
+fn convert<T: Serialize, U: DeserializeOwned>(value: &Option<T>) -> Result<Option<U>> {
+    match value {
+        Some(value) => Ok(Some(from_json(to_json(value)?)?)),
+        None => Ok(None),
+    }
+}
+
+/// OpenAI messages may omit `content` (e.g. assistant tool calls); this crate requires it.
+fn fill_missing_content(message: &mut Value) {
+    if let Value::Object(map) = message {
+        if map.get("content").is_none_or(Value::is_null) {
+            map.insert("content".into(), Value::String(String::new()));
+        }
+    }
+}
+
+impl TryFrom<ChatCompletionRequestMessage> for Message {
+    type Error = Error;
+
+    fn try_from(message: ChatCompletionRequestMessage) -> Result<Self> {
+        let mut value = to_json(&message)?;
+        fill_missing_content(&mut value);
+        from_json(value)
+    }
+}
+
+impl TryFrom<Message> for ChatCompletionRequestMessage {
+    type Error = Error;
+
+    fn try_from(message: Message) -> Result<Self> {
+        from_json(to_json(&message)?)
+    }
+}
+
+impl TryFrom<CreateChatCompletionRequest> for ChatCompletionRequest {
+    type Error = Error;
+
+    /// Converts the model, messages, streaming flag, tools, response format, predicted
+    /// output, modalities, sampling parameters, and token limits.
+    /// Other parameters not modeled by [`ChatCompletionRequest`] are dropped.
+    fn try_from(request: CreateChatCompletionRequest) -> Result<Self> {
+        let tools = convert(&request.tools)?;
+        let response_format = convert(&request.response_format)?;
+        let prediction = convert(&request.prediction)?;
+        let modalities = convert(&request.modalities)?;
+        Ok(ChatCompletionRequest {
+            model: request.model,
+            messages: request
+                .messages
+                .into_iter()
+                .map(Message::try_from)
+                .collect::<Result<_>>()?,
+            stream: request.stream,
+            response_format,
+            tools,
+            sampling: SamplingParams {
+                temperature: request.temperature.map(f64::from),
+                top_p: request.top_p.map(f64::from),
+                frequency_penalty: request.frequency_penalty.map(f64::from),
+                presence_penalty: request.presence_penalty.map(f64::from),
+                #[allow(deprecated)]
+                seed: request.seed,
+                ..Default::default()
+            },
+            #[allow(deprecated)]
+            max_tokens: request.max_tokens,
+            max_completion_tokens: request.max_completion_tokens,
+            prediction,
+            modalities,
+            ..Default::default()
+        })
+    }
+}
+
+impl TryFrom<ChatCompletionRequest> for CreateChatCompletionRequest {
+    type Error = Error;
+
+    fn try_from(request: ChatCompletionRequest) -> Result<Self> {
+        let mut value = to_json(&request)?;
+        if let Value::Object(ref mut map) = value {
+            for field in OPENROUTER_ONLY_FIELDS {
+                map.remove(*field);
+            }
+        }
+        from_json(value)
+    }
+}
+
+impl TryFrom<CreateChatCompletionResponse> for ChatCompletionResponse {
+    type Error = Error;
+
+    fn try_from(response: CreateChatCompletionResponse) -> Result<Self> {
+        let mut value = to_json(&response)?;
+        if let Some(Value::Array(choices)) = value.get_mut("choices") {
+            for choice in choices {
+                if let Some(message) = choice.get_mut("message") {
+                    fill_missing_content(message);
+                }
+            }
+        }
+        from_json(value)
+    }
+}
+
+impl TryFrom<ChatCompletionResponse> for CreateChatCompletionResponse {
+    type Error = Error;
+
+    fn try_from(response: ChatCompletionResponse) -> Result<Self> {
+        let mut value = to_json(&response)?;
+        if let Value::Object(ref mut map) = value {
+            map.insert("object".into(), Value::String("chat.completion".into()));
+            if let Some(Value::Array(choices)) = map.get_mut("choices") {
+                for (index, choice) in choices.iter_mut().enumerate() {
+                    if let Value::Object(choice) = choice {
+                        choice.remove("native_finish_reason");
+                        choice.entry("index").or_insert(Value::from(index));
+                    }
+                }
+            }
+        }
+        from_json(value)
+    }
+}
@@ -0,0 +1,107 @@
+//! Routing types for OpenRouter's Auto Router.
+//!
+//! Requesting the [`AUTO_ROUTER_MODEL`] lets OpenRouter pick a model for each prompt. The
+//! response then names the model (and provider) that actually served it, which [`Routing`]
+//! captures alongside whether the choice was explicit or made by the Auto Router.
+
+use super::chat::{ChatCompletionRequest, ChatCompletionResponse, Message};
+use serde::Serialize;
+use std::fmt;
+
+/// Model id of the OpenRouter Auto Router.
+pub const AUTO_ROUTER_MODEL: &str = "openrouter/auto";
+
+/// Returns true if `model` is the Auto Router.
+pub fn is_auto_router(model: &str) -> bool {
+    model == AUTO_ROUTER_MODEL
+}
+
+/// How the model that served a request was chosen.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "routing", rename_all = "snake_case")]
+pub enum Routing {
+    /// The request named the model itself.
+    Explicit {
+        /// The model that served the request.
+        model: String,
+        /// The provider that served the request, if reported.
+        provider: Option<String>,
+    },
+    /// The Auto Router picked the model.
+    Auto {
+        /// The model the Auto Router chose.
+        model: String,
+        /// The provider that served the request, if reported.
+        provider: Option<String>,
+    },
+}
+
+impl Routing {
+    /// Describes how `response` was routed, given the model that was requested.
+    pub fn of(requested_model: &str, response: &ChatCompletionResponse) -> Self {
+        let model = response.model.clone();
+        let provider = response.provider.clone();
+        if is_auto_router(requested_model) {
+            Routing::Auto { model, provider }
+        } else {
+            Routing::Explicit { model, provider }
+        }
+    }
+
+    /// Returns true if the Auto Router picked the model.
+    pub fn is_auto(&self) -> bool {
+        matches!(self, Routing::Auto { .. })
+    }
+
+    /// The model that served the request.
+    pub fn model(&self) -> &str {
+        match self {
+            Routing::Explicit { model, .. } | Routing::Auto { model, .. } => model,
+        }
+    }
+
+    /// The provider that served the request, if reported.
+    pub fn provider(&self) -> Option<&str> {
+        match self {
+            Routing::Explicit { provider, .. } | Routing::Auto { provider, .. } => {
+                provider.as_deref()
+            }
+        }
+    }
+}
+
+impl fmt::Display for Routing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_auto() {
+            write!(f, "{} -> ", AUTO_ROUTER_MODEL)?;
+        }
+        f.write_str(self.model())?;
+        if let Some(provider) = self.provider() {
+            write!(f, " via {}", provider)?;
+        }
+        Ok(())
+    }
+}
+
+impl ChatCompletionRequest {
+    /// Creates a request that lets the Auto Router pick the model.
+    pub fn auto(messages: Vec<Message>) -> Self {
+        Self {
+            model: AUTO_ROUTER_MODEL.to_string(),
+            messages,
+            ..Default::default()
+        }
+    }
+
+    /// Returns true if this request is sent to the Auto Router.
+    pub fn is_auto_routed(&self) -> bool {
+        is_auto_router(&self.model)
+    }
+}
+
+impl ChatCompletionResponse {
+    /// Describes how this response was routed, given the model that was requested.
+    pub fn routing(&self, requested_model: &str) -> Routing {
+        Routing::of(requested_model, self)
+    }
+}
@@ -0,0 +1,11 @@
+//! Stable, non-cryptographic hashing.
+//!
+//! Used where a short fingerprint must be reproducible across processes and Rust versions,
+//! such as replay cassette names and audit prompt hashes.
+
+/// Computes the 64-bit FNV-1a hash of `bytes`.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
@@ -0,0 +1,90 @@
+//! Best-effort repair of almost-valid JSON emitted by models.
+//!
+//! Handles the common failure modes: Markdown code fences or prose around the document,
+//! trailing commas, and output truncated before strings, arrays, or objects were closed.
+
+/// Repairs common defects in model-generated JSON.
+///
+/// The result is not guaranteed to be valid JSON; it is the input with code fences and
+/// surrounding prose stripped, trailing commas removed, and unclosed strings and brackets
+/// closed.
+pub fn repair_json(text: &str) -> String {
+    let text = strip_code_fence(text.trim());
+    let start = text.find(['{', '[']).unwrap_or(0);
+    let text = &text[start..];
+
+    let mut output = String::with_capacity(text.len() + 8);
+    let mut closers: Vec<char> = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for c in text.chars() {
+        if in_string {
+            output.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => closers.push('}'),
+            '[' => closers.push(']'),
+            '}' | ']' => {
+                remove_trailing_comma(&mut output);
+                if closers.last() == Some(&c) {
+                    closers.pop();
+                }
+            }
+            _ => {}
+        }
+        output.push(c);
+        if closers.is_empty() && matches!(c, '}' | ']') {
+            // Ignore anything after the end of the document.
+            break;
+        }
+    }
+
+    if in_string {
+        if escaped {
+            output.pop();
+        }
+        output.push('"');
+    }
+    let trimmed = output.trim_end().len();
+    output.truncate(trimmed);
+    if output.ends_with(':') {
+        output.push_str("null");
+    }
+    while let Some(closer) = closers.pop() {
+        remove_trailing_comma(&mut output);
+        output.push(closer);
+    }
+    output
+}
+
+/// Removes a Markdown code fence (with optional language tag) around `text`, including a
+/// fence on a single line such as ```` ```{"a":1}``` ````.
+fn strip_code_fence(text: &str) -> &str {
+    let Some(rest) = text.strip_prefix("```") else {
+        return text;
+    };
+    let body = match rest.split_once('\n') {
+        Some((_, body)) => body,
+        None => rest.strip_prefix("json").unwrap_or(rest),
+    };
+    body.trim_end().strip_suffix("```").unwrap_or(body).trim()
+}
+
+/// Drops a comma (and whitespace after it) at the end of `output`.
+fn remove_trailing_comma(output: &mut String) {
+    let trimmed = output.trim_end();
+    if trimmed.ends_with(',') {
+        let len = trimmed.len() - 1;
+        output.truncate(len);
+    }
+}
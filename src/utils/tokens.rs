@@ -0,0 +1,58 @@
+//! Lightweight token estimation.
+//!
+//! These estimates use the common "about four characters per token" heuristic plus a
+//! small per-message overhead. They are intentionally tokenizer-agnostic: good enough
+//! for budgeting and truncation decisions, not for exact billing.
+
+use crate::types::chat::{Content, ContentPart, ImageDetail, Message};
+
+/// Approximate number of characters per token for typical English text.
+pub(crate) const CHARS_PER_TOKEN: usize = 4;
+/// Tokens added per message for role and formatting markers.
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+/// Flat estimate for an image content part.
+const IMAGE_TOKENS: usize = 85;
+/// Estimate for a high-detail image, assuming a typical four-tile image.
+const HIGH_DETAIL_IMAGE_TOKENS: usize = 765;
+
+/// Estimates the number of tokens in a piece of text.
+pub fn estimate_tokens(text: &str) -> usize {
+    estimate_tokens_for_chars(text.chars().count())
+}
+
+/// Estimates the number of tokens in a text of `chars` characters.
+pub(crate) fn estimate_tokens_for_chars(chars: usize) -> usize {
+    chars.div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Estimates the number of tokens a single message contributes to a prompt.
+pub fn estimate_message_tokens(message: &Message) -> usize {
+    let content_tokens = match &message.content {
+        Content::Text(text) => estimate_tokens(text),
+        Content::Parts(parts) => parts
+            .iter()
+            .map(|part| match part {
+                ContentPart::Text { text } => estimate_tokens(text),
+                ContentPart::ImageUrl { image_url } => match image_url.detail {
+                    Some(ImageDetail::High) => HIGH_DETAIL_IMAGE_TOKENS,
+                    _ => IMAGE_TOKENS,
+                },
+            })
+            .sum(),
+    };
+    let tool_call_tokens: usize = message
+        .tool_calls
+        .iter()
+        .flatten()
+        .map(|tc| {
+            estimate_tokens(&tc.function_call.name) + estimate_tokens(&tc.function_call.arguments)
+        })
+        .sum();
+    let name_tokens = message.name.as_deref().map(estimate_tokens).unwrap_or(0);
+    MESSAGE_OVERHEAD_TOKENS + content_tokens + tool_call_tokens + name_tokens
+}
+
+/// Estimates the number of prompt tokens for a list of messages.
+pub fn estimate_messages_tokens(messages: &[Message]) -> usize {
+    messages.iter().map(estimate_message_tokens).sum()
+}